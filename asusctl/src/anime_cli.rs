@@ -21,6 +21,8 @@ pub struct AnimeCommand {
     pub image_brightness: Option<f32>,
     #[options(help = "clear the display")]
     pub clear: bool,
+    #[options(help = "show the display state")]
+    pub get_state: bool,
     #[options(command)]
     pub command: Option<AnimeActions>,
 }
@@ -0,0 +1,535 @@
+//! `asusctl apply`, sets the laptop to the state described in a file. The
+//! difference to the current state is worked out first so that only what
+//! differs is changed, and with `--dry-run` nothing is.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use gumdrop::Options;
+use rog_anime::usb::Brightness;
+use rog_anime::{Animations, DeviceState as AnimeDeviceState};
+use rog_aura::usb::{AuraDevice, AuraPowerDev};
+use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+use rog_dbus::RogDbusClientBlocking;
+use rog_platform::supported::SupportedFunctions;
+use rog_profiles::fan_curve_set::CurveData;
+use rog_profiles::Profile;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Options)]
+pub struct ApplyCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "only print the changes that would be made")]
+    pub dry_run: bool,
+    #[options(free, help = "the state file")]
+    pub file: String,
+}
+
+/// The wanted state of the laptop. Every section is optional and anything left
+/// out is not changed. Values may be written without `Some()`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+    pub profile: Option<Profile>,
+    /// Curves for each fan, only the fans listed are changed
+    pub fan_curves: BTreeMap<Profile, Vec<CurveData>>,
+    pub chg_limit: Option<u8>,
+    pub led: Option<LedState>,
+    pub panel_od: Option<bool>,
+    pub anime: Option<AnimeState>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LedState {
+    pub mode: Option<AuraEffect>,
+    pub brightness: Option<LedBrightness>,
+    /// The `tuf` and `old_rog` lists are the parts which are enabled
+    pub power: Option<AuraPowerDev>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnimeState {
+    pub display_enabled: Option<bool>,
+    pub brightness: Option<Brightness>,
+    pub builtins_enabled: Option<bool>,
+    pub builtin_anims: Option<Animations>,
+}
+
+impl State {
+    pub fn parse(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(text)
+    }
+}
+
+/// The current state of the sections in a [`State`], `None` if the laptop
+/// doesn't support that section
+#[derive(Debug, Default)]
+struct Live {
+    profile: Option<Profile>,
+    fan_curves: Option<BTreeMap<Profile, Vec<CurveData>>>,
+    chg_limit: Option<u8>,
+    led_modes: Option<(AuraEffect, Vec<AuraModeNum>)>,
+    led_brightness: Option<LedBrightness>,
+    led_power: Option<AuraPowerDev>,
+    panel_od: Option<bool>,
+    anime: Option<AnimeDeviceState>,
+}
+
+impl Live {
+    /// Read only what `state` will change
+    fn read(
+        dbus: &RogDbusClientBlocking<'_>,
+        supported: &SupportedFunctions,
+        state: &State,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut live = Self::default();
+        let proxies = dbus.proxies();
+        if state.profile.is_some() && supported.platform_profile.platform_profile {
+            live.profile = Some(proxies.profile().active_profile()?);
+        }
+        if !state.fan_curves.is_empty() && !supported.platform_profile.fans.is_empty() {
+            let mut curves = BTreeMap::new();
+            let profiles = proxies.profile().profiles()?;
+            for profile in state.fan_curves.keys().filter(|p| profiles.contains(p)) {
+                curves.insert(*profile, proxies.profile().fan_curve_data(*profile)?);
+            }
+            live.fan_curves = Some(curves);
+        }
+        if state.chg_limit.is_some() && supported.charge_ctrl.charge_level_set {
+            live.chg_limit = Some(proxies.charge().charge_control_end_threshold()?);
+        }
+        if let Some(led) = &state.led {
+            let kbd = &supported.keyboard_led;
+            if led.mode.is_some() && !kbd.basic_modes.is_empty() {
+                let current = proxies.led().led_mode()?;
+                if let Some(effect) = proxies.led().led_modes()?.remove(&current) {
+                    live.led_modes = Some((effect, kbd.basic_modes.clone()));
+                }
+            }
+            if led.brightness.is_some() && kbd.brightness {
+                let level = proxies.led().led_brightness()?;
+                live.led_brightness = Some(LedBrightness::from(level as u32));
+            }
+            if led.power.is_some() && kbd.dev_id != AuraDevice::Unknown {
+                live.led_power = Some(proxies.led().led_power()?);
+            }
+        }
+        if state.panel_od.is_some() && supported.rog_bios_ctrl.panel_overdrive {
+            live.panel_od = Some(proxies.rog_bios().panel_od()?);
+        }
+        if state.anime.is_some() && supported.anime_ctrl.0 {
+            live.anime = Some(proxies.anime().device_state()?);
+        }
+        Ok(live)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    FanCurve(Profile, CurveData),
+    Profile(Profile),
+    ChgLimit(u8),
+    LedMode(AuraEffect),
+    LedBrightness(LedBrightness),
+    /// The parts to enable, and those to disable
+    LedPower(AuraPowerDev, AuraPowerDev),
+    PanelOd(bool),
+    AnimeDisplay(bool),
+    AnimeBrightness(Brightness),
+    AnimeBuiltinsEnabled(bool),
+    AnimeBuiltins(Animations),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub what: String,
+    pub from: String,
+    pub to: String,
+    action: Action,
+}
+
+impl Change {
+    fn new(what: impl Into<String>, from: String, to: String, action: Action) -> Self {
+        Self {
+            what: what.into(),
+            from,
+            to,
+            action,
+        }
+    }
+
+    fn apply(&self, dbus: &RogDbusClientBlocking<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        let proxies = dbus.proxies();
+        match &self.action {
+            Action::FanCurve(profile, curve) => {
+                proxies.profile().set_fan_curve(*profile, curve.clone())?;
+            }
+            Action::Profile(profile) => proxies.profile().set_active_profile(*profile)?,
+            Action::ChgLimit(limit) => proxies.charge().set_charge_control_end_threshold(*limit)?,
+            Action::LedMode(effect) => proxies.led().set_led_mode(effect)?,
+            Action::LedBrightness(level) => proxies.led().set_brightness(*level)?,
+            Action::LedPower(enable, disable) => {
+                proxies.led().set_led_power(enable.clone(), true)?;
+                if !disable.tuf.is_empty() || !disable.old_rog.is_empty() {
+                    proxies.led().set_led_power(disable.clone(), false)?;
+                }
+            }
+            Action::PanelOd(on) => proxies.rog_bios().set_panel_od(*on)?,
+            Action::AnimeDisplay(on) => proxies.anime().set_enable_display(*on)?,
+            Action::AnimeBrightness(level) => proxies.anime().set_brightness(*level)?,
+            Action::AnimeBuiltinsEnabled(on) => proxies.anime().set_builtins_enabled(*on)?,
+            Action::AnimeBuiltins(anims) => proxies.anime().set_builtin_animations(
+                anims.boot,
+                anims.awake,
+                anims.sleep,
+                anims.shutdown,
+            )?,
+        }
+        Ok(())
+    }
+}
+
+/// What `apply` will change, in the order it is done, and the sections which
+/// will be skipped with the reason
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+    pub skipped: Vec<String>,
+}
+
+impl Plan {
+    fn changed<T: PartialEq + std::fmt::Debug>(
+        &mut self,
+        what: &str,
+        from: &T,
+        to: &T,
+        action: Action,
+    ) {
+        if from != to {
+            self.changes.push(Change::new(
+                what,
+                format!("{from:?}"),
+                format!("{to:?}"),
+                action,
+            ));
+        }
+    }
+
+    fn skip(&mut self, what: &str) {
+        self.skipped
+            .push(format!("{what} is not supported by this laptop"));
+    }
+
+    /// Fan curves go first so a profile switch applies the new ones
+    fn new(state: &State, live: &Live) -> Self {
+        let mut plan = Self::default();
+
+        if !state.fan_curves.is_empty() {
+            match &live.fan_curves {
+                Some(curves) => {
+                    for (profile, wanted) in &state.fan_curves {
+                        let current = match curves.get(profile) {
+                            Some(current) => current,
+                            None => {
+                                plan.skip(&format!("fan curves for {profile:?}"));
+                                continue;
+                            }
+                        };
+                        for curve in wanted {
+                            let what = format!("fan curve {profile:?} {:?}", curve.fan);
+                            match current.iter().find(|c| c.fan == curve.fan) {
+                                Some(c) if c == curve => {}
+                                Some(c) => plan.changes.push(Change::new(
+                                    what,
+                                    String::from(c),
+                                    String::from(curve),
+                                    Action::FanCurve(*profile, curve.clone()),
+                                )),
+                                None => plan.skip(&what),
+                            }
+                        }
+                    }
+                }
+                None => plan.skip("fan curves"),
+            }
+        }
+
+        if let Some(profile) = state.profile {
+            match live.profile {
+                Some(current) => {
+                    plan.changed("profile", &current, &profile, Action::Profile(profile));
+                }
+                None => plan.skip("profile"),
+            }
+        }
+
+        if let Some(limit) = state.chg_limit {
+            match live.chg_limit {
+                Some(current) => {
+                    plan.changed("charge limit", &current, &limit, Action::ChgLimit(limit));
+                }
+                None => plan.skip("charge limit"),
+            }
+        }
+
+        if let Some(led) = &state.led {
+            plan.led(led, live);
+        }
+
+        if let Some(on) = state.panel_od {
+            match live.panel_od {
+                Some(current) => {
+                    plan.changed("panel overdrive", &current, &on, Action::PanelOd(on))
+                }
+                None => plan.skip("panel overdrive"),
+            }
+        }
+
+        if let Some(anime) = &state.anime {
+            match &live.anime {
+                Some(current) => plan.anime(anime, current),
+                None => plan.skip("anime"),
+            }
+        }
+
+        plan
+    }
+
+    fn led(&mut self, led: &LedState, live: &Live) {
+        if let Some(effect) = &led.mode {
+            match &live.led_modes {
+                Some((_, modes)) if !modes.contains(&effect.mode) => {
+                    self.skip(&format!("led mode {:?}", effect.mode));
+                }
+                Some((current, _)) => {
+                    self.changed("led mode", current, effect, Action::LedMode(effect.clone()));
+                }
+                None => self.skip("led mode"),
+            }
+        }
+
+        if let Some(level) = led.brightness {
+            match live.led_brightness {
+                Some(current) => self.changed(
+                    "led brightness",
+                    &current,
+                    &level,
+                    Action::LedBrightness(level),
+                ),
+                None => self.skip("led brightness"),
+            }
+        }
+
+        if let Some(power) = &led.power {
+            match &live.led_power {
+                Some(current) => {
+                    let disable = AuraPowerDev {
+                        tuf: current
+                            .tuf
+                            .iter()
+                            .filter(|p| !power.tuf.contains(p))
+                            .copied()
+                            .collect(),
+                        old_rog: current
+                            .old_rog
+                            .iter()
+                            .filter(|p| !power.old_rog.contains(p))
+                            .copied()
+                            .collect(),
+                        rog: power.rog.clone(),
+                    };
+                    let same = disable.tuf.is_empty()
+                        && disable.old_rog.is_empty()
+                        && power.tuf.iter().all(|p| current.tuf.contains(p))
+                        && power.old_rog.iter().all(|p| current.old_rog.contains(p))
+                        && power.rog == current.rog;
+                    if !same {
+                        self.changes.push(Change::new(
+                            "led power",
+                            format!("{current:?}"),
+                            format!("{power:?}"),
+                            Action::LedPower(power.clone(), disable),
+                        ));
+                    }
+                }
+                None => self.skip("led power"),
+            }
+        }
+    }
+
+    fn anime(&mut self, anime: &AnimeState, current: &AnimeDeviceState) {
+        if let Some(on) = anime.display_enabled {
+            self.changed(
+                "anime display",
+                &current.display_enabled,
+                &on,
+                Action::AnimeDisplay(on),
+            );
+        }
+        if let Some(level) = anime.brightness {
+            self.changed(
+                "anime brightness",
+                &current.display_brightness,
+                &level,
+                Action::AnimeBrightness(level),
+            );
+        }
+        if let Some(on) = anime.builtins_enabled {
+            self.changed(
+                "anime builtins enabled",
+                &current.builtin_anims_enabled,
+                &on,
+                Action::AnimeBuiltinsEnabled(on),
+            );
+        }
+        if let Some(anims) = anime.builtin_anims {
+            self.changed(
+                "anime builtin animations",
+                &current.builtin_anims,
+                &anims,
+                Action::AnimeBuiltins(anims),
+            );
+        }
+    }
+}
+
+pub fn apply(
+    dbus: &RogDbusClientBlocking<'_>,
+    supported: &SupportedFunctions,
+    cmd: &ApplyCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(&cmd.file)?;
+    let state = State::parse(&text)?;
+    let live = Live::read(dbus, supported, &state)?;
+    let plan = Plan::new(&state, &live);
+
+    for skipped in &plan.skipped {
+        println!("Skipping: {skipped}");
+    }
+    if plan.changes.is_empty() {
+        println!("Nothing to change");
+        return Ok(());
+    }
+    for change in &plan.changes {
+        println!("{}: {} -> {}", change.what, change.from, change.to);
+    }
+    if cmd.dry_run {
+        println!("Dry run, nothing was changed");
+        return Ok(());
+    }
+    // Carry on past a failed change so that the rest are still made
+    let mut failed = Vec::new();
+    for change in &plan.changes {
+        match change.apply(dbus) {
+            Ok(()) => println!("Applied {}", change.what),
+            Err(e) => {
+                eprintln!("Failed {}: {e}", change.what);
+                failed.push(change.what.as_str());
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!(
+            "{} of {} changes failed: {}",
+            failed.len(),
+            plan.changes.len(),
+            failed.join(", ")
+        )
+        .into());
+    }
+    println!("Applied {} changes", plan.changes.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example in MANUAL.md
+    const EXAMPLE: &str = r#"(
+    profile: Quiet,
+    fan_curves: {
+        Quiet: [
+            (
+                fan: CPU,
+                pwm: (0, 0, 25, 50, 75, 125, 200, 255),
+                temp: (30, 40, 50, 60, 70, 80, 90, 100),
+                enabled: true,
+            ),
+        ],
+    },
+    chg_limit: 80,
+    led: (
+        mode: (
+            mode: Static,
+            zone: None,
+            colour1: (r: 255, g: 0, b: 0),
+            colour2: (r: 0, g: 0, b: 0),
+            speed: Med,
+            direction: Right,
+        ),
+        brightness: Low,
+    ),
+    panel_od: true,
+    anime: (
+        display_enabled: true,
+        brightness: Low,
+        builtin_anims: (
+            boot: GlitchConstruction,
+            awake: BinaryBannerScroll,
+            sleep: BannerSwipe,
+            shutdown: GlitchOut,
+        ),
+    ),
+)"#;
+
+    const STATE: &str = r#"(
+        profile: Quiet,
+        fan_curves: {
+            Quiet: [(fan: CPU, pwm: (0, 0, 0, 0, 0, 0, 0, 0), temp: (30, 40, 50, 60, 70, 80, 90, 100), enabled: true)],
+        },
+        chg_limit: 80,
+        panel_od: true,
+    )"#;
+
+    #[test]
+    fn parse_manual_example() {
+        let state = State::parse(EXAMPLE).unwrap();
+        assert_eq!(state.profile, Some(Profile::Quiet));
+        let led = state.led.unwrap();
+        assert_eq!(led.mode.unwrap().mode, AuraModeNum::Static);
+        assert_eq!(led.brightness, Some(LedBrightness::Low));
+        assert!(led.power.is_none());
+        assert_eq!(state.anime.unwrap().brightness, Some(Brightness::Low));
+    }
+
+    #[test]
+    fn plan_only_differences() {
+        let state = State::parse(STATE).unwrap();
+        let mut current = state.fan_curves[&Profile::Quiet][0].clone();
+        current.enabled = false;
+        let live = Live {
+            profile: Some(Profile::Balanced),
+            fan_curves: Some(BTreeMap::from([(Profile::Quiet, vec![current])])),
+            chg_limit: Some(80),
+            ..Default::default()
+        };
+
+        let plan = Plan::new(&state, &live);
+        let what: Vec<&str> = plan.changes.iter().map(|c| c.what.as_str()).collect();
+        assert_eq!(what, ["fan curve Quiet CPU", "profile"]);
+        assert_eq!(
+            plan.changes[0].action,
+            Action::FanCurve(Profile::Quiet, state.fan_curves[&Profile::Quiet][0].clone())
+        );
+        assert_eq!(
+            plan.skipped,
+            ["panel overdrive is not supported by this laptop"]
+        );
+    }
+}
@@ -18,12 +18,16 @@ pub struct LedPowerCommand1 {
     pub boot: Option<bool>,
     #[options(meta = "", help = "Control suspend animations <true/false>")]
     pub sleep: Option<bool>,
+    #[options(help = "show the current LED power states")]
+    pub get: bool,
 }
 
 #[derive(Options, Debug)]
 pub struct LedPowerCommand2 {
     #[options(help = "print help message")]
     pub help: bool,
+    #[options(help = "show the current LED power states")]
+    pub get: bool,
     #[options(command)]
     pub command: Option<SetAuraZoneEnabled>,
 }
@@ -0,0 +1,396 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use gumdrop::Options;
+use rog_aura::advanced::{LedCode, LedUsbPackets};
+use rog_aura::aura_detection::{
+    LaptopLedData, LedUserFile, PowerZones, ASUS_KEYBOARD_DEVICES, ASUS_LED_MODE_USER_CONF,
+};
+use rog_aura::power::AuraPower;
+use rog_aura::usb::{AuraDevice, LED_APPLY, LED_SET};
+use rog_aura::{
+    AdvancedAuraType, AuraEffect, AuraModeNum, AuraZone, Colour, BLUE, GREEN, LED_MSG_LEN, RED,
+    YELLOW,
+};
+use rog_platform::error::PlatformError;
+use rog_platform::hid_raw::HidRaw;
+use rog_platform::keyboard_led::KeyboardLed;
+
+/// Every mode a controller might accept, in the order they are tried
+const MODES: [AuraModeNum; 12] = [
+    AuraModeNum::Static,
+    AuraModeNum::Breathe,
+    AuraModeNum::Strobe,
+    AuraModeNum::Rainbow,
+    AuraModeNum::Star,
+    AuraModeNum::Rain,
+    AuraModeNum::Highlight,
+    AuraModeNum::Laser,
+    AuraModeNum::Ripple,
+    AuraModeNum::Pulse,
+    AuraModeNum::Comet,
+    AuraModeNum::Flash,
+];
+/// Zones of the `b3` builtin mode packet, with where to look for them
+const ZONES: [(AuraZone, &str); 7] = [
+    (AuraZone::Key1, "leftmost quarter of the keyboard"),
+    (AuraZone::Key2, "second quarter of the keyboard"),
+    (AuraZone::Key3, "third quarter of the keyboard"),
+    (AuraZone::Key4, "rightmost quarter of the keyboard"),
+    (AuraZone::Logo, "logo on the lid"),
+    (AuraZone::BarLeft, "left half of the lightbar"),
+    (AuraZone::BarRight, "right half of the lightbar"),
+];
+/// Power zones other than the keyboard, with where to look for them
+const POWER_ZONES: [(PowerZones, &str); 4] = [
+    (PowerZones::Logo, "logo on the lid"),
+    (PowerZones::Lightbar, "lightbar on the front or sides"),
+    (PowerZones::Lid, "LEDs around the edge of the lid"),
+    (PowerZones::RearGlow, "LED strip on the rear"),
+];
+const WHITE: Colour = Colour {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+const OFF: Colour = Colour { r: 0, g: 0, b: 0 };
+
+#[derive(Options)]
+pub struct AuraProbeCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "",
+        help = "match on this instead of the full DMI board_name, such as GU603Z"
+    )]
+    pub board_name: Option<String>,
+    #[options(help = "add the entry to /etc/asusd/asusd_user_ledmodes.ron")]
+    pub install: bool,
+    #[options(no_short, help = "run even though asusd is running")]
+    pub force: bool,
+}
+
+/// USB vendor ID of ASUS
+const ASUS_VENDOR: &str = "0b05";
+/// ASUS USB devices that are known not to be keyboards
+const NOT_KEYBOARDS: [&str; 1] = [
+    // AniMe Matrix
+    "193b",
+];
+
+/// The keyboard as asusd would find it, or an ASUS USB device asusd doesn't
+/// know of yet
+enum Keyboard {
+    Rog(AuraDevice, HidRaw),
+    /// Product ID and node of a device not in `ASUS_KEYBOARD_DEVICES`
+    Unknown(String, HidRaw),
+    Tuf(KeyboardLed),
+}
+
+impl Keyboard {
+    fn find() -> Result<Self, PlatformError> {
+        for prod in ASUS_KEYBOARD_DEVICES {
+            if prod == AuraDevice::Tuf {
+                continue;
+            }
+            if let Ok(node) = HidRaw::new(prod.into()) {
+                return Ok(Self::Rog(prod, node));
+            }
+        }
+        if let Ok(led) = KeyboardLed::new() {
+            if led.has_kbd_rgb_mode() {
+                return Ok(Self::Tuf(led));
+            }
+        }
+        // A keyboard with a product ID asusd doesn't have yet
+        let known: Vec<&str> = ASUS_KEYBOARD_DEVICES
+            .into_iter()
+            .map(<&str>::from)
+            .collect();
+        HidRaw::find_vendor(ASUS_VENDOR)?
+            .into_iter()
+            .find(|(prod, _)| {
+                !known.contains(&prod.as_str()) && !NOT_KEYBOARDS.contains(&prod.as_str())
+            })
+            .map(|(prod, node)| Self::Unknown(prod, node))
+            .ok_or_else(|| {
+                PlatformError::MissingFunction("no ASUS USB keyboard or kbd_rgb_mode".to_owned())
+            })
+    }
+
+    fn device(&self) -> AuraDevice {
+        match self {
+            Self::Rog(prod, _) => *prod,
+            Self::Unknown(..) => AuraDevice::Unknown,
+            Self::Tuf(_) => AuraDevice::Tuf,
+        }
+    }
+
+    /// Such as `0b05:19b6`, or `kbd_rgb_mode` for TUF
+    fn id(&self) -> String {
+        match self {
+            Self::Rog(prod, _) => format!("{ASUS_VENDOR}:{}", <&str>::from(*prod)),
+            Self::Unknown(prod, _) => format!("{ASUS_VENDOR}:{prod}"),
+            Self::Tuf(_) => "kbd_rgb_mode".to_owned(),
+        }
+    }
+
+    fn hid_raw(&self) -> Option<&HidRaw> {
+        match self {
+            Self::Rog(_, hid_raw) | Self::Unknown(_, hid_raw) => Some(hid_raw),
+            Self::Tuf(_) => None,
+        }
+    }
+
+    /// Newer keyboards have the 4 byte power packet with per-zone bits, a
+    /// keyboard newer than asusd is assumed to as well
+    fn has_power_zones(&self) -> bool {
+        matches!(
+            self.device(),
+            AuraDevice::X18c6 | AuraDevice::X19b6 | AuraDevice::X1a30 | AuraDevice::Unknown
+        )
+    }
+
+    /// Write as asusd does, including set and apply
+    fn write_effect(&self, effect: &AuraEffect) -> Result<(), PlatformError> {
+        match self {
+            Self::Rog(_, hid_raw) | Self::Unknown(_, hid_raw) => {
+                let bytes: [u8; LED_MSG_LEN] = effect.into();
+                hid_raw.write_bytes(&bytes)?;
+                hid_raw.write_bytes(&LED_SET)?;
+                hid_raw.write_bytes(&LED_APPLY)
+            }
+            Self::Tuf(led) => led.set_kbd_rgb_mode(&[
+                1,
+                effect.mode as u8,
+                effect.colour1.r,
+                effect.colour1.g,
+                effect.colour1.b,
+                effect.speed as u8,
+            ]),
+        }
+    }
+
+    fn write_power(&self, power: &AuraPower) -> Result<(), PlatformError> {
+        if let Some(hid_raw) = self.hid_raw() {
+            let bytes = power.to_bytes();
+            hid_raw.write_bytes(&[0x5d, 0xbd, 0x01, bytes[0], bytes[1], bytes[2], bytes[3]])?;
+            hid_raw.write_bytes(&LED_SET)?;
+            hid_raw.write_bytes(&LED_APPLY)?;
+        }
+        Ok(())
+    }
+
+    fn write_packets(&self, packets: &LedUsbPackets) -> Result<(), PlatformError> {
+        if let Some(hid_raw) = self.hid_raw() {
+            hid_raw.write_bytes(&LedUsbPackets::get_init_msg())?;
+            for packet in packets.get_ref() {
+                hid_raw.write_bytes(packet)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ask a yes/no question, no if stdin is closed
+fn confirm(question: &str) -> bool {
+    print!("{question} [y/N] ");
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn static_colour(zone: AuraZone, colour: Colour) -> AuraEffect {
+    AuraEffect {
+        zone,
+        colour1: colour,
+        ..Default::default()
+    }
+}
+
+fn probe_modes(keyboard: &Keyboard) -> Result<Vec<AuraModeNum>, PlatformError> {
+    println!("\nEach mode is shown in turn. Answer no if the keyboard is unchanged or wrong.");
+    let mut modes = Vec::new();
+    for mode in MODES {
+        // Off first so a rejected mode can't be mistaken for the last one
+        keyboard.write_effect(&static_colour(AuraZone::None, OFF))?;
+        keyboard.write_effect(&AuraEffect {
+            mode,
+            colour1: RED,
+            colour2: BLUE,
+            ..Default::default()
+        })?;
+        if confirm(&format!("Is the keyboard showing {mode}?")) {
+            modes.push(mode);
+        }
+    }
+    Ok(modes)
+}
+
+fn probe_zones(keyboard: &Keyboard) -> Result<Vec<AuraZone>, PlatformError> {
+    let mut zones = Vec::new();
+    if keyboard.hid_raw().is_none() {
+        return Ok(zones);
+    }
+    println!("\nEach zone is lit red in turn.");
+    for (zone, place) in ZONES {
+        keyboard.write_effect(&static_colour(AuraZone::None, OFF))?;
+        keyboard.write_effect(&static_colour(zone, RED))?;
+        if confirm(&format!("Is only the {place} red?")) {
+            zones.push(zone);
+        }
+    }
+    Ok(zones)
+}
+
+fn probe_power_zones(keyboard: &Keyboard) -> Result<Vec<PowerZones>, PlatformError> {
+    let mut zones = vec![PowerZones::Keyboard];
+    if !keyboard.has_power_zones() {
+        if keyboard.hid_raw().is_some() && confirm("\nDoes the laptop have a lightbar?") {
+            zones.push(PowerZones::Lightbar);
+        }
+        return Ok(zones);
+    }
+    println!("\nEach zone is turned off in turn while the others stay on.");
+    keyboard.write_effect(&static_colour(AuraZone::None, RED))?;
+    for (zone, place) in POWER_ZONES {
+        let mut power = AuraPower::new_all_on();
+        match zone {
+            PowerZones::Logo => power.logo.awake = false,
+            PowerZones::Lightbar => power.lightbar.awake = false,
+            PowerZones::Lid => power.lid.awake = false,
+            PowerZones::RearGlow => power.rear_glow.awake = false,
+            PowerZones::Keyboard => power.keyboard.awake = false,
+        }
+        keyboard.write_power(&power)?;
+        if confirm(&format!("Did the {place} turn off?")) {
+            zones.push(zone);
+        }
+    }
+    keyboard.write_power(&AuraPower::new_all_on())?;
+    Ok(zones)
+}
+
+fn probe_advanced(
+    keyboard: &Keyboard,
+    power_zones: &[PowerZones],
+) -> Result<AdvancedAuraType, PlatformError> {
+    if keyboard.hid_raw().is_none() {
+        return Ok(AdvancedAuraType::None);
+    }
+    println!("\nChecking for per-key and zoned control.");
+    keyboard.write_effect(&static_colour(AuraZone::None, RED))?;
+    let mut per_key = LedUsbPackets::new_per_key();
+    for code in LedCode::ALL {
+        per_key.set(code, BLUE.r, BLUE.g, BLUE.b);
+    }
+    keyboard.write_packets(&per_key)?;
+    if confirm("Did the whole keyboard turn blue?") {
+        return Ok(AdvancedAuraType::PerKey);
+    }
+
+    keyboard.write_effect(&static_colour(AuraZone::None, RED))?;
+    let mut zoned = LedUsbPackets::new_zoned(true);
+    for (code, c) in [
+        (LedCode::ZonedKbLeft, GREEN),
+        (LedCode::ZonedKbLeftMid, BLUE),
+        (LedCode::ZonedKbRightMid, YELLOW),
+        (LedCode::ZonedKbRight, WHITE),
+    ] {
+        zoned.set(code, c.r, c.g, c.b);
+    }
+    keyboard.write_packets(&zoned)?;
+    if confirm("Is the keyboard green, blue, yellow and white from left to right?") {
+        let mut codes: Vec<LedCode> = LedCode::ALL
+            .into_iter()
+            .filter(LedCode::is_keyboard_zone)
+            .collect();
+        if power_zones.contains(&PowerZones::Lightbar) {
+            let mut lightbar = LedUsbPackets::new_zoned(true);
+            for code in LedCode::ALL.into_iter().filter(LedCode::is_lightbar_zone) {
+                lightbar.set(code, BLUE.r, BLUE.g, BLUE.b);
+            }
+            keyboard.write_packets(&lightbar)?;
+            if confirm("Did the lightbar turn blue?") {
+                codes.extend(LedCode::ALL.into_iter().filter(LedCode::is_lightbar_zone));
+            }
+        }
+        return Ok(AdvancedAuraType::Zoned(codes));
+    }
+
+    keyboard.write_effect(&static_colour(AuraZone::None, RED))?;
+    let mut single = LedUsbPackets::new_zoned(false);
+    single.set(LedCode::SingleZone, BLUE.r, BLUE.g, BLUE.b);
+    keyboard.write_packets(&single)?;
+    if confirm("Did the keyboard turn blue?") {
+        return Ok(AdvancedAuraType::Zoned(vec![LedCode::SingleZone]));
+    }
+    Ok(AdvancedAuraType::None)
+}
+
+/// Work out the `LaptopLedData` of this laptop by writing to the keyboard and
+/// asking what happened. Requires root.
+pub fn probe(cmd: &AuraProbeCommand) -> Result<LaptopLedData, Box<dyn std::error::Error>> {
+    let board_name = match &cmd.board_name {
+        Some(name) => name.clone(),
+        None => sysfs_class::DmiId::default()
+            .board_name()?
+            .trim()
+            .to_owned(),
+    };
+    let keyboard = Keyboard::find()?;
+    println!(
+        "Found keyboard {} ({:?}) on board {board_name}. The keyboard will change colours.",
+        keyboard.id(),
+        keyboard.device()
+    );
+    if let Keyboard::Unknown(..) = &keyboard {
+        println!(
+            "{} is not a keyboard asusd knows, please include it when reporting this laptop \
+             upstream.",
+            keyboard.id()
+        );
+        // Nothing is known about what this device does with the packets
+        if !confirm(&format!("Write LED packets to {}?", keyboard.id())) {
+            return Err("Stopped before writing to the device".into());
+        }
+    }
+    if let Keyboard::Tuf(led) = &keyboard {
+        led.set_brightness(3)?;
+    } else if let Ok(led) = KeyboardLed::new() {
+        led.set_brightness(3)?;
+    }
+
+    let basic_modes = probe_modes(&keyboard)?;
+    let basic_zones = probe_zones(&keyboard)?;
+    let power_zones = probe_power_zones(&keyboard)?;
+    let advanced_type = probe_advanced(&keyboard, &power_zones)?;
+    keyboard.write_effect(&AuraEffect::default())?;
+
+    Ok(LaptopLedData {
+        board_name,
+        basic_modes,
+        basic_zones,
+        advanced_type,
+        power_zones,
+        ..Default::default()
+    })
+}
+
+/// Add or replace the entry in `/etc/asusd/asusd_user_ledmodes.ron`
+pub fn install(data: LaptopLedData) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = match fs::read_to_string(ASUS_LED_MODE_USER_CONF) {
+        Ok(text) if !text.trim().is_empty() => LedUserFile::parse(&text)?,
+        _ => LedUserFile::default(),
+    };
+    file.insert(data.into());
+    if let Some(dir) = Path::new(ASUS_LED_MODE_USER_CONF).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(ASUS_LED_MODE_USER_CONF, file.to_ron()?)?;
+    Ok(())
+}
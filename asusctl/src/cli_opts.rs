@@ -1,8 +1,15 @@
 use gumdrop::Options;
+use rog_anime::AnimeType;
+use rog_platform::capture::CaptureKind;
 
 use crate::anime_cli::AnimeCommand;
+use crate::apply::ApplyCommand;
 use crate::aura_cli::{LedBrightness, LedPowerCommand1, LedPowerCommand2, SetAuraBuiltin};
+use crate::aura_probe::AuraProbeCommand;
+use crate::monitor::MonitorCommand;
+use crate::platform_cli::{AttrValue, PlatformAttr};
 use crate::profiles_cli::{FanCurveCommand, ProfileCommand};
+use crate::support_bundle::SupportBundleCommand;
 
 #[derive(Default, Options)]
 pub struct CliStart {
@@ -12,6 +19,8 @@ pub struct CliStart {
     pub version: bool,
     #[options(help = "show supported functions of this laptop")]
     pub show_supported: bool,
+    #[options(help = "print the output of getters as one JSON object")]
+    pub json: bool,
     #[options(meta = "", help = "<off, low, med, high>")]
     pub kbd_bright: Option<LedBrightness>,
     #[options(help = "Toggle to next keyboard brightness")]
@@ -20,6 +29,8 @@ pub struct CliStart {
     pub prev_kbd_bright: bool,
     #[options(meta = "", help = "Set your battery charge limit <20-100>")]
     pub chg_limit: Option<u8>,
+    #[options(no_short, help = "show your battery charge limit")]
+    pub show_chg_limit: bool,
     #[options(command)]
     pub command: Option<CliCommand>,
 }
@@ -32,6 +43,8 @@ pub enum CliCommand {
     LedPow1(LedPowerCommand1),
     #[options(help = "Set the LED power states")]
     LedPow2(LedPowerCommand2),
+    #[options(help = "Tools for keyboard lighting support")]
+    Aura(AuraCommand),
     #[options(help = "Set or select platform_profile")]
     Profile(ProfileCommand),
     #[options(help = "Set, select, or modify fan curves if supported")]
@@ -42,6 +55,16 @@ pub enum CliCommand {
     Anime(AnimeCommand),
     #[options(help = "Change bios settings")]
     Bios(BiosCommand),
+    #[options(help = "Print events from asusd as they happen")]
+    Monitor(MonitorCommand),
+    #[options(help = "Change the laptop to the state described in a file")]
+    Apply(ApplyCommand),
+    #[options(help = "Tools for debugging and adding laptop support")]
+    Debug(DebugCommand),
+    #[options(help = "Check the kernel, devices and service for problems")]
+    Doctor(DoctorCommand),
+    #[options(help = "Write a tarball of system information for bug reports")]
+    SupportBundle(SupportBundleCommand),
 }
 
 #[derive(Options)]
@@ -52,10 +75,26 @@ pub struct LedModeCommand {
     pub next_mode: bool,
     #[options(help = "switch to previous aura mode")]
     pub prev_mode: bool,
+    #[options(help = "show the current aura mode and its settings")]
+    pub get: bool,
     #[options(command)]
     pub command: Option<SetAuraBuiltin>,
 }
 
+#[derive(Options)]
+pub struct AuraCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(command)]
+    pub command: Option<AuraSubCommand>,
+}
+
+#[derive(Options)]
+pub enum AuraSubCommand {
+    #[options(help = "Find the modes and zones of an unsupported keyboard, requires root")]
+    Probe(AuraProbeCommand),
+}
+
 #[derive(Options)]
 pub struct GraphicsCommand {
     #[options(help = "print help message")]
@@ -93,4 +132,72 @@ pub struct BiosCommand {
     pub panel_overdrive_set: Option<bool>,
     #[options(no_long, short = "o", help = "get panel overdrive")]
     pub panel_overdrive_get: bool,
+    #[options(help = "list the platform attributes of this laptop with their values")]
+    pub list: bool,
+    #[options(
+        meta = "",
+        help = "get an attribute, e.g: mini_led_mode or ppt_pl1_spl"
+    )]
+    pub get: Vec<PlatformAttr>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "set an attribute, e.g: dgpu_disable=false, gpu_mux=optimus, ppt_pl1_spl=80"
+    )]
+    pub set: Vec<AttrValue>,
+    #[options(help = "print changes to the platform attributes as they happen")]
+    pub watch: bool,
+}
+
+#[derive(Options)]
+pub struct DoctorCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+}
+
+#[derive(Options)]
+pub struct DebugCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(command)]
+    pub command: Option<DebugSubCommand>,
+}
+
+#[derive(Options)]
+pub enum DebugSubCommand {
+    #[options(help = "Replay a capture file recorded by asusd, requires root")]
+    Replay(ReplayCommand),
+    #[options(help = "Decode the Aura and AniMe packets of a pcap, pcapng or usbmon capture")]
+    Decode(DecodeCommand),
+}
+
+#[derive(Options)]
+pub struct ReplayCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "print each write without sending it")]
+    pub dry_run: bool,
+    #[options(help = "don't wait between writes as recorded")]
+    pub no_delay: bool,
+    #[options(meta = "", help = "only replay <hidraw, usb, sysfs> writes")]
+    pub kind: Option<CaptureKind>,
+    #[options(free, help = "the capture file")]
+    pub file: String,
+}
+
+#[derive(Options)]
+pub struct DecodeCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "also show packets that are not for Aura or AniMe")]
+    pub all: bool,
+    #[options(
+        meta = "",
+        help = "AniMe type to assemble frames for <GA401, GA402, GU604>, default GA402"
+    )]
+    pub anime_type: Option<AnimeType>,
+    #[options(meta = "", help = "only show packets to this <bus:device>")]
+    pub device: Option<String>,
+    #[options(free, help = "the capture file")]
+    pub file: String,
 }
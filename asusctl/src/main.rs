@@ -1,30 +1,49 @@
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::env::args;
+use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::str::FromStr;
 use std::thread::sleep;
+use std::time::Duration;
 
 use anime_cli::{AnimeActions, AnimeCommand};
 use aura_cli::{LedPowerCommand1, LedPowerCommand2};
 use gumdrop::{Opt, Options};
 use profiles_cli::{FanCurveCommand, ProfileCommand};
-use rog_anime::usb::get_anime_type;
 use rog_anime::{AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, AnimeType, Vec2};
+use rog_aura::aura_detection::ASUS_LED_MODE_USER_CONF;
 use rog_aura::power::KbAuraPowerState;
 use rog_aura::usb::{AuraDevRog1, AuraDevTuf, AuraDevice, AuraPowerDev};
 use rog_aura::{self, AuraEffect};
 use rog_dbus::RogDbusClientBlocking;
+use rog_platform::capture::{read_capture, Replayer};
+use rog_platform::capture_decode::{read_usb_capture, Decoded, Decoder};
 use rog_platform::platform::GpuMode;
+use rog_platform::quirks::{get_anime_type, LaptopQuirks};
 use rog_platform::supported::*;
 use rog_profiles::error::ProfileError;
 
+use crate::apply::ApplyCommand;
 use crate::aura_cli::{AuraPowerStates, LedBrightness};
+use crate::aura_probe::AuraProbeCommand;
 use crate::cli_opts::*;
+use crate::monitor::{MonitorCommand, SubsystemList};
+use crate::output::Output;
+use crate::platform_cli::{AttrValue, PlatformAttr, PlatformValue};
+use crate::support_bundle::SupportBundleCommand;
 
 mod anime_cli;
+mod apply;
 mod aura_cli;
+mod aura_probe;
 mod cli_opts;
+mod monitor;
+mod output;
+mod platform_cli;
 mod profiles_cli;
+mod support_bundle;
 
 fn main() {
     let args: Vec<String> = args().skip(1).collect();
@@ -42,6 +61,33 @@ fn main() {
         }
     };
 
+    // Commands which must work without asusd, so are run before connecting
+    match &parsed.command {
+        // Debug commands work on files and hardware directly
+        Some(CliCommand::Debug(cmd)) => {
+            if let Err(err) = handle_debug(cmd) {
+                println!("Error: {}", err);
+            }
+            return;
+        }
+        // Probing writes to the keyboard directly, asusd should be stopped
+        Some(CliCommand::Aura(cmd)) => {
+            if let Err(err) = handle_aura(cmd) {
+                println!("Error: {}", err);
+            }
+            return;
+        }
+        Some(CliCommand::Doctor(cmd)) => {
+            handle_doctor(cmd);
+            return;
+        }
+        Some(CliCommand::SupportBundle(cmd)) => {
+            handle_support_bundle(cmd);
+            return;
+        }
+        _ => {}
+    }
+
     if let Ok((dbus, _)) = RogDbusClientBlocking::new().map_err(|e| {
         print_error_help(&e, None);
     }) {
@@ -107,21 +153,42 @@ fn do_parsed(
     supported: &SupportedFunctions,
     dbus: &RogDbusClientBlocking<'_>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = Output::new(parsed.json);
     match &parsed.command {
-        Some(CliCommand::LedMode(mode)) => handle_led_mode(dbus, &supported.keyboard_led, mode)?,
-        Some(CliCommand::LedPow1(pow)) => handle_led_power1(dbus, &supported.keyboard_led, pow)?,
-        Some(CliCommand::LedPow2(pow)) => handle_led_power2(dbus, &supported.keyboard_led, pow)?,
-        Some(CliCommand::Profile(cmd)) => handle_profile(dbus, &supported.platform_profile, cmd)?,
+        Some(CliCommand::LedMode(mode)) => {
+            handle_led_mode(dbus, &supported.keyboard_led, mode, &mut out)?;
+        }
+        Some(CliCommand::LedPow1(pow)) => {
+            handle_led_power1(dbus, &supported.keyboard_led, pow, &mut out)?;
+        }
+        Some(CliCommand::LedPow2(pow)) => {
+            handle_led_power2(dbus, &supported.keyboard_led, pow, &mut out)?;
+        }
+        Some(CliCommand::Profile(cmd)) => {
+            handle_profile(dbus, &supported.platform_profile, cmd, &mut out)?;
+        }
         Some(CliCommand::FanCurve(cmd)) => {
-            handle_fan_curve(dbus, &supported.platform_profile, cmd)?;
+            handle_fan_curve(dbus, &supported.platform_profile, cmd, &mut out)?;
         }
         Some(CliCommand::Graphics(_)) => do_gfx(),
-        Some(CliCommand::Anime(cmd)) => handle_anime(dbus, &supported.anime_ctrl, cmd)?,
-        Some(CliCommand::Bios(cmd)) => handle_bios_option(dbus, &supported.rog_bios_ctrl, cmd)?,
+        Some(CliCommand::Anime(cmd)) => handle_anime(dbus, &supported.anime_ctrl, cmd, &mut out)?,
+        Some(CliCommand::Bios(cmd)) => {
+            handle_bios_option(dbus, &supported.rog_bios_ctrl, cmd, &mut out)?;
+        }
+        Some(CliCommand::Monitor(cmd)) => handle_monitor(cmd, parsed.json)?,
+        Some(CliCommand::Apply(cmd)) => handle_apply(dbus, supported, cmd)?,
+        // Run by main() before connecting to asusd
+        Some(
+            CliCommand::Aura(_)
+            | CliCommand::Debug(_)
+            | CliCommand::Doctor(_)
+            | CliCommand::SupportBundle(_),
+        ) => {}
         None => {
             if (!parsed.show_supported
                 && parsed.kbd_bright.is_none()
                 && parsed.chg_limit.is_none()
+                && !parsed.show_chg_limit
                 && !parsed.next_kbd_bright
                 && !parsed.prev_kbd_bright)
                 || parsed.help
@@ -163,7 +230,11 @@ fn do_parsed(
         match brightness.level() {
             None => {
                 let level = dbus.proxies().led().led_brightness()?;
-                println!("Current keyboard led brightness: {}", level);
+                out.show(
+                    "kbd_brightness",
+                    &level,
+                    format!("Current keyboard led brightness: {level}"),
+                )?;
             }
             Some(level) => dbus
                 .proxies()
@@ -181,7 +252,11 @@ fn do_parsed(
     }
 
     if parsed.show_supported {
-        println!("Supported laptop functions:\n\n{}", supported);
+        out.show(
+            "supported",
+            supported,
+            format!("Supported laptop functions:\n\n{supported}"),
+        )?;
     }
 
     if let Some(chg_limit) = parsed.chg_limit {
@@ -190,6 +265,12 @@ fn do_parsed(
             .set_charge_control_end_threshold(chg_limit)?;
     }
 
+    if parsed.show_chg_limit {
+        let limit = dbus.proxies().charge().charge_control_end_threshold()?;
+        out.show("chg_limit", &limit, format!("Charge limit: {limit}%"))?;
+    }
+
+    out.finish()?;
     Ok(())
 }
 
@@ -205,13 +286,15 @@ fn handle_anime(
     dbus: &RogDbusClientBlocking<'_>,
     _supported: &AnimeSupportedFunctions,
     cmd: &AnimeCommand,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if (cmd.command.is_none()
         && cmd.enable_display.is_none()
         && cmd.enable_powersave_anim.is_none()
         && cmd.brightness.is_none()
         && cmd.image_brightness.is_none()
-        && !cmd.clear)
+        && !cmd.clear
+        && !cmd.get_state)
         || cmd.help
     {
         println!("Missing arg or command\n\n{}", cmd.self_usage());
@@ -232,6 +315,10 @@ fn handle_anime(
         verify_brightness(bright);
         dbus.proxies().anime().set_image_brightness(bright)?;
     }
+    if cmd.get_state {
+        let state = dbus.proxies().anime().device_state()?;
+        out.show("anime", &state, format!("{state:#?}"))?;
+    }
 
     let mut anime_type = get_anime_type()?;
     if let AnimeType::Unknown = anime_type {
@@ -392,8 +479,9 @@ fn handle_led_mode(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &LedSupportedFunctions,
     mode: &LedModeCommand,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if mode.command.is_none() && !mode.prev_mode && !mode.next_mode {
+    if mode.command.is_none() && !mode.prev_mode && !mode.next_mode && !mode.get {
         if !mode.help {
             println!("Missing arg or command\n");
         }
@@ -442,6 +530,14 @@ fn handle_led_mode(
             .set_led_mode(&<AuraEffect>::from(mode))?;
     }
 
+    if mode.get {
+        let current = dbus.proxies().led().led_mode()?;
+        let modes = dbus.proxies().led().led_modes()?;
+        if let Some(effect) = modes.get(&current) {
+            out.show("led_mode", effect, format!("{effect:#?}"))?;
+        }
+    }
+
     Ok(())
 }
 
@@ -449,13 +545,14 @@ fn handle_led_power1(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &LedSupportedFunctions,
     power: &LedPowerCommand1,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if power.awake.is_none()
-        && power.sleep.is_none()
-        && power.boot.is_none()
-        && power.keyboard.is_none()
-        && power.lightbar.is_none()
-    {
+    let set = power.awake.is_some()
+        || power.sleep.is_some()
+        || power.boot.is_some()
+        || power.keyboard.is_some()
+        || power.lightbar.is_some();
+    if !set && !power.get {
         if !power.help {
             println!("Missing arg or command\n");
         }
@@ -463,20 +560,32 @@ fn handle_led_power1(
         return Ok(());
     }
 
-    if matches!(
-        supported.dev_id,
-        AuraDevice::X1854 | AuraDevice::X1869 | AuraDevice::X1866
-    ) {
-        handle_led_power_1_do_1866(dbus, power)?;
-        return Ok(());
+    if set {
+        if matches!(
+            supported.dev_id,
+            AuraDevice::X1854 | AuraDevice::X1869 | AuraDevice::X1866
+        ) {
+            handle_led_power_1_do_1866(dbus, power)?;
+        } else if matches!(supported.dev_id, AuraDevice::Tuf) {
+            handle_led_power_1_do_tuf(dbus, power)?;
+        } else {
+            println!("These options are for keyboards of product ID 0x1866 or TUF only");
+            return Ok(());
+        }
     }
 
-    if matches!(supported.dev_id, AuraDevice::Tuf) {
-        handle_led_power_1_do_tuf(dbus, power)?;
-        return Ok(());
+    if power.get {
+        show_led_power(dbus, out)?;
     }
+    Ok(())
+}
 
-    println!("These options are for keyboards of product ID 0x1866 or TUF only");
+fn show_led_power(
+    dbus: &RogDbusClientBlocking<'_>,
+    out: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let power = dbus.proxies().led().led_power()?;
+    out.show("led_power", &power, format!("{power:#?}"))?;
     Ok(())
 }
 
@@ -559,8 +668,9 @@ fn handle_led_power2(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &LedSupportedFunctions,
     power: &LedPowerCommand2,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if power.command().is_none() {
+    if power.command().is_none() && !power.get {
         if !power.help {
             println!("Missing arg or command\n");
         }
@@ -609,6 +719,9 @@ fn handle_led_power2(
         dbus.proxies().led().set_led_power(enabled, true)?;
     }
 
+    if power.get {
+        show_led_power(dbus, out)?;
+    }
     Ok(())
 }
 
@@ -616,13 +729,20 @@ fn handle_profile(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &PlatformProfileFunctions,
     cmd: &ProfileCommand,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !supported.platform_profile {
         println!("Profiles not supported by either this kernel or by the laptop.");
         return Err(ProfileError::NotSupported.into());
     }
 
-    if !cmd.next && !cmd.list && cmd.profile_set.is_none() && !cmd.profile_get {
+    if !cmd.next
+        && !cmd.list
+        && cmd.profile_set.is_none()
+        && !cmd.profile_get
+        && !cmd.cycle_get
+        && !cmd.cycle_set()
+    {
         if !cmd.help {
             println!("Missing arg or command\n");
         }
@@ -642,14 +762,48 @@ fn handle_profile(
 
     if cmd.list {
         let res = dbus.proxies().profile().profiles()?;
-        for p in &res {
-            println!("{:?}", p);
-        }
+        let text: Vec<String> = res.iter().map(|p| format!("{p:?}")).collect();
+        out.show("profiles", &res, text.join("\n"))?;
     }
 
     if cmd.profile_get {
         let res = dbus.proxies().profile().active_profile()?;
-        println!("Active profile is {:?}", res);
+        out.show("profile", &res, format!("Active profile is {res:?}"))?;
+    }
+
+    if cmd.cycle_ac.is_some() || cmd.skip_ac.is_some() {
+        let mut cycle = dbus.proxies().profile().profile_cycle(true)?;
+        if let Some(order) = &cmd.cycle_ac {
+            cycle.order = order.0.clone();
+        }
+        if let Some(skip) = &cmd.skip_ac {
+            cycle.skip = skip.0.clone();
+        }
+        dbus.proxies().profile().set_profile_cycle(true, cycle)?;
+    }
+
+    if cmd.cycle_bat.is_some() || cmd.skip_bat.is_some() {
+        let mut cycle = dbus.proxies().profile().profile_cycle(false)?;
+        if let Some(order) = &cmd.cycle_bat {
+            cycle.order = order.0.clone();
+        }
+        if let Some(skip) = &cmd.skip_bat {
+            cycle.skip = skip.0.clone();
+        }
+        dbus.proxies().profile().set_profile_cycle(false, cycle)?;
+    }
+
+    if cmd.cycle_get {
+        let ac = dbus.proxies().profile().profile_cycle(true)?;
+        let bat = dbus.proxies().profile().profile_cycle(false)?;
+        let text = format!(
+            "AC cycle order: {:?}, skip: {:?}\nBattery cycle order: {:?}, skip: {:?}",
+            ac.order, ac.skip, bat.order, bat.skip
+        );
+        let mut cycles = BTreeMap::new();
+        cycles.insert("ac", ac);
+        cycles.insert("battery", bat);
+        out.show("profile_cycle", &cycles, text)?;
     }
 
     Ok(())
@@ -659,6 +813,7 @@ fn handle_fan_curve(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &PlatformProfileFunctions,
     cmd: &FanCurveCommand,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if supported.fans.is_empty() {
         println!("Fan-curves not supported by either this kernel or by the laptop.");
@@ -691,9 +846,14 @@ fn handle_fan_curve(
     if cmd.get_enabled {
         let profile = dbus.proxies().profile().active_profile()?;
         let curves = dbus.proxies().profile().fan_curve_data(profile)?;
-        for curve in curves.iter() {
-            println!("{}", String::from(curve));
-        }
+        let text: Vec<String> = curves
+            .iter()
+            .map(|curve| match LaptopQuirks::get().fan_label(curve.fan) {
+                Some(label) => format!("{} ({label} fan)", String::from(curve)),
+                None => String::from(curve),
+            })
+            .collect();
+        out.show("fan_curves", &curves, text.join("\n"))?;
     }
 
     if cmd.default {
@@ -703,8 +863,13 @@ fn handle_fan_curve(
     if let Some(profile) = cmd.mod_profile {
         if cmd.enable_fan_curves.is_none() && cmd.data.is_none() {
             let data = dbus.proxies().profile().fan_curve_data(profile)?;
-            let data = toml::to_string(&data)?;
-            println!("\nFan curves for {:?}\n\n{}", profile, data);
+            let text = format!(
+                "\nFan curves for {profile:?}\n\n{}",
+                toml::to_string(&data)?
+            );
+            let mut curves = BTreeMap::new();
+            curves.insert(profile, data);
+            out.show("profile_fan_curves", &curves, text)?;
         }
 
         if let Some(enabled) = cmd.enable_fan_curves {
@@ -740,60 +905,115 @@ fn handle_bios_option(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &RogBiosSupportedFunctions,
     cmd: &BiosCommand,
+    out: &mut Output,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    {
-        if (cmd.gpu_mux_mode_set.is_none()
-            && !cmd.gpu_mux_mode_get
-            && cmd.post_sound_set.is_none()
-            && !cmd.post_sound_get
-            && cmd.panel_overdrive_set.is_none()
-            && !cmd.panel_overdrive_get)
-            || cmd.help
-        {
+    let mut get = cmd.get.clone();
+    let mut set = cmd.set.clone();
+    // The older single attribute options
+    let old_set = [
+        ("post_sound", cmd.post_sound_set.map(PlatformValue::Bool)),
+        (
+            "gpu_mux",
+            cmd.gpu_mux_mode_set
+                .map(|mode| PlatformValue::Mode(GpuMode::from_mux(mode))),
+        ),
+        (
+            "panel_overdrive",
+            cmd.panel_overdrive_set.map(PlatformValue::Bool),
+        ),
+    ];
+    for (name, value) in old_set {
+        if let Some(value) = value {
+            let attr = PlatformAttr::from_str(name)?;
+            set.push(AttrValue { attr, value });
+        }
+    }
+    for (wanted, name) in [
+        (cmd.post_sound_get, "post_sound"),
+        (cmd.gpu_mux_mode_get, "gpu_mux"),
+        (cmd.panel_overdrive_get, "panel_overdrive"),
+    ] {
+        if wanted {
+            get.push(PlatformAttr::from_str(name)?);
+        }
+    }
+
+    if (get.is_empty() && set.is_empty() && !cmd.list && !cmd.watch) || cmd.help {
+        if !cmd.help {
             println!("Missing arg or command\n");
+        }
 
-            let usage: Vec<String> = BiosCommand::usage().lines().map(|s| s.to_owned()).collect();
+        let usage: Vec<String> = BiosCommand::usage().lines().map(|s| s.to_owned()).collect();
+        for line in usage.iter().filter(|line| {
+            line.contains("sound") && supported.post_sound
+                || line.contains("GPU") && supported.gpu_mux
+                || line.contains("panel") && supported.panel_overdrive
+                || line.contains("attribute")
+                || line.contains("help")
+        }) {
+            println!("{}", line);
+        }
 
-            for line in usage.iter().filter(|line| {
-                line.contains("sound") && supported.post_sound
-                    || line.contains("GPU") && supported.gpu_mux
-                    || line.contains("panel") && supported.panel_overdrive
-            }) {
-                println!("{}", line);
+        let attrs = PlatformAttr::supported(supported);
+        if !attrs.is_empty() {
+            println!("\nAttributes of this laptop:");
+            for attr in attrs {
+                println!("  {attr:<18} {}", attr.description());
             }
         }
+        return Ok(());
+    }
 
-        if let Some(opt) = cmd.post_sound_set {
-            dbus.proxies().rog_bios().set_post_boot_sound(opt)?;
-        }
-        if cmd.post_sound_get {
-            let res = dbus.proxies().rog_bios().post_boot_sound()? == 1;
-            println!("Bios POST sound on: {}", res);
+    for attr in set.iter().map(|s| &s.attr).chain(get.iter()) {
+        if !attr.is_supported(supported) {
+            return Err(format!("{attr} is not supported by this laptop").into());
         }
+    }
 
-        if let Some(opt) = cmd.gpu_mux_mode_set {
-            println!("Rebuilding initrd to include drivers");
-            dbus.proxies()
-                .rog_bios()
-                .set_gpu_mux_mode(GpuMode::from_mux(opt))?;
-            println!(
-                "The mode change is not active until you reboot, on boot the bios will make the \
-                 required change"
-            );
-        }
-        if cmd.gpu_mux_mode_get {
-            let res = dbus.proxies().rog_bios().gpu_mux_mode()?;
-            println!("Bios GPU MUX: {:?}", res);
+    for s in &set {
+        if let Some(notice) = s.attr.set(dbus, &s.value)? {
+            out.note(notice);
         }
+    }
 
-        if let Some(opt) = cmd.panel_overdrive_set {
-            dbus.proxies().rog_bios().set_panel_od(opt)?;
+    if cmd.list {
+        let attrs = PlatformAttr::supported(supported);
+        for attr in &attrs {
+            let value = attr.get(dbus)?;
+            let text = format!("{attr:<18} {value:<10} {}", attr.description());
+            out.show(&attr.to_string(), &value, text)?;
         }
-        if cmd.panel_overdrive_get {
-            let res = dbus.proxies().rog_bios().panel_od()?;
-            println!("Panel overdrive on: {}", res);
+        // Firmware attributes with no switch or tunable of their own
+        let names: Vec<String> = attrs.iter().map(|a| a.to_string()).collect();
+        for attr in dbus
+            .proxies()
+            .firmware_attributes()
+            .attributes()
+            .unwrap_or_default()
+        {
+            if names.contains(&attr.name) {
+                continue;
+            }
+            let key = format!("{}/{}", attr.device, attr.name);
+            let value = PlatformValue::Text(attr.current_value);
+            let text = format!("{key:<18} {value:<10} {}", attr.display_name);
+            out.show(&key, &value, text)?;
         }
     }
+
+    for attr in &get {
+        let value = attr.get(dbus)?;
+        out.show(&attr.to_string(), &value, format!("{attr}: {value}"))?;
+    }
+
+    if cmd.watch {
+        out.flush()?;
+        let watch = MonitorCommand {
+            help: false,
+            only: Some(SubsystemList(vec!["platform"])),
+        };
+        monitor::monitor(&watch, out.is_json())?;
+    }
     Ok(())
 }
 
@@ -820,3 +1040,223 @@ fn check_systemd_unit_enabled(name: &str) -> bool {
     }
     false
 }
+
+fn handle_doctor(cmd: &DoctorCommand) {
+    if cmd.help {
+        println!("{}", DoctorCommand::usage());
+        return;
+    }
+
+    print_info();
+    let release = fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+    println!("         Kernel: {}", release.trim());
+    let active = check_systemd_unit_active("asusd");
+    let enabled = check_systemd_unit_enabled("asusd");
+    println!(
+        "  asusd service: {}, {}",
+        if active { "running" } else { "not running" },
+        if enabled { "enabled" } else { "not enabled" }
+    );
+
+    let probes = match RogDbusClientBlocking::new()
+        .and_then(|(dbus, _)| dbus.proxies().supported().diagnostics())
+    {
+        Ok(probes) => {
+            println!("\nChecks made by asusd:\n");
+            probes
+        }
+        Err(err) => {
+            println!("\nCould not get the checks made by asusd ({err}), checking here instead:\n");
+            asusd::diagnostics::run_probes()
+        }
+    };
+    for probe in &probes {
+        print!("{probe}");
+    }
+
+    let missing = probes.iter().filter(|p| !p.found).count();
+    println!(
+        "\n{} of {} checks passed",
+        probes.len() - missing,
+        probes.len()
+    );
+    if !active {
+        println!("asusd is not running, start it with `systemctl start asusd`");
+    } else if !enabled {
+        println!("asusd is not enabled, enable it with `systemctl enable asusd`");
+    }
+    if missing > 0 {
+        println!(
+            "Missing items are not always a problem, see the detail of each. When reporting an \
+             issue please include this output."
+        );
+    }
+}
+
+fn handle_support_bundle(cmd: &SupportBundleCommand) {
+    if cmd.help {
+        println!("{}", SupportBundleCommand::usage());
+        return;
+    }
+    match support_bundle::write_bundle(cmd) {
+        Ok(path) => {
+            println!("Wrote {}", path.display());
+            println!(
+                "Serial numbers, user and host names are redacted. Please check the contents \
+                 before attaching it to an issue."
+            );
+        }
+        Err(err) => println!("Error: {}", err),
+    }
+}
+
+fn handle_aura(cmd: &AuraCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match &cmd.command {
+        Some(AuraSubCommand::Probe(probe)) => handle_aura_probe(probe)?,
+        None => {
+            println!("{}", AuraCommand::usage());
+            if let Some(lst) = cmd.self_command_list() {
+                println!("\n{}", lst);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_aura_probe(cmd: &AuraProbeCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", AuraProbeCommand::usage());
+        return Ok(());
+    }
+    if check_systemd_unit_active("asusd") && !cmd.force {
+        return Err(
+            "asusd is running and would overwrite the probe, stop it with `systemctl stop asusd` \
+             or use --force"
+                .into(),
+        );
+    }
+    let data = aura_probe::probe(cmd)?;
+    let pretty = ron::ser::PrettyConfig::new().depth_limit(1);
+    println!("\n{},", ron::ser::to_string_pretty(&data, pretty)?);
+    println!(
+        "\nlayout_name is left empty, set it to the closest layout in \
+         /usr/share/rog-gui/layouts without the _US.ron suffix."
+    );
+    if cmd.install {
+        aura_probe::install(data)?;
+        println!("Added to {ASUS_LED_MODE_USER_CONF}, restart asusd to use it.");
+    } else {
+        println!("Run again with --install to add it to {ASUS_LED_MODE_USER_CONF}.");
+    }
+    println!("Please submit the entry upstream so it can be added to aura_support.ron.");
+    Ok(())
+}
+
+fn handle_apply(
+    dbus: &RogDbusClientBlocking<'_>,
+    supported: &SupportedFunctions,
+    cmd: &ApplyCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || cmd.file.is_empty() {
+        println!("{}", ApplyCommand::usage());
+        return Ok(());
+    }
+    apply::apply(dbus, supported, cmd)
+}
+
+fn handle_monitor(cmd: &MonitorCommand, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", MonitorCommand::usage());
+        return Ok(());
+    }
+    monitor::monitor(cmd, json)
+}
+
+fn handle_debug(cmd: &DebugCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match &cmd.command {
+        Some(DebugSubCommand::Replay(replay)) => handle_replay(replay)?,
+        Some(DebugSubCommand::Decode(decode)) => handle_decode(decode)?,
+        None => {
+            println!("{}", DebugCommand::usage());
+            if let Some(lst) = cmd.self_command_list() {
+                println!("\n{}", lst);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Don't wait longer than this between replayed writes
+const MAX_REPLAY_DELAY: Duration = Duration::from_secs(5);
+
+fn handle_replay(cmd: &ReplayCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || cmd.file.is_empty() {
+        println!("{}", ReplayCommand::usage());
+        return Ok(());
+    }
+
+    let records = read_capture(Path::new(&cmd.file))?;
+    let mut replayer = Replayer::default();
+    let mut last = None;
+    for record in records
+        .iter()
+        .filter(|r| !matches!(cmd.kind, Some(k) if k != r.kind))
+    {
+        if let Some(last) = last {
+            if !cmd.dry_run && !cmd.no_delay {
+                sleep(record.timestamp.saturating_sub(last).min(MAX_REPLAY_DELAY));
+            }
+        }
+        last = Some(record.timestamp);
+        println!("{record}");
+        if !cmd.dry_run {
+            replayer.write(record)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_decode(cmd: &DecodeCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || cmd.file.is_empty() {
+        println!("{}", DecodeCommand::usage());
+        return Ok(());
+    }
+
+    let transfers = read_usb_capture(Path::new(&cmd.file))?;
+    let mut decoder = Decoder::new(cmd.anime_type.unwrap_or(AnimeType::GA402));
+    let start = transfers.first().map(|t| t.timestamp).unwrap_or_default();
+    for transfer in transfers
+        .iter()
+        .filter(|t| !matches!(&cmd.device, Some(d) if *d != t.device))
+    {
+        let packet = decoder.decode(&transfer.data);
+        if !cmd.all && matches!(packet.decoded, Decoded::Unknown) {
+            continue;
+        }
+        let time = transfer.timestamp.saturating_sub(start);
+        println!(
+            "{:>5}.{:06} {} {:>3} bytes: {}",
+            time.as_secs(),
+            time.subsec_micros(),
+            transfer.device,
+            transfer.data.len(),
+            packet.decoded
+        );
+        if matches!(packet.decoded, Decoded::Unknown) {
+            let hex: Vec<String> = transfer.data.iter().map(|b| format!("{b:02x}")).collect();
+            println!("    data: {}", hex.join(" "));
+        }
+        if !packet.unknown.is_empty() {
+            let unknown: Vec<String> = packet
+                .unknown
+                .iter()
+                .map(|(offset, value)| format!("[{offset}]={value:02x}"))
+                .collect();
+            println!("    unknown bytes: {}", unknown.join(" "));
+        }
+        if packet.truncated {
+            println!("    truncated, missing bytes were decoded as zero");
+        }
+    }
+    Ok(())
+}
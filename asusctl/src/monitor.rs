@@ -0,0 +1,76 @@
+//! `asusctl monitor`, prints each signal from asusd as it arrives
+
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use gumdrop::Options;
+use rog_dbus::events::DaemonEvent;
+use rog_dbus::RogDbusClient;
+use serde_json::Value;
+use zbus::export::futures_util::StreamExt;
+
+#[derive(Options)]
+pub struct MonitorCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "",
+        help = "only show events from these subsystems, e.g: profile,aura. Any of anime, aura, \
+                platform, power, profile, firmware, supported"
+    )]
+    pub only: Option<SubsystemList>,
+}
+
+/// A comma separated list of [`DaemonEvent::subsystem()`] names
+#[derive(Debug, Clone, Default)]
+pub struct SubsystemList(pub Vec<&'static str>);
+
+impl FromStr for SubsystemList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match DaemonEvent::SUBSYSTEMS.iter().find(|s| **s == name) {
+                Some(s) => list.push(*s),
+                None => {
+                    return Err(format!(
+                        "Unknown subsystem {name}, expected any of {}",
+                        DaemonEvent::SUBSYSTEMS.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(Self(list))
+    }
+}
+
+/// Print events until the connection to the system bus is closed. With `json`
+/// each event is one JSON object per line.
+pub fn monitor(cmd: &MonitorCommand, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    zbus::block_on(async {
+        let (client, _conn) = RogDbusClient::new().await?;
+        let mut events = client.events().await?;
+        while let Some(event) = events.next().await {
+            let subsystem = event.subsystem();
+            if let Some(only) = &cmd.only {
+                if !only.0.contains(&subsystem) {
+                    continue;
+                }
+            }
+
+            let time = humantime::format_rfc3339_millis(SystemTime::now()).to_string();
+            if json {
+                let mut value = serde_json::to_value(&event)?;
+                if let Value::Object(map) = &mut value {
+                    map.insert("time".to_owned(), Value::String(time));
+                    map.insert("subsystem".to_owned(), Value::String(subsystem.to_owned()));
+                }
+                println!("{value}");
+            } else {
+                println!("{time} {subsystem:<9} {event:?}");
+            }
+        }
+        Ok(())
+    })
+}
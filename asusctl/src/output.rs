@@ -0,0 +1,67 @@
+//! Getter output. Normally each value is printed as text when it is read, with
+//! `--json` the values are instead collected in to one JSON object which is
+//! printed once all commands are done. The keys are documented in MANUAL.md.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+pub struct Output {
+    json: Option<Map<String, Value>>,
+}
+
+impl Output {
+    pub fn new(json: bool) -> Self {
+        Self {
+            json: json.then(Map::new),
+        }
+    }
+
+    /// Print `text`, or store `value` under `key` if `--json` was used
+    pub fn show<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: &T,
+        text: impl Display,
+    ) -> Result<(), serde_json::Error> {
+        match &mut self.json {
+            Some(map) => {
+                map.insert(key.to_owned(), serde_json::to_value(value)?);
+            }
+            None => println!("{text}"),
+        }
+        Ok(())
+    }
+
+    /// Print a message that isn't a value, to stderr if `--json` was used so
+    /// that the JSON stays valid
+    pub fn note(&self, text: impl Display) {
+        if self.is_json() {
+            eprintln!("{text}");
+        } else {
+            println!("{text}");
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.json.is_some()
+    }
+
+    /// Print the JSON object of the values stored so far, if any, and start a
+    /// new one. Used before a command which doesn't return
+    pub fn flush(&mut self) -> Result<(), serde_json::Error> {
+        if let Some(map) = &mut self.json {
+            if !map.is_empty() {
+                println!("{}", serde_json::to_string_pretty(map)?);
+                map.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Print the collected JSON object, if any values were stored
+    pub fn finish(mut self) -> Result<(), serde_json::Error> {
+        self.flush()
+    }
+}
@@ -0,0 +1,436 @@
+//! Get and set any of the platform attributes listed in
+//! `RogBiosSupportedFunctions`, the switches and the power limit tunables.
+//!
+//! The switches are the rows of [`SWITCHES`]. Once asusd has the dbus methods
+//! and supported field of a new one it only needs a row there. The tunables
+//! are all those of [`PlatformTunable`]. Any other name is looked up in the
+//! generic firmware attributes of asusd, so attributes new to the kernel work
+//! without a row.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rog_dbus::zbus_platform::RogBiosProxyBlocking;
+use rog_dbus::RogDbusClientBlocking;
+use rog_platform::firmware_attributes::FirmwareAttribute;
+use rog_platform::platform::{GpuMode, PlatformTunable};
+use rog_platform::supported::RogBiosSupportedFunctions;
+use serde_derive::Serialize;
+
+/// How the value of a switch is read and written over dbus
+#[derive(Debug)]
+enum Access {
+    Bool {
+        get: fn(&RogBiosProxyBlocking<'_>) -> zbus::Result<bool>,
+        set: fn(&RogBiosProxyBlocking<'_>, bool) -> zbus::Result<()>,
+    },
+    Mode {
+        get: fn(&RogBiosProxyBlocking<'_>) -> zbus::Result<GpuMode>,
+        set: fn(&RogBiosProxyBlocking<'_>, GpuMode) -> zbus::Result<()>,
+    },
+}
+
+/// An on/off or mode attribute of `asus-wmi`
+#[derive(Debug)]
+pub struct Switch {
+    /// The field in `RogBiosSupportedFunctions`
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Shown to the user after the switch is set
+    pub notice: Option<&'static str>,
+    supported: fn(&RogBiosSupportedFunctions) -> bool,
+    access: Access,
+}
+
+impl PartialEq for Switch {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Switch {}
+
+pub static SWITCHES: [Switch; 6] = [
+    Switch {
+        name: "post_sound",
+        description: "POST sound on boot",
+        notice: None,
+        supported: |s| s.post_sound,
+        access: Access::Bool {
+            get: |bios| Ok(bios.post_boot_sound()? == 1),
+            set: |bios, on| bios.set_post_boot_sound(on),
+        },
+    },
+    Switch {
+        name: "gpu_mux",
+        description: "GPU MUX mode, a reboot is required to change",
+        supported: |s| s.gpu_mux,
+        notice: Some(
+            "The initrd was rebuilt to include drivers. The mode change is not active until you \
+             reboot, on boot the bios will make the required change",
+        ),
+        access: Access::Mode {
+            get: |bios| bios.gpu_mux_mode(),
+            set: |bios, mode| bios.set_gpu_mux_mode(mode),
+        },
+    },
+    Switch {
+        name: "panel_overdrive",
+        description: "Panel overdrive",
+        notice: None,
+        supported: |s| s.panel_overdrive,
+        access: Access::Bool {
+            get: |bios| bios.panel_od(),
+            set: |bios, on| bios.set_panel_od(on),
+        },
+    },
+    Switch {
+        name: "dgpu_disable",
+        description: "Disable the dGPU",
+        notice: None,
+        supported: |s| s.dgpu_disable,
+        access: Access::Bool {
+            get: |bios| bios.dgpu_disable(),
+            set: |bios, on| bios.set_dgpu_disable(on),
+        },
+    },
+    Switch {
+        name: "egpu_enable",
+        description: "Enable the eGPU",
+        notice: None,
+        supported: |s| s.egpu_enable,
+        access: Access::Bool {
+            get: |bios| bios.egpu_enable(),
+            set: |bios, on| bios.set_egpu_enable(on),
+        },
+    },
+    Switch {
+        name: "mini_led_mode",
+        description: "MiniLED backlight mode",
+        notice: None,
+        supported: |s| s.mini_led_mode,
+        access: Access::Bool {
+            get: |bios| bios.mini_led_mode(),
+            set: |bios, on| bios.set_mini_led_mode(on),
+        },
+    },
+];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PlatformAttr {
+    Switch(&'static Switch),
+    Tunable(PlatformTunable),
+    /// An attribute of `/org/asuslinux/FirmwareAttributes`. Without a device
+    /// the name must be unique across all devices.
+    Firmware {
+        device: Option<String>,
+        name: String,
+    },
+}
+
+impl PlatformAttr {
+    /// Every attribute available on this laptop, switches first
+    pub fn supported(supported: &RogBiosSupportedFunctions) -> Vec<Self> {
+        SWITCHES
+            .iter()
+            .map(Self::Switch)
+            .chain(PlatformTunable::ALL.into_iter().map(Self::Tunable))
+            .filter(|attr| attr.is_supported(supported))
+            .collect()
+    }
+
+    /// A firmware attribute is always supported here, asusd refuses one that
+    /// doesn't exist
+    pub fn is_supported(&self, supported: &RogBiosSupportedFunctions) -> bool {
+        match self {
+            Self::Switch(s) => (s.supported)(supported),
+            Self::Tunable(t) => supported.tunables.contains(t),
+            Self::Firmware { .. } => true,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Switch(s) => s.description,
+            Self::Tunable(t) => t.description(),
+            Self::Firmware { .. } => "Firmware attribute",
+        }
+    }
+
+    /// Parse a value of the type this attribute takes
+    pub fn parse_value(&self, value: &str) -> Result<PlatformValue, String> {
+        let lower = value.trim().to_ascii_lowercase();
+        match self {
+            Self::Switch(Switch {
+                access: Access::Mode { .. },
+                ..
+            }) => match lower.as_str() {
+                "0" | "discrete" => Ok(PlatformValue::Mode(GpuMode::Discrete)),
+                "1" | "optimus" => Ok(PlatformValue::Mode(GpuMode::Optimus)),
+                _ => Err(format!("{self} is one of <discrete, optimus> or <0, 1>")),
+            },
+            Self::Switch(_) => match lower.as_str() {
+                "true" | "on" | "1" => Ok(PlatformValue::Bool(true)),
+                "false" | "off" | "0" => Ok(PlatformValue::Bool(false)),
+                _ => Err(format!("{self} is one of <true, false>")),
+            },
+            Self::Tunable(_) => lower
+                .parse()
+                .map(PlatformValue::Number)
+                .map_err(|_| format!("{self} is a number")),
+            // Checked against the attribute metadata by asusd
+            Self::Firmware { .. } => Ok(PlatformValue::Text(value.to_owned())),
+        }
+    }
+
+    /// Find the firmware attribute `name`, on `device` if given
+    fn firmware(
+        dbus: &RogDbusClientBlocking<'_>,
+        device: Option<&str>,
+        name: &str,
+    ) -> Result<FirmwareAttribute, Box<dyn std::error::Error>> {
+        let fw = dbus.proxies().firmware_attributes();
+        if let Some(device) = device {
+            return Ok(fw.attribute(device, name)?);
+        }
+        let mut found: Vec<FirmwareAttribute> = fw
+            .attributes()?
+            .into_iter()
+            .filter(|a| a.name == name)
+            .collect();
+        match found.len() {
+            0 => Err(format!("Unknown attribute {name}").into()),
+            1 => Ok(found.remove(0)),
+            _ => {
+                let names: Vec<String> = found
+                    .iter()
+                    .map(|a| format!("{}/{}", a.device, a.name))
+                    .collect();
+                Err(format!("{name} is ambiguous, use one of {}", names.join(", ")).into())
+            }
+        }
+    }
+
+    pub fn get(
+        &self,
+        dbus: &RogDbusClientBlocking<'_>,
+    ) -> Result<PlatformValue, Box<dyn std::error::Error>> {
+        let bios = dbus.proxies().rog_bios();
+        Ok(match self {
+            Self::Switch(s) => match &s.access {
+                Access::Bool { get, .. } => PlatformValue::Bool(get(bios)?),
+                Access::Mode { get, .. } => PlatformValue::Mode(get(bios)?),
+            },
+            Self::Tunable(t) => PlatformValue::Number(bios.tunable(*t)?),
+            Self::Firmware { device, name } => {
+                PlatformValue::Text(Self::firmware(dbus, device.as_deref(), name)?.current_value)
+            }
+        })
+    }
+
+    /// The value must be one returned by [`PlatformAttr::parse_value()`].
+    /// Returns the notice to show the user, if the attribute has one.
+    pub fn set(
+        &self,
+        dbus: &RogDbusClientBlocking<'_>,
+        value: &PlatformValue,
+    ) -> Result<Option<&'static str>, Box<dyn std::error::Error>> {
+        let bios = dbus.proxies().rog_bios();
+        match (self, value) {
+            (Self::Switch(s), value) => {
+                match (&s.access, value) {
+                    (Access::Bool { set, .. }, PlatformValue::Bool(on)) => set(bios, *on)?,
+                    (Access::Mode { set, .. }, PlatformValue::Mode(mode)) => set(bios, *mode)?,
+                    (_, value) => return Err(format!("{value} is not a value of {self}").into()),
+                }
+                return Ok(s.notice);
+            }
+            (Self::Tunable(t), PlatformValue::Number(value)) => {
+                let (min, max) = bios.tunable_range(*t)?;
+                if !(min..=max).contains(value) {
+                    return Err(format!("{self} must be {min} to {max}").into());
+                }
+                bios.set_tunable(*t, *value)?;
+            }
+            (Self::Firmware { device, name }, PlatformValue::Text(value)) => {
+                let attr = Self::firmware(dbus, device.as_deref(), name)?;
+                dbus.proxies().firmware_attributes().set_attribute(
+                    &attr.device,
+                    &attr.name,
+                    value,
+                )?;
+            }
+            (_, value) => return Err(format!("{value} is not a value of {self}").into()),
+        }
+        Ok(None)
+    }
+}
+
+impl fmt::Display for PlatformAttr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Switch(s) => write!(f, "{}", s.name),
+            Self::Tunable(t) => write!(f, "{t}"),
+            Self::Firmware {
+                device: Some(device),
+                name,
+            } => write!(f, "{device}/{name}"),
+            Self::Firmware { device: None, name } => write!(f, "{name}"),
+        }
+    }
+}
+
+impl FromStr for PlatformAttr {
+    type Err = String;
+
+    /// Accepts a `RogBiosSupportedFunctions` field or a tunable attribute name,
+    /// and otherwise a firmware attribute as `<name>` or `<device>/<name>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase().replace('-', "_");
+        if let Some(switch) = SWITCHES.iter().find(|sw| sw.name == name) {
+            return Ok(Self::Switch(switch));
+        }
+        if let Ok(tunable) = PlatformTunable::from_str(s) {
+            return Ok(Self::Tunable(tunable));
+        }
+
+        let (device, name) = match s.trim().split_once('/') {
+            Some((device, name)) => (Some(device.to_owned()), name),
+            None => (None, s.trim()),
+        };
+        if name.is_empty() || name.contains('/') || device.as_deref() == Some("") {
+            return Err(format!(
+                "Invalid attribute {s}, expected <name> or <device>/<name>"
+            ));
+        }
+        Ok(Self::Firmware {
+            device,
+            name: name.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PlatformValue {
+    Bool(bool),
+    Mode(GpuMode),
+    Number(u8),
+    /// The `current_value` of a firmware attribute
+    Text(String),
+}
+
+impl fmt::Display for PlatformValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Mode(m) => write!(f, "{m}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Text(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+/// An `<attribute>=<value>` argument
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AttrValue {
+    pub attr: PlatformAttr,
+    pub value: PlatformValue,
+}
+
+impl FromStr for AttrValue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (attr, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <attribute>=<value>, got {s}"))?;
+        let attr = PlatformAttr::from_str(attr)?;
+        let value = attr.parse_value(value)?;
+        Ok(Self { attr, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_attr_values() {
+        assert_eq!(
+            AttrValue::from_str("panel-overdrive=on").unwrap(),
+            AttrValue {
+                attr: PlatformAttr::from_str("panel_overdrive").unwrap(),
+                value: PlatformValue::Bool(true),
+            }
+        );
+        assert_eq!(
+            AttrValue::from_str("gpu_mux=0").unwrap().value,
+            PlatformValue::Mode(GpuMode::Discrete)
+        );
+        assert_eq!(
+            AttrValue::from_str("ppt_pl1_spl=80").unwrap(),
+            AttrValue {
+                attr: PlatformAttr::Tunable(PlatformTunable::PptPl1Spl),
+                value: PlatformValue::Number(80),
+            }
+        );
+        assert!(AttrValue::from_str("mini_led_mode=2").is_err());
+        assert!(AttrValue::from_str("ppt_pl1_spl").is_err());
+        assert!(AttrValue::from_str("/=1").is_err());
+    }
+
+    #[test]
+    fn unknown_names_are_firmware_attrs() {
+        assert_eq!(
+            AttrValue::from_str("asus-armoury/boot_sound=1").unwrap(),
+            AttrValue {
+                attr: PlatformAttr::Firmware {
+                    device: Some("asus-armoury".to_owned()),
+                    name: "boot_sound".to_owned(),
+                },
+                value: PlatformValue::Text("1".to_owned()),
+            }
+        );
+        let attr = PlatformAttr::from_str("screen_auto_brightness").unwrap();
+        assert_eq!(
+            attr,
+            PlatformAttr::Firmware {
+                device: None,
+                name: "screen_auto_brightness".to_owned(),
+            }
+        );
+        assert_eq!(attr.to_string(), "screen_auto_brightness");
+        assert!(attr.is_supported(&RogBiosSupportedFunctions::default()));
+    }
+
+    #[test]
+    fn only_supported_attrs() {
+        let supported = RogBiosSupportedFunctions {
+            panel_overdrive: true,
+            mini_led_mode: true,
+            tunables: vec![PlatformTunable::NvTempTarget],
+            ..Default::default()
+        };
+        let names: Vec<String> = PlatformAttr::supported(&supported)
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(
+            names,
+            ["panel_overdrive", "mini_led_mode", "nv_temp_target"]
+        );
+        let gpu_mux = PlatformAttr::from_str("gpu_mux").unwrap();
+        assert!(!gpu_mux.is_supported(&supported));
+    }
+
+    #[test]
+    fn switch_names_parse() {
+        for (i, switch) in SWITCHES.iter().enumerate() {
+            assert_eq!(
+                PlatformAttr::from_str(switch.name).unwrap(),
+                PlatformAttr::Switch(&SWITCHES[i])
+            );
+        }
+    }
+}
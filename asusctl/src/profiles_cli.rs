@@ -1,4 +1,7 @@
+use std::str::FromStr;
+
 use gumdrop::Options;
+use rog_profiles::error::ProfileError;
 use rog_profiles::fan_curve_set::CurveData;
 use rog_profiles::{FanCurvePU, Profile};
 
@@ -18,6 +21,56 @@ pub struct ProfileCommand {
 
     #[options(meta = "", help = "set the active profile")]
     pub profile_set: Option<Profile>,
+
+    #[options(help = "show the profile cycle order and skip lists for AC and battery")]
+    pub cycle_get: bool,
+
+    #[options(
+        meta = "",
+        help = "set the order `--next` cycles in on AC, e.g: quiet,balanced,performance. An \
+                empty list uses the kernel order"
+    )]
+    pub cycle_ac: Option<ProfileList>,
+
+    #[options(meta = "", help = "set the profiles `--next` skips on AC, e.g: quiet")]
+    pub skip_ac: Option<ProfileList>,
+
+    #[options(
+        meta = "",
+        help = "set the order `--next` cycles in on battery. An empty list uses the kernel order"
+    )]
+    pub cycle_bat: Option<ProfileList>,
+
+    #[options(
+        meta = "",
+        help = "set the profiles `--next` skips on battery, e.g: performance"
+    )]
+    pub skip_bat: Option<ProfileList>,
+}
+
+impl ProfileCommand {
+    pub fn cycle_set(&self) -> bool {
+        self.cycle_ac.is_some()
+            || self.skip_ac.is_some()
+            || self.cycle_bat.is_some()
+            || self.skip_bat.is_some()
+    }
+}
+
+/// A comma separated list of profiles
+#[derive(Debug, Clone, Default)]
+pub struct ProfileList(pub Vec<Profile>);
+
+impl FromStr for ProfileList {
+    type Err = ProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = Vec::new();
+        for p in s.split(',').filter(|p| !p.trim().is_empty()) {
+            list.push(Profile::from_str(p)?);
+        }
+        Ok(Self(list))
+    }
 }
 
 #[derive(Debug, Clone, Options)]
@@ -0,0 +1,310 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gumdrop::Options;
+use rog_aura::aura_detection::{LaptopLedData, LedSupportDb};
+use rog_dbus::RogDbusClientBlocking;
+
+const DMI_PATH: &str = "/sys/class/dmi/id";
+/// DMI fields that identify the model
+const DMI_FIELDS: [&str; 11] = [
+    "sys_vendor",
+    "product_name",
+    "product_family",
+    "product_version",
+    "product_sku",
+    "board_vendor",
+    "board_name",
+    "board_version",
+    "bios_vendor",
+    "bios_version",
+    "bios_date",
+];
+/// DMI fields that identify the single laptop, only readable by root. These
+/// are never included and are redacted from everything else.
+const DMI_SECRETS: [&str; 4] = [
+    "product_serial",
+    "board_serial",
+    "chassis_serial",
+    "product_uuid",
+];
+/// Directories of attributes to list with their values
+const ATTRIBUTE_DIRS: [&str; 2] = ["/sys/devices/platform/asus-nb-wmi", "/sys/firmware/acpi"];
+const HWMON_PATH: &str = "/sys/class/hwmon";
+const CONFIG_PATH: &str = "/etc/asusd";
+const REDACTED: &str = "<redacted>";
+/// Default number of asusd journal entries to include
+const DEFAULT_EVENTS: u32 = 200;
+
+#[derive(Options)]
+pub struct SupportBundleCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "",
+        help = "file to write, default asusctl-support-<time>.tar.gz"
+    )]
+    pub output: Option<String>,
+    #[options(
+        meta = "",
+        help = "number of asusd log entries to include, default 200"
+    )]
+    pub events: Option<u32>,
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_owned())
+}
+
+/// Serial numbers, user and host names which must not leave the machine
+fn secrets() -> Vec<String> {
+    let mut secrets: Vec<String> = DMI_SECRETS
+        .iter()
+        .filter_map(|f| read_trimmed(&Path::new(DMI_PATH).join(f)))
+        .collect();
+    for var in ["USER", "SUDO_USER", "LOGNAME"] {
+        if let Ok(user) = std::env::var(var) {
+            secrets.push(user);
+        }
+    }
+    if let Some(host) = read_trimmed(Path::new("/proc/sys/kernel/hostname")) {
+        secrets.push(host);
+    }
+    // Names of home directories are user names too
+    for home in fs::read_dir("/home").into_iter().flatten().flatten() {
+        secrets.push(home.file_name().to_string_lossy().to_string());
+    }
+    // Short values would redact unrelated text, and "root" is not private
+    secrets.retain(|s| s.len() >= 3 && s != "root");
+    // Longest first so a secret containing another is fully redacted
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    secrets.dedup();
+    secrets
+}
+
+/// Replace each secret where it is a whole word, so that a user named `ann`
+/// doesn't redact `announce`
+fn redact(text: &str, secrets: &[String]) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut text = text.to_owned();
+    for secret in secrets {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, _) in text.match_indices(secret.as_str()) {
+            let end = start + secret.len();
+            if start < last
+                || is_word(text[..start].chars().next_back())
+                || is_word(text[end..].chars().next())
+            {
+                continue;
+            }
+            out.push_str(&text[last..start]);
+            out.push_str(REDACTED);
+            last = end;
+        }
+        out.push_str(&text[last..]);
+        text = out;
+    }
+    text
+}
+
+fn system_info() -> String {
+    let mut out = String::new();
+    writeln!(out, "asusctl version: {}", env!("CARGO_PKG_VERSION")).ok();
+    for file in ["/proc/sys/kernel/osrelease", "/proc/version"] {
+        let value = read_trimmed(Path::new(file)).unwrap_or_default();
+        writeln!(out, "{file}: {value}").ok();
+    }
+    for field in DMI_FIELDS {
+        let value = read_trimmed(&Path::new(DMI_PATH).join(field)).unwrap_or_default();
+        writeln!(out, "{field}: {value}").ok();
+    }
+    out
+}
+
+fn daemon_info() -> (String, String) {
+    let dbus = match RogDbusClientBlocking::new() {
+        Ok((dbus, _)) => dbus,
+        Err(e) => {
+            let err = format!("asusd could not be reached: {e}\n");
+            let probes: String = asusd::diagnostics::run_probes()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            return (err.clone(), format!("{err}Checked by asusctl:\n{probes}"));
+        }
+    };
+    let supported = match dbus.proxies().supported().supported_functions() {
+        Ok(supported) => supported.to_string(),
+        Err(e) => format!("asusd could not be reached: {e}\n"),
+    };
+    let probes = match dbus.proxies().supported().diagnostics() {
+        Ok(probes) => probes.iter().map(ToString::to_string).collect(),
+        Err(e) => format!("asusd could not be reached: {e}\n"),
+    };
+    (supported, probes)
+}
+
+fn led_support() -> String {
+    let board_name = read_trimmed(&Path::new(DMI_PATH).join("board_name")).unwrap_or_default();
+    let family = read_trimmed(&Path::new(DMI_PATH).join("product_family")).unwrap_or_default();
+    let matched = LedSupportDb::load()
+        .map(|db| db.explain(&board_name, &family))
+        .filter(|m| m.is_match());
+    let mut out = String::new();
+    match matched {
+        Some(matched) => {
+            writeln!(out, "// Matched entry for {board_name}").ok();
+            for line in matched.to_string().lines() {
+                writeln!(out, "// {line}").ok();
+            }
+            let data = matched.data;
+            writeln!(
+                out,
+                "// Layout file: {}_US.ron",
+                if data.layout_name.is_empty() {
+                    "ga401q"
+                } else {
+                    &data.layout_name
+                }
+            )
+            .ok();
+            let pretty = ron::ser::PrettyConfig::new();
+            writeln!(
+                out,
+                "{}",
+                ron::ser::to_string_pretty(&data, pretty).unwrap_or_default()
+            )
+            .ok();
+        }
+        None => {
+            writeln!(out, "// No entry for {board_name}, using defaults").ok();
+            writeln!(out, "// {:?}", LaptopLedData::default()).ok();
+        }
+    }
+    out
+}
+
+/// The name and value of each readable file in `dir`, not recursive
+fn list_attributes(dir: &Path, out: &mut String) {
+    writeln!(out, "{}:", dir.display()).ok();
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match fs::read_to_string(&path) {
+            Ok(value) => writeln!(out, "\t{name}: {}", value.trim()).ok(),
+            Err(e) => writeln!(out, "\t{name}: <{e}>").ok(),
+        };
+    }
+}
+
+fn attributes() -> String {
+    let mut out = String::new();
+    for dir in ATTRIBUTE_DIRS {
+        list_attributes(Path::new(dir), &mut out);
+    }
+    let mut hwmons: Vec<PathBuf> = fs::read_dir(HWMON_PATH)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    hwmons.sort();
+    for hwmon in hwmons {
+        list_attributes(&hwmon, &mut out);
+    }
+    out
+}
+
+fn journal(events: u32) -> String {
+    match Command::new("journalctl")
+        .args(["-b", "-u", "asusd", "--no-pager", "-n"])
+        .arg(events.to_string())
+        .output()
+    {
+        Ok(out) => String::from_utf8_lossy(&out.stdout).to_string(),
+        Err(e) => format!("journalctl failed: {e}\n"),
+    }
+}
+
+/// Write a tarball of everything needed to triage an issue. Returns the path
+/// written.
+pub fn write_bundle(cmd: &SupportBundleCommand) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let name = format!("asusctl-support-{secs}");
+    let output = cmd
+        .output
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("{name}.tar.gz")));
+    // A new private directory with a random name, so nothing written can be
+    // redirected by links placed in advance
+    let staging = tempfile::Builder::new()
+        .prefix("asusctl-support-")
+        .tempdir()?;
+    let dir = staging.path().join(&name);
+    fs::create_dir(&dir)?;
+    fs::create_dir(dir.join("etc-asusd"))?;
+
+    let secrets = secrets();
+    let write = |file: &str, contents: &str| fs::write(dir.join(file), redact(contents, &secrets));
+
+    write("system.txt", &system_info())?;
+    let (supported, probes) = daemon_info();
+    write("supported.txt", &supported)?;
+    write("doctor.txt", &probes)?;
+    write("led_support.ron", &led_support())?;
+    write("attributes.txt", &attributes())?;
+    write("asusd.log", &journal(cmd.events.unwrap_or(DEFAULT_EVENTS)))?;
+    for entry in fs::read_dir(CONFIG_PATH).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if let Ok(contents) = fs::read_to_string(&path) {
+            let file = format!(
+                "etc-asusd/{}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            );
+            write(&file, &contents)?;
+        }
+    }
+
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(&output)
+        .arg("-C")
+        .arg(staging.path())
+        .arg(&name)
+        .status();
+    staging.close().ok();
+    if !status?.success() {
+        return Err(format!("tar failed to write {}", output.display()).into());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redact;
+
+    #[test]
+    fn redact_secrets() {
+        let secrets = vec!["N3CV1234567".to_owned(), "alice".to_owned()];
+        assert_eq!(
+            redact("serial N3CV1234567 in /home/alice/.config", &secrets),
+            "serial <redacted> in /home/<redacted>/.config"
+        );
+        assert_eq!(
+            redact("alice-laptop: malice, alice_1, alice2 and alice", &secrets),
+            "<redacted>-laptop: malice, alice_1, alice2 and <redacted>"
+        );
+    }
+}
@@ -6,6 +6,7 @@ use rog_anime::{ActionLoader, AnimTime, AnimeType, Fade, Sequences as AnimeSeque
 use rog_aura::advanced::LedCode;
 use rog_aura::effects::{AdvancedEffects as AuraSequences, Breathe, DoomFlicker, Effect, Static};
 use rog_aura::{Colour, Speed};
+use rog_profiles::app_profile::AppProfileRule;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::error::Error;
@@ -210,6 +211,8 @@ pub struct ConfigBase {
     pub active_anime: Option<String>,
     /// Name of active aura config file in the user config directory
     pub active_aura: Option<String>,
+    /// Serve the OpenRGB SDK on this port of localhost, OpenRGB uses 6742
+    pub openrgb_port: Option<u16>,
 }
 
 impl StdConfig for ConfigBase {
@@ -217,6 +220,7 @@ impl StdConfig for ConfigBase {
         Self {
             active_anime: Some("anime-default".to_owned()),
             active_aura: Some("aura-default".to_owned()),
+            openrgb_port: None,
         }
     }
 
@@ -230,3 +234,27 @@ impl StdConfig for ConfigBase {
 }
 
 impl StdConfigLoad for ConfigBase {}
+
+/// Per-application profile rules. Earlier rules take priority when several
+/// matching processes are running.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigAppProfiles {
+    pub rules: Vec<AppProfileRule>,
+}
+
+impl StdConfig for ConfigAppProfiles {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "rog-app-profiles.ron".to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        root_conf_dir()
+    }
+}
+
+impl StdConfigLoad for ConfigAppProfiles {}
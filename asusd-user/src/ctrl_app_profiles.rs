@@ -0,0 +1,261 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use config_traits::StdConfig;
+use log::{info, warn};
+use rog_dbus::RogDbusClientBlocking;
+use rog_profiles::app_profile::AppProfileRule;
+use rog_profiles::Profile;
+use zbus::dbus_interface;
+use zbus::zvariant::ObjectPath;
+
+use crate::config::ConfigAppProfiles;
+use crate::error::Error;
+
+const PROC_DIR: &str = "/proc";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The system state to return to once no rule matches any more
+#[derive(Debug, Clone, Copy)]
+struct SavedState {
+    profile: Profile,
+    /// Profile and prior enabled state of its fan curves, if a rule changed
+    /// them
+    fan_curves: Option<(Profile, bool)>,
+    /// Prior panel overdrive state, if a rule changed it
+    panel_od: Option<bool>,
+}
+
+/// Find the highest priority rule with a running process by scanning `/proc`
+fn find_running_rule(rules: &[AppProfileRule]) -> Option<AppProfileRule> {
+    if rules.is_empty() {
+        return None;
+    }
+
+    let mut found: Option<usize> = None;
+    for entry in fs::read_dir(PROC_DIR).into_iter().flatten().flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+
+        let path = entry.path();
+        let comm = fs::read_to_string(path.join("comm")).unwrap_or_default();
+        // Will fail for processes not owned by the user, comm is still checked
+        let exe = fs::read_link(path.join("exe")).ok();
+        if let Some(idx) = rules
+            .iter()
+            .position(|r| r.matches(exe.as_deref(), comm.trim_end()))
+        {
+            if found.map(|f| idx < f).unwrap_or(true) {
+                found = Some(idx);
+            }
+            if idx == 0 {
+                break;
+            }
+        }
+    }
+    found.map(|idx| rules[idx].clone())
+}
+
+/// Polls the running processes and switches the system daemon state when a
+/// process matching one of the configured rules starts or exits
+pub struct AppProfileWatcher<'a> {
+    config: Arc<Mutex<ConfigAppProfiles>>,
+    client: RogDbusClientBlocking<'a>,
+    active: Arc<Mutex<Option<AppProfileRule>>>,
+    saved: Option<SavedState>,
+}
+
+impl AppProfileWatcher<'static> {
+    pub fn new(
+        config: Arc<Mutex<ConfigAppProfiles>>,
+        client: RogDbusClientBlocking<'static>,
+        active: Arc<Mutex<Option<AppProfileRule>>>,
+    ) -> Self {
+        Self {
+            config,
+            client,
+            active,
+            saved: None,
+        }
+    }
+
+    /// Blocking loop, should be run in its own thread. Returns once `stop` is
+    /// set, after restoring the state from before any active rule.
+    pub fn run(&mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            if let Err(e) = self.poll() {
+                warn!("AppProfileWatcher: {e}");
+            }
+            sleep(POLL_INTERVAL);
+        }
+
+        if let Err(e) = self.restore() {
+            warn!("AppProfileWatcher: could not restore state on exit: {e}");
+        }
+        if let Ok(mut active) = self.active.lock() {
+            *active = None;
+        }
+    }
+
+    fn poll(&mut self) -> Result<(), Error> {
+        let rules = self
+            .config
+            .lock()
+            .map_err(|_| Error::AppProfilesLockFail)?
+            .rules
+            .clone();
+        let rule = find_running_rule(&rules);
+
+        let active = self.active.clone();
+        let mut active = active.lock().map_err(|_| Error::AppProfilesLockFail)?;
+        // A failed restore leaves the saved state in place with no active rule
+        let restore_pending = active.is_none() && self.saved.is_some();
+        if *active == rule && !restore_pending {
+            return Ok(());
+        }
+
+        *active = None;
+        self.restore()?;
+        if let Some(rule) = rule {
+            info!("AppProfileWatcher: {} is running, applying rule", rule.exe);
+            self.apply(&rule)?;
+            *active = Some(rule);
+        }
+        Ok(())
+    }
+
+    /// Store the current state then apply the rule
+    fn apply(&mut self, rule: &AppProfileRule) -> Result<(), Error> {
+        let profile = self.client.proxies().profile();
+        let platform = self.client.proxies().rog_bios();
+
+        let fan_curves = if rule.fan_curves {
+            let enabled = profile
+                .fan_curve_data(rule.profile)?
+                .iter()
+                .any(|c| c.enabled);
+            Some((rule.profile, enabled))
+        } else {
+            None
+        };
+        let panel_od = if rule.panel_od {
+            Some(platform.panel_od()?)
+        } else {
+            None
+        };
+        self.saved = Some(SavedState {
+            profile: profile.active_profile()?,
+            fan_curves,
+            panel_od,
+        });
+
+        profile.set_active_profile(rule.profile)?;
+        if rule.fan_curves {
+            profile.set_fan_curves_enabled(rule.profile, true)?;
+        }
+        if rule.panel_od {
+            platform.set_panel_od(true)?;
+        }
+        Ok(())
+    }
+
+    /// Return to the state stored before the last rule was applied. On error
+    /// the state is kept so the next poll tries again.
+    fn restore(&mut self) -> Result<(), Error> {
+        if let Some(saved) = self.saved {
+            info!("AppProfileWatcher: restoring previous state");
+            let profile = self.client.proxies().profile();
+            if let Some((fan_profile, enabled)) = saved.fan_curves {
+                profile.set_fan_curves_enabled(fan_profile, enabled)?;
+            }
+            if let Some(panel_od) = saved.panel_od {
+                self.client.proxies().rog_bios().set_panel_od(panel_od)?;
+            }
+            profile.set_active_profile(saved.profile)?;
+            self.saved = None;
+        }
+        Ok(())
+    }
+}
+
+pub struct CtrlAppProfiles {
+    config: Arc<Mutex<ConfigAppProfiles>>,
+    active: Arc<Mutex<Option<AppProfileRule>>>,
+}
+
+impl CtrlAppProfiles {
+    pub fn new(
+        config: Arc<Mutex<ConfigAppProfiles>>,
+        active: Arc<Mutex<Option<AppProfileRule>>>,
+    ) -> Self {
+        Self { config, active }
+    }
+
+    pub async fn add_to_server(self, server: &mut zbus::Connection) {
+        server
+            .object_server()
+            .at(
+                &ObjectPath::from_str_unchecked("/org/asuslinux/AppProfiles"),
+                self,
+            )
+            .await
+            .map_err(|err| {
+                info!("CtrlAppProfiles: add_to_server {}", err);
+                err
+            })
+            .ok();
+    }
+}
+
+#[dbus_interface(name = "org.asuslinux.Daemon")]
+impl CtrlAppProfiles {
+    /// Fetch all rules, in priority order
+    pub fn rules(&self) -> zbus::fdo::Result<Vec<AppProfileRule>> {
+        let config = self.config.lock().map_err(|_| Error::AppProfilesLockFail)?;
+        Ok(config.rules.clone())
+    }
+
+    /// Add a rule, replacing an existing rule for the same executable
+    pub fn set_rule(&mut self, rule: AppProfileRule) -> zbus::fdo::Result<()> {
+        if rule.exe.trim().is_empty() {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Rule executable can not be empty".into(),
+            ));
+        }
+        let mut config = self.config.lock().map_err(|_| Error::AppProfilesLockFail)?;
+        if let Some(existing) = config.rules.iter_mut().find(|r| r.exe == rule.exe) {
+            *existing = rule;
+        } else {
+            config.rules.push(rule);
+        }
+        config.write();
+        Ok(())
+    }
+
+    /// Remove the rule for this executable
+    pub fn remove_rule(&mut self, exe: &str) -> zbus::fdo::Result<()> {
+        let mut config = self.config.lock().map_err(|_| Error::AppProfilesLockFail)?;
+        let len = config.rules.len();
+        config.rules.retain(|r| r.exe != exe);
+        if config.rules.len() == len {
+            return Err(zbus::fdo::Error::InvalidArgs(format!("No rule for {exe}")));
+        }
+        config.write();
+        Ok(())
+    }
+
+    /// The executable of the rule currently applied, empty if none
+    pub fn active_rule(&self) -> zbus::fdo::Result<String> {
+        let active = self.active.lock().map_err(|_| Error::AppProfilesLockFail)?;
+        Ok(active.as_ref().map(|r| r.exe.clone()).unwrap_or_default())
+    }
+}
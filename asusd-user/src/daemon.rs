@@ -1,16 +1,20 @@
 use std::io::Write;
 use std::path::PathBuf;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use asusd_user::config::*;
 use asusd_user::ctrl_anime::{CtrlAnime, CtrlAnimeInner};
+use asusd_user::ctrl_app_profiles::{AppProfileWatcher, CtrlAppProfiles};
+use asusd_user::openrgb::{self, KeyboardDevice};
 use asusd_user::DBUS_NAME;
 use config_traits::{StdConfig, StdConfigLoad};
-use rog_anime::usb::get_anime_type;
 use rog_aura::aura_detection::LaptopLedData;
 use rog_aura::layouts::KeyLayout;
 use rog_dbus::RogDbusClientBlocking;
+use rog_platform::quirks::get_anime_type;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
 use smol::Executor;
 use zbus::Connection;
 
@@ -40,6 +44,32 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let executor = Executor::new();
 
+    // Create server
+    let mut connection = smol::block_on(Connection::session())?;
+    smol::block_on(connection.request_name(DBUS_NAME))?;
+
+    // Per-application profile rules, polls running processes in own thread
+    if supported.platform_profile.platform_profile {
+        let app_config = Arc::new(Mutex::new(ConfigAppProfiles::new().load()));
+        let active = Arc::new(Mutex::new(None));
+        let (client, _) = RogDbusClientBlocking::new()?;
+        let mut watcher = AppProfileWatcher::new(app_config.clone(), client, active.clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher_stop = stop.clone();
+        let watcher = std::thread::spawn(move || watcher.run(&watcher_stop));
+
+        // Put back the profile a rule replaced before exiting
+        let mut signals = Signals::new([SIGINT, SIGTERM])?;
+        std::thread::spawn(move || {
+            if signals.forever().next().is_some() {
+                stop.store(true, Ordering::Relaxed);
+                watcher.join().ok();
+                std::process::exit(0);
+            }
+        });
+        smol::block_on(CtrlAppProfiles::new(app_config, active).add_to_server(&mut connection));
+    }
+
     let early_return = Arc::new(AtomicBool::new(false));
     // Set up the anime data and run loop/thread
     if supported.anime_ctrl.0 {
@@ -49,12 +79,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let anime = anime_config.create(anime_type)?;
             let anime_config = Arc::new(Mutex::new(anime_config));
 
+            let mut connection = connection.clone();
             executor
                 .spawn(async move {
-                    // Create server
-                    let mut connection = Connection::session().await.unwrap();
-                    connection.request_name(DBUS_NAME).await.unwrap();
-
                     // Inner behind mutex required for thread safety
                     let inner = Arc::new(Mutex::new(
                         CtrlAnimeInner::new(anime, client, early_return.clone()).unwrap(),
@@ -75,11 +102,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // if supported.keyboard_led.per_key_led_mode {
-    if let Some(cfg) = config.active_aura {
-        let mut aura_config = ConfigAura::new().set_name(cfg).load();
-        // let baord_name = std::fs::read_to_string(BOARD_NAME)?;
-
+    if config.active_aura.is_some() || config.openrgb_port.is_some() {
         let led_support = LaptopLedData::get_data();
+        let advanced_type = led_support.advanced_type.clone();
 
         let layout = KeyLayout::find_layout(led_support, PathBuf::from(DATA_DIR))
             .map_err(|e| {
@@ -87,26 +112,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             })
             .unwrap_or_else(|_| KeyLayout::default_layout());
 
-        executor
-            .spawn(async move {
-                // Create server
-                let (client, _) = RogDbusClientBlocking::new().unwrap();
-                // let connection = Connection::session().await.unwrap();
-                // connection.request_name(DBUS_NAME).await.unwrap();
-
-                loop {
-                    aura_config.aura.next_state(&layout);
-                    let packets = aura_config.aura.create_packets();
-
-                    client
-                        .proxies()
-                        .led()
-                        .direct_addressing_raw(packets)
-                        .unwrap();
-                    std::thread::sleep(std::time::Duration::from_millis(33));
-                }
-            })
-            .detach();
+        if let Some(port) = config.openrgb_port {
+            let (client, _) = RogDbusClientBlocking::new()?;
+            KeyboardDevice::new(&layout, &advanced_type, &client)
+                .and_then(|device| openrgb::serve(port, device))
+                .map_err(|e| println!("OpenRGB server: {e}"))
+                .ok();
+        }
+
+        if let Some(cfg) = config.active_aura {
+            let mut aura_config = ConfigAura::new().set_name(cfg).load();
+            // let baord_name = std::fs::read_to_string(BOARD_NAME)?;
+
+            executor
+                .spawn(async move {
+                    // Create server
+                    let (client, _) = RogDbusClientBlocking::new().unwrap();
+                    // let connection = Connection::session().await.unwrap();
+                    // connection.request_name(DBUS_NAME).await.unwrap();
+
+                    loop {
+                        aura_config.aura.next_state(&layout);
+                        let packets = aura_config.aura.create_packets();
+
+                        client
+                            .proxies()
+                            .led()
+                            .direct_addressing_raw(packets)
+                            .unwrap();
+                        std::thread::sleep(std::time::Duration::from_millis(33));
+                    }
+                })
+                .detach();
+        }
     }
     // }
 
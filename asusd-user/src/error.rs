@@ -7,8 +7,11 @@ pub enum Error {
     Io(std::io::Error),
     ConfigLoadFail,
     ConfigLockFail,
+    /// The app profile rules could not be locked
+    AppProfilesLockFail,
     XdgVars,
     Anime(AnimeError),
+    Zbus(zbus::Error),
 }
 
 impl fmt::Display for Error {
@@ -18,8 +21,10 @@ impl fmt::Display for Error {
             Error::Io(err) => write!(f, "Failed to open: {}", err),
             Error::ConfigLoadFail => write!(f, "Failed to load user config"),
             Error::ConfigLockFail => write!(f, "Failed to lock user config"),
+            Error::AppProfilesLockFail => write!(f, "Failed to lock app profile rules"),
             Error::XdgVars => write!(f, "XDG environment vars appear unset"),
             Error::Anime(err) => write!(f, "Anime error: {}", err),
+            Error::Zbus(err) => write!(f, "Dbus error: {}", err),
         }
     }
 }
@@ -38,8 +43,17 @@ impl From<AnimeError> for Error {
     }
 }
 
+impl From<zbus::Error> for Error {
+    fn from(err: zbus::Error) -> Self {
+        Error::Zbus(err)
+    }
+}
+
 impl From<Error> for zbus::fdo::Error {
     fn from(err: Error) -> Self {
-        zbus::fdo::Error::Failed(format!("Anime zbus error: {}", err))
+        match err {
+            Error::AppProfilesLockFail => zbus::fdo::Error::Failed(err.to_string()),
+            _ => zbus::fdo::Error::Failed(format!("Anime zbus error: {}", err)),
+        }
     }
 }
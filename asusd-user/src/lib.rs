@@ -4,6 +4,10 @@ pub mod error;
 
 pub mod ctrl_anime;
 
+pub mod ctrl_app_profiles;
+
+pub mod openrgb;
+
 pub mod zbus_anime;
 
 pub static DBUS_NAME: &str = "org.asuslinux.Daemon";
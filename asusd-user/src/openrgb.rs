@@ -0,0 +1,877 @@
+//! A server for the OpenRGB network SDK on localhost, so that OpenRGB can
+//! control the keyboard while asusd stays the only owner of the HID device.
+//!
+//! The keyboard is one device. Its LEDs and zones come from the `KeyLayout`
+//! and `AdvancedAuraType`, and colours set by the client are written with
+//! `direct_addressing_raw` in a `Direct` mode. The builtin modes of asusd
+//! follow, these are set with `set_led_mode`.
+//!
+//! Protocol versions 0 and 1 are implemented, a client uses the lower version
+//! of itself and the server.
+
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info, warn};
+use rog_aura::advanced::{LedCode, LedUsbPackets};
+use rog_aura::layouts::{KeyLayout, KeyShape};
+use rog_aura::{AdvancedAuraType, AuraEffect, AuraModeNum, Colour, Direction, Speed};
+use rog_dbus::RogDbusClientBlocking;
+
+use crate::error::Error;
+
+const MAGIC: &[u8; 4] = b"ORGB";
+const PROTOCOL_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 16;
+/// Larger packets are treated as a broken client
+const MAX_PACKET_SIZE: usize = 1 << 20;
+
+const REQUEST_CONTROLLER_COUNT: u32 = 0;
+const REQUEST_CONTROLLER_DATA: u32 = 1;
+const REQUEST_PROTOCOL_VERSION: u32 = 40;
+const SET_CLIENT_NAME: u32 = 50;
+const RESIZE_ZONE: u32 = 1000;
+const UPDATE_LEDS: u32 = 1050;
+const UPDATE_ZONE_LEDS: u32 = 1051;
+const UPDATE_SINGLE_LED: u32 = 1052;
+const SET_CUSTOM_MODE: u32 = 1100;
+const UPDATE_MODE: u32 = 1101;
+const SAVE_MODE: u32 = 1102;
+
+const DEVICE_TYPE_KEYBOARD: i32 = 5;
+
+const MODE_FLAG_HAS_SPEED: u32 = 1 << 0;
+const MODE_FLAG_HAS_DIRECTION_LR: u32 = 1 << 1;
+const MODE_FLAG_HAS_DIRECTION_UD: u32 = 1 << 2;
+const MODE_FLAG_HAS_PER_LED_COLOR: u32 = 1 << 5;
+const MODE_FLAG_HAS_MODE_SPECIFIC_COLOR: u32 = 1 << 6;
+
+const COLOR_MODE_NONE: u32 = 0;
+const COLOR_MODE_PER_LED: u32 = 1;
+const COLOR_MODE_MODE_SPECIFIC: u32 = 2;
+
+const ZONE_TYPE_SINGLE: i32 = 0;
+const ZONE_TYPE_LINEAR: i32 = 1;
+const ZONE_TYPE_MATRIX: i32 = 2;
+/// An empty cell of a zone matrix
+const NO_LED: u32 = u32::MAX;
+
+/// The mode value of `Direct`, builtin modes use their `AuraModeNum`
+const DIRECT_MODE_VALUE: i32 = 0xff;
+
+const SPEEDS: [Speed; 3] = [Speed::Low, Speed::Med, Speed::High];
+/// In the order of the OpenRGB directions
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Left,
+    Direction::Right,
+    Direction::Up,
+    Direction::Down,
+];
+
+fn invalid_data(msg: &str) -> Error {
+    Error::Io(io::Error::new(ErrorKind::InvalidData, msg))
+}
+
+fn colour_to_u32(c: Colour) -> u32 {
+    u32::from_le_bytes([c.r, c.g, c.b, 0])
+}
+
+fn colour_from_u32(c: u32) -> Colour {
+    let [r, g, b, _] = c.to_le_bytes();
+    Colour { r, g, b }
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u16(&mut self, v: u16) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn i32(&mut self, v: i32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    /// Length including the nul terminator, then the string
+    fn string(&mut self, s: &str) {
+        self.u16(s.len() as u16 + 1);
+        self.0.extend_from_slice(s.as_bytes());
+        self.0.push(0);
+    }
+
+    fn colour(&mut self, c: Colour) {
+        self.u32(colour_to_u32(c));
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(invalid_data("OpenRGB packet is too short"));
+        }
+        let (data, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(data)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.u16()? as usize;
+        let b = self.take(len)?;
+        Ok(String::from_utf8_lossy(b).trim_end_matches('\0').to_owned())
+    }
+
+    fn colours(&mut self) -> Result<Vec<Colour>, Error> {
+        let count = self.u16()?;
+        (0..count)
+            .map(|_| self.u32().map(colour_from_u32))
+            .collect()
+    }
+}
+
+/// The mode settings sent by the client with `UpdateMode`
+struct ModeUpdate {
+    speed: u32,
+    direction: u32,
+    colours: Vec<Colour>,
+}
+
+impl ModeUpdate {
+    fn read(r: &mut Reader<'_>) -> Result<Self, Error> {
+        let _name = r.string()?;
+        let _value = r.i32()?;
+        let _flags = r.u32()?;
+        let _speed_min = r.u32()?;
+        let _speed_max = r.u32()?;
+        let _colours_min = r.u32()?;
+        let _colours_max = r.u32()?;
+        let speed = r.u32()?;
+        let direction = r.u32()?;
+        let _colour_mode = r.u32()?;
+        let colours = r.colours()?;
+        Ok(Self {
+            speed,
+            direction,
+            colours,
+        })
+    }
+}
+
+struct Mode {
+    /// `None` for `Direct`
+    builtin: Option<AuraModeNum>,
+    /// The settings of a builtin mode
+    effect: AuraEffect,
+}
+
+impl Mode {
+    /// If the mode has a speed and direction, and the number of colours it
+    /// uses
+    fn caps(&self) -> (bool, bool, usize) {
+        match self.builtin {
+            None => (false, false, 0),
+            Some(AuraModeNum::Static) => (false, false, 1),
+            Some(AuraModeNum::Breathe | AuraModeNum::Star) => (true, false, 2),
+            Some(AuraModeNum::Strobe | AuraModeNum::Rain) => (true, false, 0),
+            Some(AuraModeNum::Rainbow) => (true, true, 0),
+            Some(AuraModeNum::Highlight | AuraModeNum::Laser | AuraModeNum::Ripple) => {
+                (true, false, 1)
+            }
+            Some(AuraModeNum::Pulse | AuraModeNum::Comet | AuraModeNum::Flash) => (false, false, 1),
+        }
+    }
+
+    fn write(&self, w: &mut Writer) {
+        let (speed, direction, colours) = self.caps();
+        let mut flags = 0;
+        if speed {
+            flags |= MODE_FLAG_HAS_SPEED;
+        }
+        if direction {
+            flags |= MODE_FLAG_HAS_DIRECTION_LR | MODE_FLAG_HAS_DIRECTION_UD;
+        }
+        let colour_mode = match self.builtin {
+            None => {
+                flags |= MODE_FLAG_HAS_PER_LED_COLOR;
+                COLOR_MODE_PER_LED
+            }
+            Some(_) if colours > 0 => {
+                flags |= MODE_FLAG_HAS_MODE_SPECIFIC_COLOR;
+                COLOR_MODE_MODE_SPECIFIC
+            }
+            Some(_) => COLOR_MODE_NONE,
+        };
+
+        match self.builtin {
+            Some(mode) => {
+                w.string(<&str>::from(&mode));
+                w.i32(mode as i32);
+            }
+            None => {
+                w.string("Direct");
+                w.i32(DIRECT_MODE_VALUE);
+            }
+        }
+        w.u32(flags);
+        w.u32(0);
+        w.u32(if speed { SPEEDS.len() as u32 - 1 } else { 0 });
+        w.u32(colours as u32);
+        w.u32(colours as u32);
+        w.u32(
+            SPEEDS
+                .iter()
+                .position(|s| *s == self.effect.speed)
+                .unwrap_or(1) as u32,
+        );
+        w.u32(
+            DIRECTIONS
+                .iter()
+                .position(|d| *d == self.effect.direction)
+                .unwrap_or(1) as u32,
+        );
+        w.u32(colour_mode);
+        w.u16(colours as u16);
+        for c in [self.effect.colour1, self.effect.colour2]
+            .into_iter()
+            .take(colours)
+        {
+            w.colour(c);
+        }
+    }
+
+    fn update(&mut self, update: &ModeUpdate) {
+        if let Some(speed) = SPEEDS.get(update.speed as usize) {
+            self.effect.speed = *speed;
+        }
+        if let Some(direction) = DIRECTIONS.get(update.direction as usize) {
+            self.effect.direction = *direction;
+        }
+        let mut colours = update.colours.iter();
+        if let Some(c) = colours.next() {
+            self.effect.colour1 = *c;
+        }
+        if let Some(c) = colours.next() {
+            self.effect.colour2 = *c;
+        }
+    }
+}
+
+struct Zone {
+    name: &'static str,
+    kind: i32,
+    start: usize,
+    len: usize,
+    /// Height, width, and the index in the zone of the LED of each cell
+    matrix: Option<(usize, usize, Vec<u32>)>,
+}
+
+/// What a client packet asks of asusd
+enum Action {
+    None,
+    Reply(Vec<u8>),
+    /// Write the colours with `DirectAddressingRaw`
+    Direct(LedUsbPackets),
+    /// Set a builtin mode
+    Mode(AuraEffect),
+}
+
+/// The keyboard as presented to OpenRGB clients, shared by all of them
+pub struct KeyboardDevice {
+    zoned: bool,
+    multizone: bool,
+    leds: Vec<LedCode>,
+    zones: Vec<Zone>,
+    modes: Vec<Mode>,
+    active_mode: usize,
+    colours: Vec<Colour>,
+}
+
+impl KeyboardDevice {
+    pub fn new(
+        layout: &KeyLayout,
+        advanced_type: &AdvancedAuraType,
+        client: &RogDbusClientBlocking<'_>,
+    ) -> Result<Self, Error> {
+        let led = client.proxies().led();
+        Ok(Self::with_modes(
+            layout,
+            advanced_type,
+            led.led_modes()?,
+            led.led_mode()?,
+        ))
+    }
+
+    fn with_modes(
+        layout: &KeyLayout,
+        advanced_type: &AdvancedAuraType,
+        builtin: BTreeMap<AuraModeNum, AuraEffect>,
+        active: AuraModeNum,
+    ) -> Self {
+        let mut device = Self {
+            zoned: false,
+            multizone: false,
+            leds: Vec::new(),
+            zones: Vec::new(),
+            modes: Vec::new(),
+            active_mode: 0,
+            colours: Vec::new(),
+        };
+
+        match advanced_type {
+            AdvancedAuraType::None => {}
+            AdvancedAuraType::PerKey => device.add_per_key(layout),
+            AdvancedAuraType::Zoned(codes) => device.add_zoned(codes),
+        }
+        device.colours = vec![Colour { r: 0, g: 0, b: 0 }; device.leds.len()];
+
+        if !device.leds.is_empty() {
+            device.modes.push(Mode {
+                builtin: None,
+                effect: AuraEffect::default(),
+            });
+        }
+        for (mode, effect) in builtin {
+            if mode == active {
+                device.active_mode = device.modes.len();
+            }
+            device.modes.push(Mode {
+                builtin: Some(mode),
+                effect,
+            });
+        }
+        device
+    }
+
+    /// A matrix zone of the keys in the layout, and a linear zone of any
+    /// lightbar LEDs
+    fn add_per_key(&mut self, layout: &KeyLayout) {
+        let mut lightbar = Vec::new();
+        let mut rows = Vec::new();
+        for row in layout.rows() {
+            let mut cells = Vec::new();
+            for (code, shape) in row.row() {
+                if code.is_lightbar_zone() {
+                    if !lightbar.contains(code) {
+                        lightbar.push(*code);
+                    }
+                    continue;
+                }
+                if code.is_placeholder()
+                    || matches!(shape, KeyShape::Blank { .. })
+                    || !LedUsbPackets::is_addressable(*code, false)
+                {
+                    cells.push(NO_LED);
+                    continue;
+                }
+                let idx = match self.leds.iter().position(|l| l == code) {
+                    Some(idx) => idx,
+                    None => {
+                        self.leds.push(*code);
+                        self.leds.len() - 1
+                    }
+                };
+                cells.push(idx as u32);
+            }
+            if cells.iter().any(|c| *c != NO_LED) {
+                rows.push(cells);
+            }
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut matrix = Vec::with_capacity(rows.len() * width);
+        for mut row in rows.iter().cloned() {
+            row.resize(width, NO_LED);
+            matrix.extend(row);
+        }
+        self.zones.push(Zone {
+            name: "Keyboard",
+            kind: ZONE_TYPE_MATRIX,
+            start: 0,
+            len: self.leds.len(),
+            matrix: Some((rows.len(), width, matrix)),
+        });
+        self.add_lightbar(lightbar);
+    }
+
+    fn add_zoned(&mut self, codes: &[LedCode]) {
+        self.zoned = true;
+        self.multizone = !codes.iter().all(|c| *c == LedCode::SingleZone);
+        let keyboard: Vec<LedCode> = codes
+            .iter()
+            .copied()
+            .filter(|c| c.is_keyboard_zone() || *c == LedCode::SingleZone)
+            .collect();
+        if !keyboard.is_empty() {
+            self.zones.push(Zone {
+                name: "Keyboard",
+                kind: if keyboard.len() == 1 {
+                    ZONE_TYPE_SINGLE
+                } else {
+                    ZONE_TYPE_LINEAR
+                },
+                start: self.leds.len(),
+                len: keyboard.len(),
+                matrix: None,
+            });
+            self.leds.extend(keyboard);
+        }
+        self.add_lightbar(
+            codes
+                .iter()
+                .copied()
+                .filter(LedCode::is_lightbar_zone)
+                .collect(),
+        );
+    }
+
+    fn add_lightbar(&mut self, mut codes: Vec<LedCode>) {
+        codes.retain(|c| LedUsbPackets::is_addressable(*c, self.zoned));
+        if codes.is_empty() {
+            return;
+        }
+        self.zones.push(Zone {
+            name: "Lightbar",
+            kind: ZONE_TYPE_LINEAR,
+            start: self.leds.len(),
+            len: codes.len(),
+            matrix: None,
+        });
+        self.leds.extend(codes);
+    }
+
+    /// The controller data of the device for protocol `version`
+    fn data(&self, version: u32) -> Vec<u8> {
+        let mut w = Writer::default();
+        // Total size, filled in last
+        w.u32(0);
+        w.i32(DEVICE_TYPE_KEYBOARD);
+        w.string("ASUS Aura Keyboard");
+        if version >= 1 {
+            w.string("ASUS");
+        }
+        w.string("Keyboard controlled by asusd");
+        w.string(crate::VERSION);
+        w.string("");
+        w.string("asusd /org/asuslinux/Aura");
+
+        w.u16(self.modes.len() as u16);
+        w.i32(self.active_mode as i32);
+        for mode in &self.modes {
+            mode.write(&mut w);
+        }
+
+        w.u16(self.zones.len() as u16);
+        for zone in &self.zones {
+            w.string(zone.name);
+            w.i32(zone.kind);
+            w.u32(zone.len as u32);
+            w.u32(zone.len as u32);
+            w.u32(zone.len as u32);
+            match &zone.matrix {
+                Some((height, width, map)) => {
+                    w.u16((8 + map.len() * 4) as u16);
+                    w.u32(*height as u32);
+                    w.u32(*width as u32);
+                    for cell in map {
+                        w.u32(*cell);
+                    }
+                }
+                None => w.u16(0),
+            }
+        }
+
+        w.u16(self.leds.len() as u16);
+        for (idx, code) in self.leds.iter().enumerate() {
+            if code.is_lightbar_zone() {
+                w.string(code.into());
+            } else {
+                w.string(&format!("Key: {}", <&str>::from(code)));
+            }
+            w.u32(idx as u32);
+        }
+
+        w.u16(self.colours.len() as u16);
+        for c in &self.colours {
+            w.colour(*c);
+        }
+
+        let size = (w.0.len() as u32).to_le_bytes();
+        w.0[..4].copy_from_slice(&size);
+        w.0
+    }
+
+    fn direct_mode(&self) -> Option<usize> {
+        self.modes.iter().position(|m| m.builtin.is_none())
+    }
+
+    /// Switch to `Direct` and write the colours of all LEDs
+    fn write_direct(&mut self) -> Action {
+        let direct = match self.direct_mode() {
+            Some(direct) => direct,
+            None => return Action::None,
+        };
+        self.active_mode = direct;
+        let mut packets = if self.zoned {
+            LedUsbPackets::new_zoned(self.multizone)
+        } else {
+            LedUsbPackets::new_per_key()
+        };
+        for (code, c) in self.leds.iter().zip(self.colours.iter()) {
+            packets.set(*code, c.r, c.g, c.b);
+        }
+        Action::Direct(packets)
+    }
+
+    fn set_colours(&mut self, start: usize, colours: &[Colour]) {
+        for (led, c) in self.colours.iter_mut().skip(start).zip(colours) {
+            *led = *c;
+        }
+    }
+
+    /// Handle a packet from a client, returning what to do for it
+    fn handle(&mut self, id: u32, data: &[u8], version: &mut u32) -> Result<Action, Error> {
+        let mut r = Reader(data);
+        match id {
+            REQUEST_CONTROLLER_COUNT => return Ok(Action::Reply(1u32.to_le_bytes().to_vec())),
+            REQUEST_CONTROLLER_DATA => {
+                // Older clients don't send the version
+                let requested = r.u32().unwrap_or(*version);
+                return Ok(Action::Reply(self.data(requested.min(PROTOCOL_VERSION))));
+            }
+            REQUEST_PROTOCOL_VERSION => {
+                *version = r.u32()?.min(PROTOCOL_VERSION);
+                return Ok(Action::Reply(PROTOCOL_VERSION.to_le_bytes().to_vec()));
+            }
+            SET_CLIENT_NAME => {
+                let name = String::from_utf8_lossy(data);
+                info!("OpenRGB: client {} connected", name.trim_end_matches('\0'));
+            }
+            UPDATE_LEDS => {
+                let _size = r.u32()?;
+                let colours = r.colours()?;
+                self.set_colours(0, &colours);
+                return Ok(self.write_direct());
+            }
+            UPDATE_ZONE_LEDS => {
+                let _size = r.u32()?;
+                let zone = r.u32()? as usize;
+                let colours = r.colours()?;
+                if let Some(zone) = self.zones.get(zone) {
+                    let (start, len) = (zone.start, zone.len);
+                    self.set_colours(start, &colours[..colours.len().min(len)]);
+                    return Ok(self.write_direct());
+                }
+            }
+            UPDATE_SINGLE_LED => {
+                let led = r.i32()? as usize;
+                let colour = colour_from_u32(r.u32()?);
+                self.set_colours(led, &[colour]);
+                return Ok(self.write_direct());
+            }
+            SET_CUSTOM_MODE => return Ok(self.write_direct()),
+            UPDATE_MODE | SAVE_MODE => {
+                let _size = r.u32()?;
+                let idx = r.i32()? as usize;
+                let update = ModeUpdate::read(&mut r)?;
+                let mode = match self.modes.get_mut(idx) {
+                    Some(mode) => mode,
+                    None => return Err(invalid_data("OpenRGB mode index out of range")),
+                };
+                if mode.builtin.is_some() {
+                    mode.update(&update);
+                    let effect = mode.effect.clone();
+                    self.active_mode = idx;
+                    return Ok(Action::Mode(effect));
+                }
+                return Ok(self.write_direct());
+            }
+            RESIZE_ZONE => debug!("OpenRGB: zones can not be resized"),
+            _ => debug!("OpenRGB: ignoring packet {id}"),
+        }
+        Ok(Action::None)
+    }
+}
+
+/// Listen on `127.0.0.1:port` in a new thread, each client is handled in its
+/// own thread
+pub fn serve(port: u16, device: KeyboardDevice) -> Result<(), Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    info!("OpenRGB: SDK server listening on 127.0.0.1:{port}");
+    let device = Arc::new(Mutex::new(device));
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let device = device.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = run_client(stream, &device) {
+                            warn!("OpenRGB: client error: {e}");
+                        }
+                    });
+                }
+                Err(e) => warn!("OpenRGB: {e}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn run_client(mut stream: TcpStream, device: &Mutex<KeyboardDevice>) -> Result<(), Error> {
+    let (client, _) = RogDbusClientBlocking::new()?;
+    let mut version = 0;
+    let mut header = [0u8; HEADER_SIZE];
+    loop {
+        match stream.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        let mut r = Reader(&header);
+        if r.take(4)? != MAGIC {
+            return Err(invalid_data("OpenRGB packet has no magic"));
+        }
+        let device_idx = r.u32()?;
+        let id = r.u32()?;
+        let size = r.u32()? as usize;
+        if size > MAX_PACKET_SIZE {
+            return Err(invalid_data("OpenRGB packet is too large"));
+        }
+        let mut data = vec![0u8; size];
+        stream.read_exact(&mut data)?;
+
+        let action =
+            device
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .handle(id, &data, &mut version)?;
+        match action {
+            Action::None => {}
+            Action::Reply(reply) => {
+                let mut w = Writer::default();
+                w.0.extend_from_slice(MAGIC);
+                w.u32(device_idx);
+                w.u32(id);
+                w.u32(reply.len() as u32);
+                w.0.extend_from_slice(&reply);
+                stream.write_all(&w.0)?;
+            }
+            Action::Direct(packets) => client
+                .proxies()
+                .led()
+                .direct_addressing_raw(packets.into())
+                .map_err(|e| Error::Zbus(e.into()))?,
+            Action::Mode(effect) => client.proxies().led().set_led_mode(&effect)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use rog_aura::advanced::{LedCode, LedUsbPackets};
+    use rog_aura::layouts::KeyLayout;
+    use rog_aura::{AdvancedAuraType, AuraEffect, AuraModeNum, Colour};
+
+    use super::{
+        Action, KeyboardDevice, Reader, Writer, DEVICE_TYPE_KEYBOARD, DIRECT_MODE_VALUE, NO_LED,
+        UPDATE_LEDS, UPDATE_ZONE_LEDS, ZONE_TYPE_LINEAR, ZONE_TYPE_MATRIX,
+    };
+
+    fn zoned_device() -> KeyboardDevice {
+        let codes = vec![
+            LedCode::ZonedKbLeft,
+            LedCode::ZonedKbRight,
+            LedCode::LightbarLeft,
+        ];
+        KeyboardDevice::with_modes(
+            &KeyLayout::default_layout(),
+            &AdvancedAuraType::Zoned(codes),
+            BTreeMap::from([(AuraModeNum::Static, AuraEffect::default())]),
+            AuraModeNum::Static,
+        )
+    }
+
+    fn colour(r: u8) -> Colour {
+        Colour { r, g: 2, b: 3 }
+    }
+
+    /// Skip a mode, returning its name and value
+    fn read_mode(r: &mut Reader<'_>) -> (String, i32) {
+        let name = r.string().unwrap();
+        let value = r.i32().unwrap();
+        for _ in 0..8 {
+            r.u32().unwrap();
+        }
+        r.colours().unwrap();
+        (name, value)
+    }
+
+    #[test]
+    fn controller_data_layout() {
+        let device = zoned_device();
+        for version in [0, 1] {
+            let data = device.data(version);
+            let mut r = Reader(&data);
+            assert_eq!(r.u32().unwrap() as usize, data.len());
+            assert_eq!(r.i32().unwrap(), DEVICE_TYPE_KEYBOARD);
+            assert_eq!(r.string().unwrap(), "ASUS Aura Keyboard");
+            if version >= 1 {
+                assert_eq!(r.string().unwrap(), "ASUS");
+            }
+            assert_eq!(r.string().unwrap(), "Keyboard controlled by asusd");
+            assert_eq!(r.string().unwrap(), crate::VERSION);
+            assert_eq!(r.string().unwrap(), "");
+            assert_eq!(r.string().unwrap(), "asusd /org/asuslinux/Aura");
+
+            assert_eq!(r.u16().unwrap(), 2);
+            assert_eq!(r.i32().unwrap(), 1);
+            assert_eq!(read_mode(&mut r), ("Direct".to_owned(), DIRECT_MODE_VALUE));
+            assert_eq!(
+                read_mode(&mut r),
+                ("Static".to_owned(), AuraModeNum::Static as i32)
+            );
+
+            assert_eq!(r.u16().unwrap(), 2);
+            for (name, len) in [("Keyboard", 2), ("Lightbar", 1)] {
+                assert_eq!(r.string().unwrap(), name);
+                assert_eq!(r.i32().unwrap(), ZONE_TYPE_LINEAR);
+                for _ in 0..3 {
+                    assert_eq!(r.u32().unwrap(), len);
+                }
+                assert_eq!(r.u16().unwrap(), 0);
+            }
+
+            assert_eq!(r.u16().unwrap(), 3);
+            for (idx, code) in [
+                LedCode::ZonedKbLeft,
+                LedCode::ZonedKbRight,
+                LedCode::LightbarLeft,
+            ]
+            .iter()
+            .enumerate()
+            {
+                let name = <&str>::from(code);
+                if idx < 2 {
+                    assert_eq!(r.string().unwrap(), format!("Key: {name}"));
+                } else {
+                    assert_eq!(r.string().unwrap(), name);
+                }
+                assert_eq!(r.u32().unwrap(), idx as u32);
+            }
+            assert_eq!(r.colours().unwrap(), vec![Colour { r: 0, g: 0, b: 0 }; 3]);
+            assert!(r.0.is_empty());
+        }
+    }
+
+    #[test]
+    fn per_key_matrix_zone() {
+        let layout = KeyLayout::from_file(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../rog-aura/data/layouts/g513i-per-key_US.ron"
+        )))
+        .unwrap();
+        let device = KeyboardDevice::with_modes(
+            &layout,
+            &AdvancedAuraType::PerKey,
+            BTreeMap::new(),
+            AuraModeNum::Static,
+        );
+        assert_eq!(device.zones.len(), 2);
+        let keyboard = &device.zones[0];
+        assert_eq!(keyboard.kind, ZONE_TYPE_MATRIX);
+        let (height, width, map) = keyboard.matrix.clone().unwrap();
+        assert_eq!(map.len(), height * width);
+        assert!(map
+            .iter()
+            .all(|cell| *cell == NO_LED || (*cell as usize) < keyboard.len));
+
+        let lightbar = &device.zones[1];
+        assert_eq!(lightbar.start, keyboard.len);
+        assert_eq!(lightbar.start + lightbar.len, device.leds.len());
+        assert!(device.leds[lightbar.start..]
+            .iter()
+            .all(LedCode::is_lightbar_zone));
+        assert!(device
+            .leds
+            .iter()
+            .all(|code| LedUsbPackets::is_addressable(*code, false)));
+    }
+
+    #[test]
+    fn update_leds_round_trip() {
+        let mut device = zoned_device();
+        device.active_mode = 1;
+        let mut version = 1;
+
+        let mut w = Writer::default();
+        w.u32(0);
+        w.u16(3);
+        for r in [10, 20, 30] {
+            w.colour(colour(r));
+        }
+        let packets = match device.handle(UPDATE_LEDS, &w.0, &mut version).unwrap() {
+            Action::Direct(packets) => packets,
+            _ => panic!("UpdateLeds did not write the colours"),
+        };
+        assert_eq!(device.active_mode, 0);
+        let mut expected = LedUsbPackets::new_zoned(true);
+        expected.set(LedCode::ZonedKbLeft, 10, 2, 3);
+        expected.set(LedCode::ZonedKbRight, 20, 2, 3);
+        expected.set(LedCode::LightbarLeft, 30, 2, 3);
+        assert_eq!(packets.get_ref(), expected.get_ref());
+
+        // Only the lightbar zone, extra colours are ignored
+        let mut w = Writer::default();
+        w.u32(0);
+        w.u32(1);
+        w.u16(2);
+        w.colour(colour(40));
+        w.colour(colour(50));
+        let packets = match device.handle(UPDATE_ZONE_LEDS, &w.0, &mut version).unwrap() {
+            Action::Direct(packets) => packets,
+            _ => panic!("UpdateZoneLeds did not write the colours"),
+        };
+        expected.set(LedCode::LightbarLeft, 40, 2, 3);
+        assert_eq!(packets.get_ref(), expected.get_ref());
+        assert_eq!(device.colours, [colour(10), colour(20), colour(40)]);
+    }
+
+    #[test]
+    fn truncated_packet_is_rejected() {
+        let mut device = zoned_device();
+        let mut version = 1;
+        let mut w = Writer::default();
+        w.u32(0);
+        w.u16(3);
+        w.colour(colour(10));
+        assert!(device.handle(UPDATE_LEDS, &w.0, &mut version).is_err());
+        assert!(device
+            .handle(UPDATE_ZONE_LEDS, &w.0[..6], &mut version)
+            .is_err());
+        assert_eq!(device.colours, vec![Colour { r: 0, g: 0, b: 0 }; 3]);
+    }
+}
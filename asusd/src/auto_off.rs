@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::sys_events::{PollGuard, SeatState, SysEvents};
+
+/// How often the auto-off policy is checked
+pub const AUTO_OFF_POLL: Duration = Duration::from_secs(1);
+
+/// When to turn a display or LEDs off for one power source
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(default)]
+pub struct AutoOffPolicy {
+    /// Seconds all sessions must be idle before turning off, 0 disables
+    pub idle_timeout: u32,
+    /// Turn off while the lid is closed
+    pub lid_closed: bool,
+}
+
+impl AutoOffPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.idle_timeout > 0 || self.lid_closed
+    }
+
+    /// `idle_for` is how long logind has reported the idle hint for, if idle
+    pub fn should_be_off(&self, lid_closed: bool, idle_for: Option<Duration>) -> bool {
+        if self.lid_closed && lid_closed {
+            return true;
+        }
+        self.idle_timeout > 0
+            && idle_for
+                .map(|d| d >= Duration::from_secs(self.idle_timeout as u64))
+                .unwrap_or(false)
+    }
+}
+
+/// Auto-off policies for AC and battery. Both are disabled by default.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(default)]
+pub struct AutoOff {
+    pub ac: AutoOffPolicy,
+    pub battery: AutoOffPolicy,
+}
+
+impl AutoOff {
+    pub fn policy(&self, on_ac: bool) -> AutoOffPolicy {
+        if on_ac {
+            self.ac
+        } else {
+            self.battery
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ac.is_enabled() || self.battery.is_enabled()
+    }
+}
+
+/// Tracks the idle time and whether the device is currently turned off
+#[derive(Debug, Default)]
+pub struct AutoOffTracker {
+    idle_since: Option<Instant>,
+    off: bool,
+}
+
+impl AutoOffTracker {
+    pub fn is_off(&self) -> bool {
+        self.off
+    }
+
+    /// Returns `Some(true)` when the device should turn off, and `Some(false)`
+    /// when it should be restored
+    pub fn update(&mut self, auto_off: &AutoOff, state: SeatState, now: Instant) -> Option<bool> {
+        if state.idle {
+            self.idle_since.get_or_insert(now);
+        } else {
+            self.idle_since = None;
+        }
+        let idle_for = self.idle_since.map(|since| now.duration_since(since));
+        let off = auto_off
+            .policy(state.on_ac)
+            .should_be_off(state.lid_closed, idle_for);
+        if off != self.off {
+            self.off = off;
+            return Some(off);
+        }
+        None
+    }
+}
+
+/// Tells when a config file was modified, so that `auto_off` edited by hand
+/// is picked up by the poll loops without parsing the file every time
+#[derive(Debug, Default)]
+pub struct ConfigModified {
+    /// `None` until the first check
+    modified: Option<Option<SystemTime>>,
+}
+
+impl ConfigModified {
+    /// True if the file was written since the last call. The first call only
+    /// records the time.
+    pub fn check(&mut self, path: &Path) -> bool {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let last = self.modified.replace(modified);
+        modified.is_some() && last.is_some_and(|last| last != modified)
+    }
+}
+
+/// Follows the logind state from `SysEvents`, which is only polled while a
+/// policy is enabled
+#[derive(Default)]
+pub struct AutoOffWatcher {
+    tracker: AutoOffTracker,
+    poll: Option<PollGuard>,
+}
+
+impl AutoOffWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the logind state against the policy, see `AutoOffTracker::update`
+    pub fn update(&mut self, auto_off: &AutoOff) -> Option<bool> {
+        if !auto_off.is_enabled() && !self.tracker.is_off() {
+            self.poll = None;
+            return None;
+        }
+        self.poll.get_or_insert_with(|| SysEvents::get().poll());
+        self.tracker
+            .update(auto_off, SysEvents::get().state(), Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, Instant, SystemTime};
+
+    use super::{AutoOff, AutoOffPolicy, AutoOffTracker, ConfigModified};
+    use crate::sys_events::SeatState;
+
+    #[test]
+    fn auto_off_idle_and_lid() {
+        let auto_off = AutoOff {
+            ac: AutoOffPolicy {
+                idle_timeout: 0,
+                lid_closed: true,
+            },
+            battery: AutoOffPolicy {
+                idle_timeout: 30,
+                lid_closed: true,
+            },
+        };
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        let mut tracker = AutoOffTracker::default();
+        let idle_bat = SeatState {
+            idle: true,
+            ..Default::default()
+        };
+
+        assert_eq!(tracker.update(&auto_off, idle_bat, at(0)), None);
+        assert_eq!(tracker.update(&auto_off, idle_bat, at(20)), None);
+        assert_eq!(tracker.update(&auto_off, idle_bat, at(30)), Some(true));
+        assert_eq!(tracker.update(&auto_off, idle_bat, at(31)), None);
+        // Activity restores
+        assert_eq!(
+            tracker.update(&auto_off, SeatState::default(), at(32)),
+            Some(false)
+        );
+        // Idle timeout is disabled on AC
+        let idle_ac = SeatState {
+            on_ac: true,
+            ..idle_bat
+        };
+        assert_eq!(tracker.update(&auto_off, idle_ac, at(100)), None);
+        assert_eq!(tracker.update(&auto_off, idle_ac, at(200)), None);
+        let lid_ac = SeatState {
+            lid_closed: true,
+            ..idle_ac
+        };
+        assert_eq!(tracker.update(&auto_off, lid_ac, at(201)), Some(true));
+        assert_eq!(tracker.update(&auto_off, idle_ac, at(202)), Some(false));
+    }
+
+    #[test]
+    fn config_modified_after_first_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aura.ron");
+        let mut modified = ConfigModified::default();
+        assert!(!modified.check(&path));
+        fs::write(&path, "()").unwrap();
+        assert!(modified.check(&path));
+        assert!(!modified.check(&path));
+
+        let later = SystemTime::now() + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(modified.check(&path));
+    }
+}
@@ -1,4 +1,9 @@
-use config_traits::{StdConfig, StdConfigLoad2};
+use std::collections::BTreeMap;
+
+use config_traits::{StdConfig, StdConfigLoad3};
+use rog_platform::platform::PlatformTunable;
+use rog_platform::quirks::LaptopQuirks;
+use rog_profiles::Profile;
 use serde_derive::{Deserialize, Serialize};
 
 const CONFIG_FILE: &str = "asusd.ron";
@@ -12,17 +17,35 @@ pub struct Config {
     pub disable_nvidia_powerd_on_battery: bool,
     pub ac_command: String,
     pub bat_command: String,
+    /// Power limit and Nvidia tunables to restore when switching to a profile
+    #[serde(default)]
+    pub tunables: BTreeMap<Profile, BTreeMap<PlatformTunable, u8>>,
+    /// Record all hardware writes to this file instead of the hardware, for
+    /// debugging. Takes effect on restart.
+    #[serde(default)]
+    pub capture_file: String,
+    /// Also write to the hardware while capturing
+    #[serde(default)]
+    pub capture_passthrough: bool,
+    /// Serve the power-profiles-daemon interface `net.hadess.PowerProfiles`.
+    /// Takes effect on restart, power-profiles-daemon must not be running.
+    #[serde(default)]
+    pub ppd_compat: bool,
 }
 
 impl StdConfig for Config {
     fn new() -> Self {
         Config {
-            bat_charge_limit: 100,
+            bat_charge_limit: LaptopQuirks::get().default_charge_limit.unwrap_or(100),
             panel_od: false,
             mini_led_mode: false,
             disable_nvidia_powerd_on_battery: true,
             ac_command: String::new(),
             bat_command: String::new(),
+            tunables: BTreeMap::new(),
+            capture_file: String::new(),
+            capture_passthrough: false,
+            ppd_compat: false,
         }
     }
 
@@ -35,7 +58,35 @@ impl StdConfig for Config {
     }
 }
 
-impl StdConfigLoad2<Config458, Config462> for Config {}
+impl StdConfigLoad3<Config458, Config462, Config472> for Config {}
+
+#[derive(Deserialize, Serialize)]
+pub struct Config472 {
+    /// Save charge limit for restoring on boot
+    pub bat_charge_limit: u8,
+    pub panel_od: bool,
+    pub mini_led_mode: bool,
+    pub disable_nvidia_powerd_on_battery: bool,
+    pub ac_command: String,
+    pub bat_command: String,
+}
+
+impl From<Config472> for Config {
+    fn from(c: Config472) -> Self {
+        Self {
+            bat_charge_limit: c.bat_charge_limit,
+            panel_od: c.panel_od,
+            mini_led_mode: c.mini_led_mode,
+            disable_nvidia_powerd_on_battery: c.disable_nvidia_powerd_on_battery,
+            ac_command: c.ac_command,
+            bat_command: c.bat_command,
+            tunables: BTreeMap::new(),
+            capture_file: String::new(),
+            capture_passthrough: false,
+            ppd_compat: false,
+        }
+    }
+}
 
 #[derive(Deserialize, Serialize)]
 pub struct Config462 {
@@ -56,6 +107,10 @@ impl From<Config462> for Config {
             disable_nvidia_powerd_on_battery: true,
             ac_command: String::new(),
             bat_command: String::new(),
+            tunables: BTreeMap::new(),
+            capture_file: String::new(),
+            capture_passthrough: false,
+            ppd_compat: false,
         }
     }
 }
@@ -78,6 +133,10 @@ impl From<Config458> for Config {
             disable_nvidia_powerd_on_battery: true,
             ac_command: c.ac_command,
             bat_command: c.bat_command,
+            tunables: BTreeMap::new(),
+            capture_file: String::new(),
+            capture_passthrough: false,
+            ppd_compat: false,
         }
     }
 }
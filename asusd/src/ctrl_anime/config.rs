@@ -6,6 +6,8 @@ use rog_anime::usb::Brightness;
 use rog_anime::{ActionData, ActionLoader, AnimTime, Animations, AnimeType, Fade, Vec2};
 use serde_derive::{Deserialize, Serialize};
 
+use crate::auto_off::AutoOff;
+
 const CONFIG_FILE: &str = "anime.ron";
 
 #[derive(Deserialize, Serialize)]
@@ -118,6 +120,8 @@ pub struct AnimeConfig {
     pub display_brightness: Brightness,
     pub builtin_anims_enabled: bool,
     pub builtin_anims: Animations,
+    #[serde(default)]
+    pub auto_off: AutoOff,
 }
 
 impl Default for AnimeConfig {
@@ -134,6 +138,7 @@ impl Default for AnimeConfig {
             display_brightness: Brightness::Med,
             builtin_anims_enabled: true,
             builtin_anims: Animations::default(),
+            auto_off: AutoOff::default(),
         }
     }
 }
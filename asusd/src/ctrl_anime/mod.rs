@@ -10,13 +10,15 @@ use std::thread::sleep;
 use ::zbus::export::futures_util::lock::Mutex;
 use log::{error, info, warn};
 use rog_anime::error::AnimeError;
-use rog_anime::usb::{get_anime_type, pkt_flush, pkt_set_enable_powersave_anim, pkts_for_init};
+use rog_anime::usb::{pkt_flush, pkt_set_enable_powersave_anim, pkts_for_init};
 use rog_anime::{ActionData, AnimeDataBuffer, AnimePacketType, AnimeType};
 use rog_platform::hid_raw::HidRaw;
+use rog_platform::quirks::get_anime_type;
 use rog_platform::supported::AnimeSupportedFunctions;
 use rog_platform::usb_raw::USBRaw;
 
 use self::config::{AnimeConfig, AnimeConfigCached};
+use crate::diagnostics;
 use crate::error::RogError;
 use crate::GetSupported;
 
@@ -69,11 +71,12 @@ impl CtrlAnime {
     pub fn new(config: AnimeConfig) -> Result<CtrlAnime, RogError> {
         let usb = USBRaw::new(0x193b).ok();
         let hid = HidRaw::new("193b").ok();
-        let node = if usb.is_some() {
-            unsafe { Node::Usb(usb.unwrap_unchecked()) }
+        let (node, bus) = if usb.is_some() {
+            (unsafe { Node::Usb(usb.unwrap_unchecked()) }, "USB")
         } else if hid.is_some() {
-            unsafe { Node::Hid(hid.unwrap_unchecked()) }
+            (unsafe { Node::Hid(hid.unwrap_unchecked()) }, "hidraw")
         } else {
+            diagnostics::record(diagnostics::anime(None));
             return Err(RogError::Anime(AnimeError::NoDevice));
         };
 
@@ -86,6 +89,7 @@ impl CtrlAnime {
         }
 
         info!("Device has an AniMe Matrix display: {anime_type:?}");
+        diagnostics::record(diagnostics::anime(Some((bus, anime_type))));
         let mut cache = AnimeConfigCached::default();
         cache.init_from_config(&config, anime_type)?;
 
@@ -3,16 +3,18 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
-use log::warn;
+use log::{debug, warn};
 use rog_anime::usb::{
     pkt_set_brightness, pkt_set_builtin_animations, pkt_set_enable_display,
     pkt_set_enable_powersave_anim, AnimAwake, AnimBooting, AnimShutdown, AnimSleeping, Brightness,
 };
 use rog_anime::{AnimeDataBuffer, DeviceState};
+use tokio::time::sleep;
 use zbus::export::futures_util::lock::Mutex;
 use zbus::{dbus_interface, Connection, SignalContext};
 
 use super::CtrlAnime;
+use crate::auto_off::{AutoOffWatcher, ConfigModified, AUTO_OFF_POLL};
 use crate::error::RogError;
 
 pub(super) const ZBUS_PATH: &str = "/org/asuslinux/Anime";
@@ -266,6 +268,33 @@ impl crate::CtrlTask for CtrlAnimeZbus {
         )
         .await;
 
+        let inner = self.0.clone();
+        crate::spawn_task(async move {
+            let mut watcher = AutoOffWatcher::new();
+            let mut modified = ConfigModified::default();
+            loop {
+                sleep(AUTO_OFF_POLL).await;
+                let auto_off = {
+                    let mut lock = inner.lock().await;
+                    if modified.check(&lock.config.file_path()) {
+                        lock.config.read();
+                    }
+                    lock.config.auto_off
+                };
+                if let Some(off) = watcher.update(&auto_off) {
+                    let lock = inner.lock().await;
+                    // The config is left as is so the display state is restored
+                    if lock.config.display_enabled {
+                        debug!("CtrlAnimeTask: auto-off, display off: {off}");
+                        lock.node
+                            .write_bytes(&pkt_set_enable_display(!off))
+                            .map_err(|e| warn!("CtrlAnimeTask: {e}"))
+                            .ok();
+                    }
+                }
+            }
+        });
+
         Ok(())
     }
 }
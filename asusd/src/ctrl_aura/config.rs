@@ -9,6 +9,8 @@ use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Direction, LedBrightness, Spee
 use rog_platform::hid_raw::HidRaw;
 use serde_derive::{Deserialize, Serialize};
 
+use crate::auto_off::AutoOff;
+
 const CONFIG_FILE: &str = "aura.ron";
 
 /// Enable/disable LED control in various states such as
@@ -113,6 +115,8 @@ pub struct AuraConfig {
     pub multizone: Option<BTreeMap<AuraModeNum, Vec<AuraEffect>>>,
     pub multizone_on: bool,
     pub enabled: AuraPowerConfig,
+    #[serde(default)]
+    pub auto_off: AutoOff,
 }
 
 impl StdConfig for AuraConfig {
@@ -168,6 +172,7 @@ impl AuraConfig {
             multizone: None,
             multizone_on: false,
             enabled,
+            auto_off: AutoOff::default(),
         };
 
         for n in &support_data.basic_modes {
@@ -11,6 +11,7 @@ use rog_platform::keyboard_led::KeyboardLed;
 use rog_platform::supported::LedSupportedFunctions;
 
 use super::config::{AuraConfig, AuraPowerConfig};
+use crate::diagnostics;
 use crate::error::RogError;
 use crate::GetSupported;
 
@@ -62,6 +63,8 @@ pub struct CtrlKbdLed {
     pub supported_modes: LaptopLedData,
     pub flip_effect_write: bool,
     pub per_key_mode_active: bool,
+    /// Set while the keyboard is turned off by `AutoOff`
+    pub auto_off_active: bool,
     pub config: AuraConfig,
 }
 
@@ -87,7 +90,13 @@ impl CtrlKbdLed {
             }
         }
 
-        let rgb_led = KeyboardLed::new()?;
+        let rgb_led = KeyboardLed::new();
+        diagnostics::record(diagnostics::keyboard_backlight(&rgb_led));
+        diagnostics::record(diagnostics::aura_keyboard(
+            usb_node.as_ref().map(|_| led_prod),
+        ));
+        diagnostics::record(diagnostics::aura_modes());
+        let rgb_led = rgb_led?;
 
         if usb_node.is_none() && !rgb_led.has_kbd_rgb_mode() {
             let dmi = sysfs_class::DmiId::default();
@@ -151,6 +160,7 @@ impl CtrlKbdLed {
             supported_modes,
             flip_effect_write: false,
             per_key_mode_active: false,
+            auto_off_active: false,
             config: config_loaded,
         };
         Ok(ctrl)
@@ -168,6 +178,24 @@ impl CtrlKbdLed {
             .map_err(RogError::Platform)
     }
 
+    /// Step the brightness down one level, used to fade out for auto-off.
+    /// Returns true once the keyboard is off. The config is not changed.
+    pub(super) fn step_brightness_down(&mut self) -> Result<bool, RogError> {
+        self.auto_off_active = true;
+        let bright = self.get_brightness()? as u32;
+        if bright == 0 {
+            return Ok(true);
+        }
+        self.set_brightness(<LedBrightness>::from(bright - 1))?;
+        Ok(bright == 1)
+    }
+
+    /// Return to the configured brightness after auto-off
+    pub(super) fn restore_brightness(&mut self) -> Result<(), RogError> {
+        self.auto_off_active = false;
+        self.set_brightness(self.config.brightness)
+    }
+
     pub fn next_brightness(&mut self) -> Result<(), RogError> {
         let mut bright = (self.config.brightness as u32) + 1;
         if bright > 3 {
@@ -424,6 +452,7 @@ mod tests {
             basic_zones: vec![],
             advanced_type: rog_aura::AdvancedAuraType::None,
             power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+            ..Default::default()
         };
         let mut controller = CtrlKbdLed {
             led_prod: AuraDevice::X19b6,
@@ -432,6 +461,7 @@ mod tests {
             supported_modes,
             flip_effect_write: false,
             per_key_mode_active: false,
+            auto_off_active: false,
             config,
         };
 
@@ -492,6 +522,7 @@ mod tests {
             basic_zones: vec![],
             advanced_type: rog_aura::AdvancedAuraType::None,
             power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+            ..Default::default()
         };
         let mut controller = CtrlKbdLed {
             led_prod: AuraDevice::X19b6,
@@ -500,6 +531,7 @@ mod tests {
             supported_modes,
             flip_effect_write: false,
             per_key_mode_active: false,
+            auto_off_active: false,
             config,
         };
 
@@ -531,6 +563,7 @@ mod tests {
             basic_zones: vec![AuraZone::Key1, AuraZone::Key2],
             advanced_type: rog_aura::AdvancedAuraType::None,
             power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+            ..Default::default()
         };
         let mut controller = CtrlKbdLed {
             led_prod: AuraDevice::X19b6,
@@ -539,6 +572,7 @@ mod tests {
             supported_modes,
             flip_effect_write: false,
             per_key_mode_active: false,
+            auto_off_active: false,
             config,
         };
 
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
@@ -7,21 +8,29 @@ use log::{debug, error, info, warn};
 use rog_aura::advanced::UsbPackets;
 use rog_aura::usb::{AuraDevice, AuraPowerDev};
 use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+use tokio::time::sleep;
 use zbus::export::futures_util::lock::{Mutex, MutexGuard};
 use zbus::export::futures_util::StreamExt;
 use zbus::{dbus_interface, Connection, SignalContext};
 
 use super::controller::CtrlKbdLed;
+use crate::auto_off::{AutoOffWatcher, ConfigModified, AUTO_OFF_POLL};
 use crate::error::RogError;
 use crate::CtrlTask;
 
 pub(super) const ZBUS_PATH: &str = "/org/asuslinux/Aura";
+/// Delay between each brightness level when fading out for auto-off
+const FADE_STEP: Duration = Duration::from_millis(300);
 
 #[derive(Clone)]
 pub struct CtrlKbdLedZbus(pub Arc<Mutex<CtrlKbdLed>>);
 
 impl CtrlKbdLedZbus {
     fn update_config(lock: &mut CtrlKbdLed) -> Result<(), RogError> {
+        // Don't store the faded out brightness
+        if lock.auto_off_active {
+            return Ok(());
+        }
         let bright = lock.kd_brightness.get_brightness()?;
         lock.config.read();
         lock.config.brightness = (bright as u32).into();
@@ -281,7 +290,7 @@ impl CtrlTask for CtrlKbdLedZbus {
         let ctrl2 = self.0.clone();
         let ctrl = self.0.lock().await;
         let watch = ctrl.kd_brightness.monitor_brightness()?;
-        tokio::spawn(async move {
+        crate::spawn_task(async move {
             let mut buffer = [0; 32];
             watch
                 .into_event_stream(&mut buffer)
@@ -294,6 +303,48 @@ impl CtrlTask for CtrlKbdLedZbus {
                 .await;
         });
 
+        let ctrl = self.0.clone();
+        crate::spawn_task(async move {
+            let mut watcher = AutoOffWatcher::new();
+            let mut modified = ConfigModified::default();
+            loop {
+                sleep(AUTO_OFF_POLL).await;
+                let auto_off = {
+                    let mut lock = ctrl.lock().await;
+                    if modified.check(&lock.config.file_path()) {
+                        lock.config.read();
+                    }
+                    lock.config.auto_off
+                };
+                match watcher.update(&auto_off) {
+                    Some(true) => {
+                        debug!("CtrlKbdLedTask: auto-off, fading out");
+                        loop {
+                            // Release the lock between steps so other calls aren't blocked
+                            let step = ctrl.lock().await.step_brightness_down();
+                            match step {
+                                Ok(false) => sleep(FADE_STEP).await,
+                                Ok(true) => break,
+                                Err(e) => {
+                                    error!("CtrlKbdLedTask: {e}");
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Some(false) => {
+                        debug!("CtrlKbdLedTask: auto-off, restoring brightness");
+                        ctrl.lock()
+                            .await
+                            .restore_brightness()
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
+                    }
+                    None => {}
+                }
+            }
+        });
+
         Ok(())
     }
 }
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use log::{info, warn};
+use rog_platform::firmware_attributes::{FirmwareAttribute, FirmwareAttributes};
+use zbus::export::futures_util::StreamExt;
+use zbus::fdo::Error;
+use zbus::{dbus_interface, Connection, SignalContext};
+
+use crate::diagnostics;
+use crate::error::RogError;
+use crate::CtrlTask;
+
+const ZBUS_PATH: &str = "/org/asuslinux/FirmwareAttributes";
+
+/// Exposes every attribute found under `/sys/class/firmware-attributes` so
+/// that clients can show controls for attributes asusd has no specific
+/// support for
+#[derive(Clone)]
+pub struct CtrlFirmwareAttributes {
+    attrs: FirmwareAttributes,
+}
+
+impl CtrlFirmwareAttributes {
+    pub fn new() -> Result<Self, RogError> {
+        let attrs = FirmwareAttributes::new();
+        let found = !attrs.list().is_empty();
+        diagnostics::record(diagnostics::firmware_attributes(found));
+        if !found {
+            return Err(RogError::MissingFunction(
+                "No firmware-attributes found".to_owned(),
+            ));
+        }
+        Ok(Self { attrs })
+    }
+}
+
+#[dbus_interface(name = "org.asuslinux.Daemon")]
+impl CtrlFirmwareAttributes {
+    /// List all attributes with their type, limits and current value
+    fn attributes(&self) -> Vec<FirmwareAttribute> {
+        self.attrs.list()
+    }
+
+    /// Get a single attribute by the name of its device and its own name
+    fn attribute(&self, device: &str, name: &str) -> zbus::fdo::Result<FirmwareAttribute> {
+        self.attrs
+            .get(device, name)
+            .map_err(|e| Error::InvalidArgs(e.to_string()))
+    }
+
+    /// Set the current value of an attribute. The value is checked against the
+    /// attribute type and limits first.
+    async fn set_attribute(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        device: &str,
+        name: &str,
+        value: &str,
+    ) -> zbus::fdo::Result<()> {
+        self.attrs.set(device, name, value).map_err(|e| {
+            warn!("CtrlFirmwareAttributes: set {device}/{name}, {e}");
+            Error::InvalidArgs(e.to_string())
+        })?;
+        if let Ok(attr) = self.attrs.get(device, name) {
+            Self::notify_attribute(&ctxt, attr).await.ok();
+        }
+        Ok(())
+    }
+
+    #[dbus_interface(signal)]
+    async fn notify_attribute(
+        signal_ctxt: &SignalContext<'_>,
+        attribute: FirmwareAttribute,
+    ) -> zbus::Result<()>;
+}
+
+#[async_trait]
+impl crate::ZbusRun for CtrlFirmwareAttributes {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ZBUS_PATH, server).await;
+    }
+}
+
+#[async_trait]
+impl crate::Reloadable for CtrlFirmwareAttributes {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CtrlTask for CtrlFirmwareAttributes {
+    fn zbus_path() -> &'static str {
+        ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        for attr in self.attrs.list() {
+            match self.attrs.monitor(&attr.device, &attr.name) {
+                Ok(watch) => {
+                    let ctrl = self.clone();
+                    let signal_ctxt = signal_ctxt.clone();
+                    crate::spawn_task(async move {
+                        let mut buffer = [0; 32];
+                        if let Ok(stream) = watch.into_event_stream(&mut buffer) {
+                            stream
+                                .for_each(|_| async {
+                                    if let Ok(attr) = ctrl.attrs.get(&attr.device, &attr.name) {
+                                        Self::notify_attribute(&signal_ctxt, attr).await.ok();
+                                    }
+                                })
+                                .await;
+                        }
+                    });
+                }
+                Err(e) => info!("inotify watch on {}/{} failed: {e}", attr.device, attr.name),
+            }
+        }
+        Ok(())
+    }
+}
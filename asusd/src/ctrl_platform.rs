@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
 use std::path::Path;
@@ -7,12 +8,17 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{info, warn};
-use rog_platform::platform::{AsusPlatform, GpuMode};
+use rog_platform::platform::{AsusPlatform, GpuMode, PlatformTunable};
+use rog_platform::quirks::LaptopQuirks;
 use rog_platform::supported::RogBiosSupportedFunctions;
+use rog_profiles::Profile;
 use zbus::export::futures_util::lock::Mutex;
+use zbus::export::futures_util::StreamExt;
+use zbus::fdo::Error;
 use zbus::{dbus_interface, Connection, SignalContext};
 
 use crate::config::Config;
+use crate::diagnostics;
 use crate::error::RogError;
 use crate::{task_watch_item, CtrlTask, GetSupported};
 
@@ -35,29 +41,38 @@ impl GetSupported for CtrlPlatform {
         let mut dgpu_disable = false;
         let mut egpu_enable = false;
         let mut gpu_mux = false;
+        let mut tunables = Vec::new();
 
+        // Attributes known to be broken on this model are treated as missing
+        let quirks = LaptopQuirks::get();
+        let works = |attr: &str| !quirks.is_broken(attr);
         if let Ok(platform) = AsusPlatform::new() {
-            panel_overdrive = platform.has_panel_od();
-            mini_led_mode = platform.has_mini_led_mode();
-            dgpu_disable = platform.has_dgpu_disable();
-            egpu_enable = platform.has_egpu_enable();
-            gpu_mux = platform.has_gpu_mux_mode();
+            panel_overdrive = platform.has_panel_od() && works("panel_od");
+            mini_led_mode = platform.has_mini_led_mode() && works("mini_led_mode");
+            dgpu_disable = platform.has_dgpu_disable() && works("dgpu_disable");
+            egpu_enable = platform.has_egpu_enable() && works("egpu_enable");
+            gpu_mux = platform.has_gpu_mux_mode() && works("gpu_mux_mode");
+            tunables = platform.supported_tunables();
+            tunables.retain(|t| works(t.attr_name()));
         }
 
         RogBiosSupportedFunctions {
-            post_sound: Path::new(ASUS_POST_LOGO_SOUND).exists(),
+            post_sound: Path::new(ASUS_POST_LOGO_SOUND).exists() && works("post_sound"),
             gpu_mux,
             panel_overdrive,
             mini_led_mode,
             dgpu_disable,
             egpu_enable,
+            tunables,
         }
     }
 }
 
 impl CtrlPlatform {
     pub fn new(config: Arc<Mutex<Config>>) -> Result<Self, RogError> {
-        let platform = AsusPlatform::new()?;
+        let platform = AsusPlatform::new();
+        diagnostics::record(diagnostics::platform_device(platform.is_ok()));
+        let platform = platform?;
 
         if !platform.has_gpu_mux_mode() {
             info!("G-Sync Switchable Graphics or GPU MUX not detected");
@@ -129,6 +144,38 @@ impl CtrlPlatform {
         Ok(())
     }
 
+    /// Restore the tunables stored for the active platform profile
+    fn restore_tunables(&self, config: &Config) {
+        if let Ok(profile) = Profile::get_active_profile() {
+            if let Some(tunables) = config.tunables.get(&profile) {
+                info!("CtrlRogBios: restoring tunables for {profile}");
+                for (tunable, value) in tunables {
+                    if self.tunable_works(*tunable) {
+                        self.platform
+                            .set_tunable(*tunable, *value)
+                            .map_err(|err| warn!("CtrlRogBios: set_tunable {}", err))
+                            .ok();
+                    }
+                }
+            }
+        }
+    }
+
+    /// The tunable exists and isn't listed as broken in the laptop quirks
+    fn tunable_works(&self, tunable: PlatformTunable) -> bool {
+        self.platform.has_tunable(tunable) && !LaptopQuirks::get().is_broken(tunable.attr_name())
+    }
+
+    fn check_tunable(&self, tunable: PlatformTunable) -> Result<(), Error> {
+        if self.tunable_works(tunable) {
+            Ok(())
+        } else {
+            Err(Error::NotSupported(format!(
+                "{tunable} is not supported on this laptop"
+            )))
+        }
+    }
+
     fn set_panel_overdrive(&self, enable: bool) -> Result<(), RogError> {
         self.platform.set_panel_od(enable).map_err(|err| {
             warn!("CtrlRogBios: set_panel_overdrive {}", err);
@@ -314,6 +361,97 @@ impl CtrlPlatform {
     #[dbus_interface(signal)]
     async fn notify_egpu_enable(signal_ctxt: &SignalContext<'_>, enable: bool) -> zbus::Result<()> {
     }
+
+    /// Get the current value of a power limit or Nvidia tunable
+    fn tunable(&self, tunable: PlatformTunable) -> zbus::fdo::Result<u8> {
+        self.check_tunable(tunable)?;
+        self.platform
+            .get_tunable(tunable)
+            .map_err(|err| Error::Failed(err.to_string()))
+    }
+
+    /// The (min, max) range accepted for a tunable
+    fn tunable_range(&self, tunable: PlatformTunable) -> (u8, u8) {
+        self.platform.tunable_range(tunable)
+    }
+
+    /// Set a power limit or Nvidia tunable. This is not stored, see
+    /// `SetProfileTunable`
+    async fn set_tunable(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        tunable: PlatformTunable,
+        value: u8,
+    ) -> zbus::fdo::Result<()> {
+        self.check_tunable(tunable)?;
+        self.platform
+            .set_tunable(tunable, value)
+            .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+        Self::notify_tunable(&ctxt, tunable, value).await.ok();
+        Ok(())
+    }
+
+    /// Get the tunables stored for a profile
+    async fn profile_tunables(&self, profile: Profile) -> BTreeMap<PlatformTunable, u8> {
+        let mut config = self.config.lock().await;
+        config.read();
+        config.tunables.get(&profile).cloned().unwrap_or_default()
+    }
+
+    /// Store a tunable value for a profile, restored each time the profile
+    /// is switched to and on resume. Set now if the profile is active.
+    async fn set_profile_tunable(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        profile: Profile,
+        tunable: PlatformTunable,
+        value: u8,
+    ) -> zbus::fdo::Result<()> {
+        self.check_tunable(tunable)?;
+        let (min, max) = self.platform.tunable_range(tunable);
+        if !(min..=max).contains(&value) {
+            return Err(Error::InvalidArgs(format!(
+                "{tunable} must be within {min}-{max}"
+            )));
+        }
+
+        let mut config = self.config.lock().await;
+        config.read();
+        config
+            .tunables
+            .entry(profile)
+            .or_default()
+            .insert(tunable, value);
+        config.write();
+
+        if Profile::get_active_profile().ok() == Some(profile) {
+            self.platform
+                .set_tunable(tunable, value)
+                .map_err(|err| Error::Failed(err.to_string()))?;
+            Self::notify_tunable(&ctxt, tunable, value).await.ok();
+        }
+        Ok(())
+    }
+
+    /// Stop restoring a tunable for a profile
+    async fn remove_profile_tunable(&mut self, profile: Profile, tunable: PlatformTunable) {
+        let mut config = self.config.lock().await;
+        config.read();
+        if let Some(tunables) = config.tunables.get_mut(&profile) {
+            tunables.remove(&tunable);
+            if tunables.is_empty() {
+                config.tunables.remove(&profile);
+            }
+        }
+        config.write();
+    }
+
+    #[dbus_interface(signal)]
+    async fn notify_tunable(
+        signal_ctxt: &SignalContext<'_>,
+        tunable: PlatformTunable,
+        value: u8,
+    ) -> zbus::Result<()>;
 }
 
 #[async_trait]
@@ -326,14 +464,11 @@ impl crate::ZbusRun for CtrlPlatform {
 #[async_trait]
 impl crate::Reloadable for CtrlPlatform {
     async fn reload(&mut self) -> Result<(), RogError> {
-        if self.platform.has_panel_od() {
-            let p = if let Some(lock) = self.config.try_lock() {
-                lock.panel_od
-            } else {
-                false
-            };
+        if self.platform.has_panel_od() && !LaptopQuirks::get().is_broken("panel_od") {
+            let p = self.config.lock().await.panel_od;
             self.set_panel_overdrive(p)?;
         }
+        self.restore_tunables(&*self.config.lock().await);
         Ok(())
     }
 }
@@ -375,6 +510,7 @@ impl CtrlTask for CtrlPlatform {
                             })
                             .ok();
                     }
+                    platform1.restore_tunables(&lock);
                 }
             },
             move || async { {} },
@@ -392,6 +528,7 @@ impl CtrlTask for CtrlPlatform {
                             })
                             .ok();
                     }
+                    platform2.restore_tunables(&lock);
                 }
             },
         )
@@ -405,6 +542,53 @@ impl CtrlTask for CtrlPlatform {
         // booted-with value  as it does not actually change until reboot.
         // self.watch_gpu_mux_mode(signal_ctxt.clone()).await?;
 
+        for tunable in self.platform.supported_tunables() {
+            match self.platform.monitor_tunable(tunable) {
+                Ok(watch) => {
+                    let ctrl = self.clone();
+                    let signal_ctxt = signal_ctxt.clone();
+                    crate::spawn_task(async move {
+                        let mut buffer = [0; 32];
+                        if let Ok(stream) = watch.into_event_stream(&mut buffer) {
+                            stream
+                                .for_each(|_| async {
+                                    if let Ok(value) = ctrl.platform.get_tunable(tunable) {
+                                        Self::notify_tunable(&signal_ctxt, tunable, value)
+                                            .await
+                                            .ok();
+                                    }
+                                })
+                                .await;
+                        }
+                    });
+                }
+                Err(e) => info!("inotify watch failed: {e}"),
+            }
+        }
+
+        // The firmware resets power limits when the profile changes, so restore
+        // those stored for the new profile
+        if !self.platform.supported_tunables().is_empty() {
+            match self.platform.monitor_platform_profile() {
+                Ok(watch) => {
+                    let ctrl = self.clone();
+                    crate::spawn_task(async move {
+                        let mut buffer = [0; 32];
+                        if let Ok(stream) = watch.into_event_stream(&mut buffer) {
+                            stream
+                                .for_each(|_| async {
+                                    let mut lock = ctrl.config.lock().await;
+                                    lock.read();
+                                    ctrl.restore_tunables(&lock);
+                                })
+                                .await;
+                        }
+                    });
+                }
+                Err(e) => info!("inotify watch failed: {e}"),
+            }
+        }
+
         Ok(())
     }
 }
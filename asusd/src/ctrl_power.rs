@@ -6,6 +6,7 @@ use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{error, info, warn};
 use rog_platform::power::AsusPower;
+use rog_platform::quirks::LaptopQuirks;
 use rog_platform::supported::ChargeSupportedFunctions;
 use systemd_zbus::{ManagerProxy as SystemdProxy, Mode, UnitFileState};
 use tokio::time::sleep;
@@ -13,6 +14,7 @@ use zbus::export::futures_util::lock::Mutex;
 use zbus::{dbus_interface, Connection, SignalContext};
 
 use crate::config::Config;
+use crate::diagnostics;
 use crate::error::RogError;
 use crate::{CtrlTask, GetSupported};
 
@@ -26,6 +28,7 @@ impl GetSupported for CtrlPower {
         ChargeSupportedFunctions {
             charge_level_set: if let Ok(power) = AsusPower::new() {
                 power.has_charge_control_end_threshold()
+                    && !LaptopQuirks::get().is_broken("charge_control_end_threshold")
             } else {
                 false
             },
@@ -123,8 +126,15 @@ impl CtrlPower {
     // task_watch_item!(charge_control_end_threshold power);
 
     pub fn new(config: Arc<Mutex<Config>>) -> Result<Self, RogError> {
+        let power = AsusPower::new();
+        diagnostics::record(diagnostics::charge_limit(
+            power
+                .as_ref()
+                .map(|p| p.get_charge_control_end_threshold().is_ok())
+                .unwrap_or(false),
+        ));
         Ok(CtrlPower {
-            power: AsusPower::new()?,
+            power: power?,
             config,
         })
     }
@@ -215,7 +225,7 @@ impl CtrlTask for CtrlPower {
         //     .await?;
 
         let ctrl = self.clone();
-        tokio::spawn(async move {
+        crate::spawn_task(async move {
             let mut online = 10;
             loop {
                 if let Ok(value) = ctrl.power.get_online() {
@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-use config_traits::{StdConfig, StdConfigLoad};
+use config_traits::{StdConfig, StdConfigLoad1};
+use rog_platform::cpu::CpuPolicy;
 use rog_profiles::fan_curve_set::CurveData;
-use rog_profiles::Profile;
+use rog_profiles::thermal_guard::ThermalGuard;
+use rog_profiles::{Profile, ProfileCycle};
 use serde_derive::{Deserialize, Serialize};
 
 use crate::CONFIG_PATH_BASE;
@@ -14,12 +17,31 @@ const CONFIG_FAN_FILE: &str = "fan_curves.ron";
 pub struct ProfileConfig {
     /// For restore on boot
     pub active_profile: Profile,
+    /// The cycle used by `next_profile` while on AC power
+    pub cycle_ac: ProfileCycle,
+    /// The cycle used by `next_profile` while on battery
+    pub cycle_bat: ProfileCycle,
+    /// Temperature guards keyed by the profile they watch
+    #[serde(default)]
+    pub thermal_guards: BTreeMap<Profile, ThermalGuard>,
+    /// cpufreq settings applied when switching to a profile
+    #[serde(default)]
+    pub cpu_policies: BTreeMap<Profile, CpuPolicy>,
+    /// The cpufreq settings found the first time asusd ran, restored for
+    /// profiles without an entry in `cpu_policies`
+    #[serde(default)]
+    pub cpu_default: Option<CpuPolicy>,
 }
 
 impl StdConfig for ProfileConfig {
     fn new() -> Self {
         Self {
             active_profile: Profile::Balanced,
+            cycle_ac: ProfileCycle::default(),
+            cycle_bat: ProfileCycle::default(),
+            thermal_guards: BTreeMap::new(),
+            cpu_policies: BTreeMap::new(),
+            cpu_default: None,
         }
     }
 
@@ -32,13 +54,26 @@ impl StdConfig for ProfileConfig {
     }
 }
 
-impl StdConfigLoad for ProfileConfig {}
+impl StdConfigLoad1<ProfileConfigV472> for ProfileConfig {}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProfileConfigV472 {
+    pub active_profile: Profile,
+}
+
+impl From<ProfileConfigV472> for ProfileConfig {
+    fn from(c: ProfileConfigV472) -> Self {
+        Self {
+            active_profile: c.active_profile,
+            ..Self::new()
+        }
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 pub struct FanCurveConfig {
-    pub balanced: Vec<CurveData>,
-    pub performance: Vec<CurveData>,
-    pub quiet: Vec<CurveData>,
+    /// Curves keyed by the `platform_profile` they belong to
+    pub curves: BTreeMap<Profile, Vec<CurveData>>,
 }
 
 impl StdConfig for FanCurveConfig {
@@ -57,4 +92,23 @@ impl StdConfig for FanCurveConfig {
     }
 }
 
-impl StdConfigLoad for FanCurveConfig {}
+impl StdConfigLoad1<FanCurveConfigV472> for FanCurveConfig {}
+
+#[derive(Deserialize, Serialize)]
+pub struct FanCurveConfigV472 {
+    pub balanced: Vec<CurveData>,
+    pub performance: Vec<CurveData>,
+    pub quiet: Vec<CurveData>,
+}
+
+impl From<FanCurveConfigV472> for FanCurveConfig {
+    fn from(c: FanCurveConfigV472) -> Self {
+        Self {
+            curves: BTreeMap::from([
+                (Profile::Balanced, c.balanced),
+                (Profile::Performance, c.performance),
+                (Profile::Quiet, c.quiet),
+            ]),
+        }
+    }
+}
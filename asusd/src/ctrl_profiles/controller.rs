@@ -1,11 +1,21 @@
-use config_traits::{StdConfig, StdConfigLoad};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use config_traits::{StdConfig, StdConfigLoad1};
 use log::{info, warn};
+use rog_platform::cpu::CpuControl;
 use rog_platform::platform::AsusPlatform;
+use rog_platform::power::AsusPower;
+use rog_platform::quirks::LaptopQuirks;
 use rog_platform::supported::PlatformProfileFunctions;
 use rog_profiles::error::ProfileError;
+use rog_profiles::thermal_guard::{
+    ThermalGuard, ThermalGuardAction, ThermalGuardEvent, ThermalGuardTracker,
+};
 use rog_profiles::{FanCurveProfiles, Profile};
 
 use super::config::{FanCurveConfig, ProfileConfig};
+use crate::diagnostics;
 use crate::error::RogError;
 use crate::GetSupported;
 
@@ -19,15 +29,11 @@ pub struct FanCurves {
 
 impl FanCurves {
     pub fn update_profiles_from_config(&mut self) {
-        self.profiles.balanced = self.config_file.balanced.clone();
-        self.profiles.performance = self.config_file.performance.clone();
-        self.profiles.quiet = self.config_file.quiet.clone();
+        self.profiles.curves = self.config_file.curves.clone();
     }
 
     pub fn update_config_from_profiles(&mut self) {
-        self.config_file.balanced = self.profiles.balanced.clone();
-        self.config_file.performance = self.profiles.performance.clone();
-        self.config_file.quiet = self.profiles.quiet.clone();
+        self.config_file.curves = self.profiles.curves.clone();
     }
 
     pub fn profiles(&self) -> &FanCurveProfiles {
@@ -39,10 +45,99 @@ impl FanCurves {
     }
 }
 
+/// The state to return to once a thermal guard is no longer escalated
+struct Escalation {
+    from: Profile,
+    guard: ThermalGuard,
+}
+
+/// The thermal guard state. Only held in memory, re-reading `ProfileConfig`
+/// can't change it and it is never written.
+#[derive(Default)]
+struct ThermalState {
+    tracker: ThermalGuardTracker,
+    /// The profile `tracker` holds samples for
+    tracked_profile: Option<Profile>,
+    escalation: Option<Escalation>,
+}
+
+impl ThermalState {
+    /// The guard to check while `active` is in use. An escalation is dropped
+    /// if the profile was changed away from it.
+    fn guard(
+        &mut self,
+        active: Profile,
+        guards: &BTreeMap<Profile, ThermalGuard>,
+    ) -> Option<ThermalGuard> {
+        if let Some(escalation) = &self.escalation {
+            if active != escalation.guard.escalate_to {
+                info!("{MOD_NAME}: profile changed while escalated, dropping thermal guard state");
+                self.escalation = None;
+                self.tracker.reset();
+            }
+        }
+        // Samples taken for a profile the user has since left don't count
+        if self.escalation.is_none() && self.tracked_profile != Some(active) {
+            self.tracker.reset();
+            self.tracked_profile = Some(active);
+        }
+
+        match &self.escalation {
+            Some(escalation) => Some(escalation.guard.clone()),
+            None => guards
+                .get(&active)
+                .filter(|guard| guard.escalate_to != active)
+                .cloned(),
+        }
+    }
+
+    /// Feed a temperature for `guard`, from [`ThermalState::guard()`]. An
+    /// escalation is recorded before it is returned, a restore is left for
+    /// the caller to take.
+    fn update(
+        &mut self,
+        active: Profile,
+        guard: &ThermalGuard,
+        temperature: u8,
+        now: Instant,
+    ) -> Option<ThermalGuardAction> {
+        let action = self.tracker.update(guard, temperature, now)?;
+        if action == ThermalGuardAction::Escalate {
+            self.escalation = Some(Escalation {
+                from: active,
+                guard: guard.clone(),
+            });
+        }
+        Some(action)
+    }
+
+    /// The profile to restore on boot, the one escalated from while `active`
+    /// is an escalation
+    fn persisted_profile(&self, active: Profile) -> Profile {
+        match &self.escalation {
+            Some(e) if e.guard.escalate_to == active => e.from,
+            _ => active,
+        }
+    }
+
+    /// If an escalation to `active` has its fan curves forced on
+    fn forces_fan_curves(&self, active: Profile) -> bool {
+        self.escalation
+            .as_ref()
+            .is_some_and(|e| e.guard.fan_curves && e.guard.escalate_to == active)
+    }
+}
+
 pub struct CtrlPlatformProfile {
     pub profile_config: ProfileConfig,
+    /// The profile in use. `profile_config.active_profile` is only what is
+    /// restored on boot, and differs while a thermal guard is escalated.
+    pub active_profile: Profile,
     pub fan_curves: Option<FanCurves>,
     pub platform: AsusPlatform,
+    power: Option<AsusPower>,
+    pub cpu: Option<CpuControl>,
+    thermal: ThermalState,
 }
 
 impl GetSupported for CtrlPlatformProfile {
@@ -66,22 +161,43 @@ impl GetSupported for CtrlPlatformProfile {
         }
 
         PlatformProfileFunctions {
-            platform_profile: Profile::is_platform_profile_supported(),
-            fans: res.unwrap_or_default(),
+            platform_profile: Profile::is_platform_profile_supported()
+                && !LaptopQuirks::get().is_broken("platform_profile"),
+            fans: LaptopQuirks::get().filter_fans(res.unwrap_or_default()),
         }
     }
 }
 
 impl CtrlPlatformProfile {
-    pub fn new(config: ProfileConfig) -> Result<Self, RogError> {
+    pub fn new(mut config: ProfileConfig) -> Result<Self, RogError> {
+        let choices = Profile::get_profile_names().ok();
+        diagnostics::record(diagnostics::platform_profile(choices.as_deref()));
+        diagnostics::record(diagnostics::fan_curves(
+            FanCurveProfiles::get_device().is_ok(),
+        ));
         let platform = AsusPlatform::new()?;
         if platform.has_platform_profile() || platform.has_throttle_thermal_policy() {
             info!("{MOD_NAME}: Device has profile control available");
 
+            let cpu = CpuControl::new()
+                .map_err(|e| info!("{MOD_NAME}: cpufreq control not available, {e}"))
+                .ok();
+            // Taken before asusd has ever applied a policy, a later start could
+            // read back a policy of the previous run
+            if config.cpu_default.is_none() {
+                config.cpu_default = cpu.as_ref().and_then(|c| c.get_policy().ok());
+                if config.cpu_default.is_some() {
+                    config.write();
+                }
+            }
             let mut controller = CtrlPlatformProfile {
+                active_profile: config.active_profile,
                 profile_config: config,
                 fan_curves: None,
                 platform,
+                power: AsusPower::new().ok(),
+                cpu,
+                thermal: ThermalState::default(),
             };
             if FanCurveProfiles::get_device().is_ok() {
                 info!("{MOD_NAME}: Device has fan curves available");
@@ -93,16 +209,18 @@ impl CtrlPlatformProfile {
                         config_file: fan_config,
                         profiles: FanCurveProfiles::default(),
                     });
-                    for _ in [Profile::Balanced, Profile::Performance, Profile::Quiet] {
+                    let choices = Profile::get_profile_names().unwrap_or_else(|_| {
+                        vec![Profile::Quiet, Profile::Balanced, Profile::Performance]
+                    });
+                    for _ in &choices {
                         // For each profile we need to switch to it before we
                         // can read the existing values from hardware. The ACPI method used
                         // for this is what limits us.
-                        let next =
-                            Profile::get_next_profile(controller.profile_config.active_profile);
+                        let next = Profile::get_next_profile(controller.active_profile, &choices);
                         Profile::set_profile(next)
                             .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
                             .ok();
-                        controller.profile_config.active_profile = next;
+                        controller.active_profile = next;
 
                         // Make sure to set the baseline to default
                         controller.set_active_curve_to_defaults()?;
@@ -135,7 +253,10 @@ impl CtrlPlatformProfile {
         Err(ProfileError::NotSupported.into())
     }
 
+    /// Write the config. While a thermal guard is escalated the profile from
+    /// before the escalation is written, so that it does not outlive a restart.
     pub fn save_config(&mut self) {
+        self.profile_config.active_profile = self.thermal.persisted_profile(self.active_profile);
         self.profile_config.write();
         if let Some(fans) = self.fan_curves.as_mut() {
             fans.update_config_from_profiles();
@@ -143,14 +264,123 @@ impl CtrlPlatformProfile {
         }
     }
 
-    /// Set the curve for the active profile active
+    /// Laptops without a detected battery are treated as being on AC
+    pub(super) fn on_mains(&self) -> bool {
+        self.power
+            .as_ref()
+            .and_then(|p| p.get_online().ok())
+            .map(|v| v == 1)
+            .unwrap_or(true)
+    }
+
+    /// Get the profile that `next_profile` should switch to, using the cycle
+    /// configured for the current power source
+    pub(super) fn next_profile(&self) -> Profile {
+        let choices = Profile::get_profile_names().unwrap_or_default();
+        let cycle = if self.on_mains() {
+            &self.profile_config.cycle_ac
+        } else {
+            &self.profile_config.cycle_bat
+        };
+        cycle.next_profile(self.active_profile, &choices)
+    }
+
+    /// Apply the `CpuPolicy` of the active profile, or the settings found on
+    /// the first start if the profile has none
+    pub(super) fn apply_cpu_policy(&self) {
+        if let Some(cpu) = &self.cpu {
+            if let Some(policy) = self
+                .profile_config
+                .cpu_policies
+                .get(&self.active_profile)
+                .or(self.profile_config.cpu_default.as_ref())
+            {
+                cpu.set_policy(policy)
+                    .map_err(|e| warn!("{MOD_NAME}: set_policy, {}", e))
+                    .ok();
+            }
+        }
+    }
+
+    /// Check the thermal guard of the active profile, escalating or switching
+    /// back if a window has elapsed. Should be called periodically.
+    pub(super) fn check_thermal_guard(&mut self) -> Option<ThermalGuardEvent> {
+        let active = self.active_profile;
+        let guard = self
+            .thermal
+            .guard(active, &self.profile_config.thermal_guards)?;
+        let temperature = guard
+            .read_temperature()
+            .map_err(|e| warn!("{MOD_NAME}: thermal guard, {e}"))
+            .ok()?;
+
+        match self
+            .thermal
+            .update(active, &guard, temperature, Instant::now())?
+        {
+            ThermalGuardAction::Escalate => {
+                info!(
+                    "{MOD_NAME}: temperature {temperature}C over {}C for {}s, switching {active} \
+                     to {}",
+                    guard.threshold, guard.window, guard.escalate_to
+                );
+                self.switch_profile(guard.escalate_to);
+                Some(ThermalGuardEvent {
+                    escalated: true,
+                    from: active,
+                    to: guard.escalate_to,
+                    temperature,
+                    threshold: guard.threshold,
+                })
+            }
+            ThermalGuardAction::Restore => {
+                let escalation = self.thermal.escalation.take()?;
+                info!(
+                    "{MOD_NAME}: temperature {temperature}C, switching back to {}",
+                    escalation.from
+                );
+                self.switch_profile(escalation.from);
+                Some(ThermalGuardEvent {
+                    escalated: false,
+                    from: escalation.guard.escalate_to,
+                    to: escalation.from,
+                    temperature,
+                    threshold: escalation.guard.threshold,
+                })
+            }
+        }
+    }
+
+    pub(super) fn switch_profile(&mut self, profile: Profile) {
+        Profile::set_profile(profile)
+            .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
+            .ok();
+        self.active_profile = profile;
+        self.write_profile_curve_to_platform()
+            .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+            .ok();
+        self.apply_cpu_policy();
+        self.save_config();
+    }
+
+    /// Set the curve for the active profile active. While a thermal guard with
+    /// `fan_curves` is escalated the curves are written enabled, without
+    /// changing the stored curves.
     pub(super) fn write_profile_curve_to_platform(&mut self) -> Result<(), RogError> {
+        let forced = self.thermal.forces_fan_curves(self.active_profile);
         if let Some(curves) = &mut self.fan_curves {
             if let Ok(mut device) = FanCurveProfiles::get_device() {
-                curves.profiles_mut().write_profile_curve_to_platform(
-                    self.profile_config.active_profile,
-                    &mut device,
-                )?;
+                if forced {
+                    for curve in curves.profiles().get_fan_curves_for(self.active_profile) {
+                        let mut curve = curve.clone();
+                        curve.enabled = true;
+                        curve.write_to_device(&mut device)?;
+                    }
+                } else {
+                    curves
+                        .profiles_mut()
+                        .write_profile_curve_to_platform(self.active_profile, &mut device)?;
+                }
             }
         }
         Ok(())
@@ -159,13 +389,83 @@ impl CtrlPlatformProfile {
     pub(super) fn set_active_curve_to_defaults(&mut self) -> Result<(), RogError> {
         if let Some(curves) = self.fan_curves.as_mut() {
             if let Ok(mut device) = FanCurveProfiles::get_device() {
-                curves.profiles_mut().set_active_curve_to_defaults(
-                    self.profile_config.active_profile,
-                    &mut device,
-                )?;
+                curves
+                    .profiles_mut()
+                    .set_active_curve_to_defaults(self.active_profile, &mut device)?;
                 curves.update_config_from_profiles();
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+
+    use config_traits::StdConfig;
+    use rog_profiles::thermal_guard::{ThermalGuard, ThermalGuardAction};
+    use rog_profiles::Profile;
+
+    use super::ThermalState;
+    use crate::ctrl_profiles::config::ProfileConfig;
+
+    #[test]
+    fn escalation_survives_config_read() {
+        let guard = ThermalGuard {
+            window: 0,
+            fan_curves: true,
+            ..Default::default()
+        };
+        let mut config = ProfileConfig::new();
+        config.active_profile = Profile::Quiet;
+        config.thermal_guards.insert(Profile::Quiet, guard.clone());
+        let mut active = config.active_profile;
+        let mut thermal = ThermalState::default();
+
+        let g = thermal.guard(active, &config.thermal_guards).unwrap();
+        assert_eq!(
+            thermal.update(active, &g, 95, Instant::now()),
+            Some(ThermalGuardAction::Escalate)
+        );
+        active = g.escalate_to;
+        // Only the profile escalated from is written
+        config.active_profile = thermal.persisted_profile(active);
+        assert_eq!(config.active_profile, Profile::Quiet);
+        assert!(thermal.forces_fan_curves(active));
+
+        // A getter re-reading the config replaces it with what is on disk
+        let thermal_guards: BTreeMap<_, _> = config.thermal_guards.clone();
+        config = ProfileConfig::new();
+        config.active_profile = Profile::Quiet;
+        config.thermal_guards = thermal_guards;
+
+        assert_eq!(active, Profile::Performance);
+        assert_eq!(thermal.guard(active, &config.thermal_guards), Some(guard));
+        assert_eq!(thermal.persisted_profile(active), Profile::Quiet);
+        assert!(thermal.forces_fan_curves(active));
+        assert_eq!(thermal.update(active, &g, 95, Instant::now()), None);
+    }
+
+    #[test]
+    fn escalation_dropped_on_profile_change() {
+        let mut guards = BTreeMap::new();
+        guards.insert(
+            Profile::Quiet,
+            ThermalGuard {
+                window: 0,
+                ..Default::default()
+            },
+        );
+        let mut thermal = ThermalState::default();
+        let g = thermal.guard(Profile::Quiet, &guards).unwrap();
+        thermal.update(Profile::Quiet, &g, 95, Instant::now());
+
+        assert!(thermal.guard(Profile::Balanced, &guards).is_none());
+        assert_eq!(
+            thermal.persisted_profile(Profile::Balanced),
+            Profile::Balanced
+        );
+    }
+}
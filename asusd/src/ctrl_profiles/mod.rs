@@ -1,4 +1,6 @@
 pub mod config;
 pub mod controller;
+/// The power-profiles-daemon interface
+pub mod ppd;
 /// Implements `CtrlTask`, Reloadable, `ZbusRun`
 pub mod trait_impls;
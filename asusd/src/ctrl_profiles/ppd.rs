@@ -0,0 +1,420 @@
+//! The `net.hadess.PowerProfiles` interface of power-profiles-daemon, so that
+//! the profile switchers of desktops control `platform_profile` through asusd.
+//! Only served if `ppd_compat` is set in `asusd.ron`, and the name can only be
+//! owned if power-profiles-daemon is not running.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use config_traits::StdConfig;
+use log::{info, warn};
+use rog_dbus::DBUS_IFACE;
+use rog_profiles::Profile;
+use zbus::export::futures_util::lock::Mutex;
+use zbus::export::futures_util::StreamExt;
+use zbus::fdo::{DBusProxy, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{
+    dbus_interface, Connection, MatchRule, MessageHeader, MessageStream, MessageType, SignalContext,
+};
+
+use super::controller::CtrlPlatformProfile;
+use super::trait_impls::ProfileZbus;
+use crate::error::RogError;
+use crate::CtrlTask;
+
+pub const PPD_NAME: &str = "net.hadess.PowerProfiles";
+const PPD_PATH: &str = "/net/hadess/PowerProfiles";
+const MOD_NAME: &str = "PowerProfilesZbus";
+const DRIVER: &str = "asusd";
+
+const POWER_SAVER: &str = "power-saver";
+const BALANCED: &str = "balanced";
+const PERFORMANCE: &str = "performance";
+
+/// The power-profiles-daemon names, and the profiles each may use in order of
+/// preference
+const PPD_PROFILES: [(&str, &[Profile]); 3] = [
+    (
+        POWER_SAVER,
+        &[Profile::Quiet, Profile::LowPower, Profile::Cool],
+    ),
+    (BALANCED, &[Profile::Balanced, Profile::Custom]),
+    (
+        PERFORMANCE,
+        &[
+            Profile::Performance,
+            Profile::BalancedPerformance,
+            Profile::MaxPower,
+        ],
+    ),
+];
+
+/// The power-profiles-daemon name shown for `profile`
+fn ppd_name(profile: Profile) -> &'static str {
+    PPD_PROFILES
+        .iter()
+        .find(|(_, profiles)| profiles.contains(&profile))
+        .map(|(name, _)| *name)
+        .unwrap_or(BALANCED)
+}
+
+/// The profile to use for the power-profiles-daemon `name`, from the `choices`
+/// of the kernel
+fn from_ppd_name(name: &str, choices: &[Profile]) -> Option<Profile> {
+    PPD_PROFILES
+        .iter()
+        .find(|(n, _)| *n == name)?
+        .1
+        .iter()
+        .find(|p| choices.contains(p))
+        .copied()
+}
+
+fn choices() -> Vec<Profile> {
+    Profile::get_profile_names().unwrap_or_default()
+}
+
+struct Hold {
+    cookie: u32,
+    profile: &'static str,
+    reason: String,
+    application_id: String,
+    /// The unique bus name of the caller, the hold is released if it exits
+    sender: String,
+}
+
+#[derive(Default)]
+struct Holds {
+    holds: Vec<Hold>,
+    next_cookie: u32,
+    /// The profile to return to once all holds are released
+    restore: Option<Profile>,
+}
+
+impl Holds {
+    /// The profile the holds require, `performance` wins over `power-saver`
+    fn wanted(&self) -> Option<&'static str> {
+        if self.holds.iter().any(|h| h.profile == PERFORMANCE) {
+            Some(PERFORMANCE)
+        } else {
+            self.holds.first().map(|h| h.profile)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PowerProfilesZbus {
+    ctrl: Arc<Mutex<CtrlPlatformProfile>>,
+    holds: Arc<Mutex<Holds>>,
+    ppd_ctx: SignalContext<'static>,
+    /// Changes are announced with `NotifyProfile` of `ProfileZbus`, which this
+    /// interface also follows
+    profile_ctx: SignalContext<'static>,
+}
+
+impl PowerProfilesZbus {
+    pub fn new(profiles: &ProfileZbus, connection: &Connection) -> Result<Self, RogError> {
+        Ok(Self {
+            ctrl: profiles.0.clone(),
+            holds: Arc::new(Mutex::new(Holds::default())),
+            ppd_ctx: Self::signal_context(connection)?,
+            profile_ctx: ProfileZbus::signal_context(connection)?,
+        })
+    }
+
+    async fn active(&self) -> Profile {
+        self.ctrl.lock().await.active_profile
+    }
+
+    async fn switch_to(&self, profile: Profile) {
+        let mut ctrl = self.ctrl.lock().await;
+        ctrl.profile_config.read();
+        ctrl.switch_profile(profile);
+        drop(ctrl);
+        ProfileZbus::notify_profile(&self.profile_ctx, profile)
+            .await
+            .ok();
+    }
+
+    /// Switch to the profile the holds require, or back to the one active
+    /// before the first hold
+    async fn apply_holds(&self) {
+        let mut holds = self.holds.lock().await;
+        let target = match holds.wanted() {
+            Some(name) => from_ppd_name(name, &choices()),
+            None => holds.restore.take(),
+        };
+        drop(holds);
+        if let Some(profile) = target {
+            self.switch_to(profile).await;
+        }
+    }
+
+    /// Remove the holds matching `pred`, returns false if there were none
+    async fn release_where(&self, pred: impl Fn(&Hold) -> bool) -> bool {
+        let mut holds = self.holds.lock().await;
+        let released: Vec<u32> = holds
+            .holds
+            .iter()
+            .filter(|h| pred(h))
+            .map(|h| h.cookie)
+            .collect();
+        holds.holds.retain(|h| !pred(h));
+        drop(holds);
+        if released.is_empty() {
+            return false;
+        }
+
+        for cookie in released {
+            info!("{MOD_NAME}: released hold {cookie}");
+            Self::profile_released(&self.ppd_ctx, cookie).await.ok();
+        }
+        self.apply_holds().await;
+        self.active_profile_holds_changed(&self.ppd_ctx).await.ok();
+        true
+    }
+}
+
+#[dbus_interface(name = "net.hadess.PowerProfiles")]
+impl PowerProfilesZbus {
+    /// Hold `performance` or `power-saver` until `ReleaseProfile` is called
+    /// with the returned cookie, or the caller exits. If both are held
+    /// `performance` is used.
+    async fn hold_profile(
+        &self,
+        #[zbus(header)] header: MessageHeader<'_>,
+        profile: &str,
+        reason: &str,
+        application_id: &str,
+    ) -> zbus::fdo::Result<u32> {
+        let profile = [PERFORMANCE, POWER_SAVER]
+            .into_iter()
+            .find(|p| *p == profile)
+            .ok_or_else(|| {
+                Error::InvalidArgs(format!("Only {PERFORMANCE} and {POWER_SAVER} can be held"))
+            })?;
+        if from_ppd_name(profile, &choices()).is_none() {
+            return Err(Error::NotSupported(format!(
+                "{profile} is not available on this laptop"
+            )));
+        }
+        let sender = header.sender()?.map(|s| s.to_string()).unwrap_or_default();
+
+        let mut holds = self.holds.lock().await;
+        if holds.holds.is_empty() {
+            holds.restore = Some(self.active().await);
+        }
+        holds.next_cookie += 1;
+        let cookie = holds.next_cookie;
+        info!("{MOD_NAME}: {application_id} holds {profile} ({reason}), cookie {cookie}");
+        holds.holds.push(Hold {
+            cookie,
+            profile,
+            reason: reason.to_owned(),
+            application_id: application_id.to_owned(),
+            sender,
+        });
+        drop(holds);
+
+        self.apply_holds().await;
+        self.active_profile_holds_changed(&self.ppd_ctx).await.ok();
+        Ok(cookie)
+    }
+
+    /// Release a hold made with `HoldProfile`
+    async fn release_profile(&self, cookie: u32) -> zbus::fdo::Result<()> {
+        if self.release_where(|h| h.cookie == cookie).await {
+            Ok(())
+        } else {
+            Err(Error::InvalidArgs(format!("No hold with cookie {cookie}")))
+        }
+    }
+
+    /// One of `power-saver`, `balanced` or `performance`
+    #[dbus_interface(property)]
+    async fn active_profile(&self) -> String {
+        ppd_name(self.active().await).to_owned()
+    }
+
+    /// Setting a profile releases all holds
+    #[dbus_interface(property)]
+    async fn set_active_profile(&self, profile: String) -> zbus::Result<()> {
+        let target = from_ppd_name(&profile, &choices())
+            .ok_or_else(|| Error::InvalidArgs(format!("{profile} is not available")))?;
+        self.holds.lock().await.restore = None;
+        self.release_where(|_| true).await;
+        self.switch_to(target).await;
+        Ok(())
+    }
+
+    /// The available profiles, each the profile name and the driver
+    #[dbus_interface(property)]
+    async fn profiles(&self) -> Vec<HashMap<String, OwnedValue>> {
+        let choices = choices();
+        PPD_PROFILES
+            .iter()
+            .filter(|(name, _)| from_ppd_name(name, &choices).is_some())
+            .map(|(name, _)| {
+                HashMap::from([
+                    ("Profile".to_owned(), Value::from(*name).into()),
+                    ("Driver".to_owned(), Value::from(DRIVER).into()),
+                    ("PlatformDriver".to_owned(), Value::from(DRIVER).into()),
+                ])
+            })
+            .collect()
+    }
+
+    /// Not supported, always empty
+    #[dbus_interface(property)]
+    async fn actions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The reason `performance` is degraded. asusd has no source for this so
+    /// it is always empty.
+    #[dbus_interface(property)]
+    async fn performance_degraded(&self) -> String {
+        String::new()
+    }
+
+    /// Deprecated by `PerformanceDegraded`, always empty
+    #[dbus_interface(property)]
+    async fn performance_inhibited(&self) -> String {
+        String::new()
+    }
+
+    /// The holds made with `HoldProfile`, each the application ID, profile
+    /// and reason
+    #[dbus_interface(property)]
+    async fn active_profile_holds(&self) -> Vec<HashMap<String, OwnedValue>> {
+        self.holds
+            .lock()
+            .await
+            .holds
+            .iter()
+            .map(|h| {
+                HashMap::from([
+                    (
+                        "ApplicationId".to_owned(),
+                        Value::from(h.application_id.as_str()).into(),
+                    ),
+                    ("Profile".to_owned(), Value::from(h.profile).into()),
+                    ("Reason".to_owned(), Value::from(h.reason.as_str()).into()),
+                ])
+            })
+            .collect()
+    }
+
+    /// Emitted when a hold is released other than by `ReleaseProfile`
+    #[dbus_interface(signal)]
+    async fn profile_released(signal_ctxt: &SignalContext<'_>, cookie: u32) -> zbus::Result<()>;
+}
+
+#[async_trait]
+impl crate::ZbusRun for PowerProfilesZbus {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, PPD_PATH, server).await;
+    }
+}
+
+#[async_trait]
+impl crate::Reloadable for PowerProfilesZbus {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CtrlTask for PowerProfilesZbus {
+    fn zbus_path() -> &'static str {
+        PPD_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        // Every profile change made by asusd, or by another program through
+        // platform_profile, is followed by `NotifyProfile`
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface(DBUS_IFACE)?
+            .path(ProfileZbus::zbus_path())?
+            .member("NotifyProfile")?
+            .build();
+        let mut stream =
+            MessageStream::for_match_rule(rule, signal_ctxt.connection(), None).await?;
+        let ppd = self.clone();
+        let sig_ctx = signal_ctxt.clone();
+        crate::spawn_task(async move {
+            while let Some(Ok(msg)) = stream.next().await {
+                if msg.body::<Profile>().is_ok() {
+                    ppd.active_profile_changed(&sig_ctx).await.ok();
+                }
+            }
+        });
+
+        let dbus = DBusProxy::new(signal_ctxt.connection()).await?;
+        let mut owners = dbus.receive_name_owner_changed().await?;
+        let ppd = self.clone();
+        crate::spawn_task(async move {
+            while let Some(signal) = owners.next().await {
+                let args = match signal.args() {
+                    Ok(args) => args,
+                    Err(e) => {
+                        warn!("{MOD_NAME}: NameOwnerChanged: {e}");
+                        continue;
+                    }
+                };
+                if args.new_owner().is_none() {
+                    let name = args.name().to_string();
+                    ppd.release_where(|h| h.sender == name).await;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rog_profiles::Profile;
+
+    use super::{from_ppd_name, ppd_name, Hold, Holds, PERFORMANCE, POWER_SAVER};
+
+    #[test]
+    fn map_ppd_names() {
+        let choices = [Profile::LowPower, Profile::Balanced, Profile::Performance];
+        assert_eq!(
+            from_ppd_name("power-saver", &choices),
+            Some(Profile::LowPower)
+        );
+        assert_eq!(from_ppd_name("balanced", &choices), Some(Profile::Balanced));
+        assert_eq!(
+            from_ppd_name("performance", &choices),
+            Some(Profile::Performance)
+        );
+        assert_eq!(from_ppd_name("turbo", &choices), None);
+        assert_eq!(from_ppd_name("power-saver", &[Profile::Balanced]), None);
+
+        assert_eq!(ppd_name(Profile::Quiet), "power-saver");
+        assert_eq!(ppd_name(Profile::BalancedPerformance), "performance");
+        assert_eq!(ppd_name(Profile::Custom), "balanced");
+    }
+
+    #[test]
+    fn performance_hold_wins() {
+        let hold = |cookie, profile| Hold {
+            cookie,
+            profile,
+            reason: String::new(),
+            application_id: String::new(),
+            sender: String::new(),
+        };
+        let mut holds = Holds::default();
+        assert_eq!(holds.wanted(), None);
+        holds.holds.push(hold(1, POWER_SAVER));
+        assert_eq!(holds.wanted(), Some(POWER_SAVER));
+        holds.holds.push(hold(2, PERFORMANCE));
+        assert_eq!(holds.wanted(), Some(PERFORMANCE));
+    }
+}
@@ -1,11 +1,16 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{error, info, warn};
+use rog_platform::cpu::CpuPolicy;
 use rog_profiles::fan_curve_set::CurveData;
-use rog_profiles::{FanCurvePU, FanCurveProfiles, Profile};
+use rog_profiles::thermal_guard::{ThermalGuard, ThermalGuardEvent};
+use rog_profiles::{FanCurvePU, FanCurveProfiles, Profile, ProfileCycle};
+use tokio::time::sleep;
 use zbus::export::futures_util::lock::Mutex;
 use zbus::export::futures_util::StreamExt;
 use zbus::fdo::Error;
@@ -18,6 +23,7 @@ use crate::CtrlTask;
 const MOD_NAME: &str = "ProfileZbus";
 
 const ZBUS_PATH: &str = "/org/asuslinux/Profile";
+const THERMAL_GUARD_POLL: Duration = Duration::from_secs(1);
 const UNSUPPORTED_MSG: &str =
     "Fan curves are not supported on this laptop or you require a patched kernel";
 
@@ -40,47 +46,207 @@ impl ProfileZbus {
     /// If fan-curves are supported will also activate a fan curve for profile.
     async fn next_profile(&mut self, #[zbus(signal_context)] ctxt: SignalContext<'_>) {
         let mut ctrl = self.0.lock().await;
-        let next = Profile::get_next_profile(ctrl.profile_config.active_profile);
+        ctrl.profile_config.read();
+        let next = ctrl.next_profile();
         Profile::set_profile(next)
             .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
             .ok();
-        ctrl.profile_config.active_profile = next;
+        ctrl.active_profile = next;
+        ctrl.apply_cpu_policy();
         ctrl.save_config();
 
-        Self::notify_profile(&ctxt, ctrl.profile_config.active_profile)
-            .await
-            .ok();
+        Self::notify_profile(&ctxt, ctrl.active_profile).await.ok();
     }
 
     /// Fetch the active profile name
     async fn active_profile(&mut self) -> zbus::fdo::Result<Profile> {
-        let mut ctrl = self.0.lock().await;
-        ctrl.profile_config.read();
-        Ok(ctrl.profile_config.active_profile)
+        let ctrl = self.0.lock().await;
+        Ok(ctrl.active_profile)
     }
 
-    /// Set this platform_profile name as active
+    /// Set this platform_profile name as active. The profile must be one the
+    /// kernel advertises.
     async fn set_active_profile(
         &self,
         #[zbus(signal_context)] ctxt: SignalContext<'_>,
         profile: Profile,
-    ) {
+    ) -> zbus::fdo::Result<()> {
+        let choices = Profile::get_profile_names().map_err(|e| Error::Failed(e.to_string()))?;
+        if !choices.contains(&profile) {
+            return Err(Error::InvalidArgs(format!(
+                "{profile} is not an available platform_profile"
+            )));
+        }
+        Profile::set_profile(profile).map_err(|e| {
+            warn!("{MOD_NAME}: set_profile, {}", e);
+            Error::Failed(e.to_string())
+        })?;
+
         let mut ctrl = self.0.lock().await;
         // Read first just incase the user has modified the config before calling this
         ctrl.profile_config.read();
-        Profile::set_profile(profile)
-            .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
-            .ok();
-        ctrl.profile_config.active_profile = profile;
+        ctrl.active_profile = profile;
         ctrl.write_profile_curve_to_platform()
             .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
             .ok();
+        ctrl.apply_cpu_policy();
 
         ctrl.save_config();
 
-        Self::notify_profile(&ctxt, ctrl.profile_config.active_profile)
-            .await
-            .ok();
+        Self::notify_profile(&ctxt, ctrl.active_profile).await.ok();
+        Ok(())
+    }
+
+    /// Fetch the order and skip list used by `NextProfile`. `mains` selects
+    /// the cycle for AC power, otherwise the cycle for battery is returned.
+    async fn profile_cycle(&mut self, mains: bool) -> ProfileCycle {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        if mains {
+            ctrl.profile_config.cycle_ac.clone()
+        } else {
+            ctrl.profile_config.cycle_bat.clone()
+        }
+    }
+
+    /// Set the order and skip list used by `NextProfile`. `mains` selects the
+    /// cycle for AC power, otherwise the cycle for battery is set. An empty
+    /// order uses the order of `Profiles`.
+    async fn set_profile_cycle(
+        &mut self,
+        mains: bool,
+        cycle: ProfileCycle,
+    ) -> zbus::fdo::Result<()> {
+        let choices = Profile::get_profile_names().map_err(RogError::from)?;
+        if let Some(p) = cycle
+            .order
+            .iter()
+            .chain(cycle.skip.iter())
+            .find(|p| !choices.contains(p))
+        {
+            return Err(Error::InvalidArgs(format!(
+                "{p} is not an available platform_profile"
+            )));
+        }
+
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        if mains {
+            ctrl.profile_config.cycle_ac = cycle;
+        } else {
+            ctrl.profile_config.cycle_bat = cycle;
+        }
+        ctrl.save_config();
+        Ok(())
+    }
+
+    /// Fetch the thermal guards, keyed by the profile each watches
+    async fn thermal_guards(&mut self) -> BTreeMap<Profile, ThermalGuard> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.thermal_guards.clone()
+    }
+
+    /// Set the thermal guard for a profile. While this profile is active and
+    /// the temperature stays over the threshold for the window, the guard
+    /// switches to its `escalate_to` profile.
+    async fn set_thermal_guard(
+        &mut self,
+        profile: Profile,
+        guard: ThermalGuard,
+    ) -> zbus::fdo::Result<()> {
+        let choices = Profile::get_profile_names().map_err(RogError::from)?;
+        if let Some(p) = [profile, guard.escalate_to]
+            .iter()
+            .find(|p| !choices.contains(p))
+        {
+            return Err(Error::InvalidArgs(format!(
+                "{p} is not an available platform_profile"
+            )));
+        }
+        if profile == guard.escalate_to {
+            return Err(Error::InvalidArgs(
+                "A thermal guard must escalate to a different profile".to_owned(),
+            ));
+        }
+        if guard.hysteresis > guard.threshold {
+            return Err(Error::InvalidArgs(
+                "Thermal guard hysteresis can not be larger than the threshold".to_owned(),
+            ));
+        }
+
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.thermal_guards.insert(profile, guard);
+        ctrl.save_config();
+        Ok(())
+    }
+
+    /// Remove the thermal guard for a profile
+    async fn remove_thermal_guard(&mut self, profile: Profile) {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.thermal_guards.remove(&profile);
+        ctrl.save_config();
+    }
+
+    /// Fetch the cpufreq settings, keyed by the profile they are applied with
+    async fn cpu_policies(&mut self) -> BTreeMap<Profile, CpuPolicy> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.cpu_policies.clone()
+    }
+
+    /// Set the cpufreq governor, EPP and boost to use with a profile. Empty
+    /// strings leave that setting unchanged. Applied now if the profile is
+    /// active.
+    async fn set_cpu_policy(
+        &mut self,
+        profile: Profile,
+        policy: CpuPolicy,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        let cpu = ctrl
+            .cpu
+            .as_ref()
+            .ok_or_else(|| Error::NotSupported("cpufreq control not available".to_owned()))?;
+        cpu.validate(&policy)
+            .map_err(|e| Error::InvalidArgs(e.to_string()))?;
+
+        ctrl.profile_config.read();
+        ctrl.profile_config.cpu_policies.insert(profile, policy);
+        if ctrl.active_profile == profile {
+            ctrl.apply_cpu_policy();
+        }
+        ctrl.save_config();
+        Ok(())
+    }
+
+    /// Remove the cpufreq settings of a profile, the settings found on start
+    /// are used for it instead
+    async fn remove_cpu_policy(&mut self, profile: Profile) {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.cpu_policies.remove(&profile);
+        if ctrl.active_profile == profile {
+            ctrl.apply_cpu_policy();
+        }
+        ctrl.save_config();
+    }
+
+    /// Fetch the available cpufreq governors and energy performance
+    /// preferences
+    async fn cpu_policy_choices(&self) -> (Vec<String>, Vec<String>) {
+        let ctrl = self.0.lock().await;
+        ctrl.cpu
+            .as_ref()
+            .map(|c| {
+                (
+                    c.available_governors(),
+                    c.available_energy_performance_preferences(),
+                )
+            })
+            .unwrap_or_default()
     }
 
     /// Set all fan curves for a profile to enabled status. Will also activate a
@@ -206,8 +372,19 @@ impl ProfileZbus {
     }
 
     #[dbus_interface(signal)]
-    async fn notify_profile(signal_ctxt: &SignalContext<'_>, profile: Profile) -> zbus::Result<()> {
+    pub(super) async fn notify_profile(
+        signal_ctxt: &SignalContext<'_>,
+        profile: Profile,
+    ) -> zbus::Result<()> {
     }
+
+    /// Emitted when a thermal guard switches profile because of temperature,
+    /// or switches back
+    #[dbus_interface(signal)]
+    async fn notify_thermal_guard(
+        signal_ctxt: &SignalContext<'_>,
+        event: ThermalGuardEvent,
+    ) -> zbus::Result<()>;
 }
 
 #[async_trait]
@@ -224,6 +401,21 @@ impl CtrlTask for ProfileZbus {
     }
 
     async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        let ctrl = self.0.clone();
+        let sig_ctx = signal_ctxt.clone();
+        crate::spawn_task(async move {
+            loop {
+                sleep(THERMAL_GUARD_POLL).await;
+                let mut lock = ctrl.lock().await;
+                if let Some(event) = lock.check_thermal_guard() {
+                    Self::notify_thermal_guard(&sig_ctx, event).await.ok();
+                    Self::notify_profile(&sig_ctx, lock.active_profile)
+                        .await
+                        .ok();
+                }
+            }
+        });
+
         let ctrl = self.0.clone();
         let sig_ctx = signal_ctxt.clone();
         let watch = self
@@ -233,7 +425,7 @@ impl CtrlTask for ProfileZbus {
             .platform
             .monitor_throttle_thermal_policy()?;
 
-        tokio::spawn(async move {
+        crate::spawn_task(async move {
             let mut buffer = [0; 32];
             if let Ok(stream) = watch.into_event_stream(&mut buffer) {
                 stream
@@ -245,18 +437,19 @@ impl CtrlTask for ProfileZbus {
                             })
                         {
                             let new_profile = Profile::from_throttle_thermal_policy(profile);
-                            if new_profile != lock.profile_config.active_profile {
+                            if new_profile != lock.active_profile {
                                 info!("{MOD_NAME}: platform_profile changed to {new_profile}");
-                                lock.profile_config.active_profile = new_profile;
+                                lock.active_profile = new_profile;
                                 lock.write_profile_curve_to_platform().unwrap();
+                                lock.apply_cpu_policy();
                                 lock.save_config();
-                                Profile::set_profile(lock.profile_config.active_profile)
+                                Profile::set_profile(lock.active_profile)
                                     .map_err(|e| {
                                         error!("Profile::set_profile() error: {e}");
                                     })
                                     .ok();
 
-                                Self::notify_profile(&sig_ctx, lock.profile_config.active_profile)
+                                Self::notify_profile(&sig_ctx, lock.active_profile)
                                     .await
                                     .ok();
                             }
@@ -269,7 +462,7 @@ impl CtrlTask for ProfileZbus {
         let ctrl = self.0.clone();
         let watch = self.0.lock().await.platform.monitor_platform_profile()?;
 
-        tokio::spawn(async move {
+        crate::spawn_task(async move {
             let mut buffer = [0; 32];
             if let Ok(stream) = watch.into_event_stream(&mut buffer) {
                 stream
@@ -281,23 +474,21 @@ impl CtrlTask for ProfileZbus {
                             if let Ok(new_profile) = Profile::from_str(&profile).map_err(|e| {
                                 error!("Profile::from_str(&profile) error: {e}");
                             }) {
-                                if new_profile != lock.profile_config.active_profile {
+                                if new_profile != lock.active_profile {
                                     info!("{MOD_NAME}: platform_profile changed to {new_profile}");
-                                    lock.profile_config.active_profile = new_profile;
+                                    lock.active_profile = new_profile;
                                     lock.write_profile_curve_to_platform().unwrap();
+                                    lock.apply_cpu_policy();
                                     lock.save_config();
-                                    Profile::set_profile(lock.profile_config.active_profile)
+                                    Profile::set_profile(lock.active_profile)
                                         .map_err(|e| {
                                             error!("Profile::set_profile() error: {e}");
                                         })
                                         .ok();
 
-                                    Self::notify_profile(
-                                        &signal_ctxt,
-                                        lock.profile_config.active_profile,
-                                    )
-                                    .await
-                                    .ok();
+                                    Self::notify_profile(&signal_ctxt, lock.active_profile)
+                                        .await
+                                        .ok();
                                 }
                             }
                         }
@@ -315,7 +506,8 @@ impl crate::Reloadable for ProfileZbus {
     /// Fetch the active profile and use that to set all related components up
     async fn reload(&mut self) -> Result<(), RogError> {
         let mut ctrl = self.0.lock().await;
-        let active = ctrl.profile_config.active_profile;
+        ctrl.apply_cpu_policy();
+        let active = ctrl.active_profile;
         if let Some(curves) = &mut ctrl.fan_curves {
             if let Ok(mut device) = FanCurveProfiles::get_device() {
                 // There is a possibility that the curve was default zeroed, so this call
@@ -1,15 +1,20 @@
 use async_trait::async_trait;
+use rog_platform::supported::Probe;
 use serde_derive::{Deserialize, Serialize};
 use zbus::zvariant::Type;
-use zbus::{dbus_interface, Connection};
+use zbus::{dbus_interface, Connection, SignalContext};
 
 use crate::ctrl_anime::CtrlAnime;
 use crate::ctrl_aura::controller::CtrlKbdLed;
 use crate::ctrl_platform::CtrlPlatform;
 use crate::ctrl_power::CtrlPower;
 use crate::ctrl_profiles::controller::CtrlPlatformProfile;
+use crate::diagnostics;
+use crate::error::RogError;
 use crate::GetSupported;
 
+const ZBUS_PATH: &str = "/org/asuslinux/Supported";
+
 #[derive(Serialize, Deserialize, Debug, Type)]
 pub struct SupportedFunctions(rog_platform::supported::SupportedFunctions);
 
@@ -21,16 +26,44 @@ impl SupportedFunctions {
         Ok(&self.0)
     }
 
+    /// The checks made by the controllers at startup, updated as USB devices
+    /// are added or removed
+    pub fn diagnostics(&self) -> Vec<Probe> {
+        diagnostics::recorded()
+    }
+
     #[dbus_interface(out_args("answer", "question"))]
     fn meaning_of_life(&self) -> zbus::fdo::Result<(i32, String)> {
         Ok((42, String::from("Meaning of life")))
     }
+
+    /// Emitted when a device such as the keyboard or AniMe is added or removed
+    #[dbus_interface(signal)]
+    pub async fn notify_supported_functions(
+        ctxt: &SignalContext<'_>,
+        data: &rog_platform::supported::SupportedFunctions,
+    ) -> zbus::Result<()>;
+}
+
+impl SupportedFunctions {
+    /// Detect the supported functions again, update the served object and
+    /// notify listeners
+    pub async fn refresh(connection: &Connection) -> Result<(), RogError> {
+        let iface = connection
+            .object_server()
+            .interface::<_, SupportedFunctions>(ZBUS_PATH)
+            .await?;
+        let mut supported = iface.get_mut().await;
+        *supported = Self::get_supported();
+        Self::notify_supported_functions(iface.signal_context(), &supported.0).await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl crate::ZbusRun for SupportedFunctions {
     async fn add_to_server(self, server: &mut Connection) {
-        Self::add_to_server_helper(self, "/org/asuslinux/Supported", server).await;
+        Self::add_to_server_helper(self, ZBUS_PATH, server).await;
     }
 }
 
@@ -1,6 +1,7 @@
 use std::env;
 use std::error::Error;
 use std::io::Write;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -12,21 +13,32 @@ use asusd::ctrl_anime::trait_impls::CtrlAnimeZbus;
 use asusd::ctrl_anime::CtrlAnime;
 use asusd::ctrl_aura::controller::CtrlKbdLed;
 use asusd::ctrl_aura::trait_impls::CtrlKbdLedZbus;
+use asusd::ctrl_firmware_attributes::CtrlFirmwareAttributes;
 use asusd::ctrl_platform::CtrlPlatform;
 use asusd::ctrl_power::CtrlPower;
 use asusd::ctrl_profiles::config::ProfileConfig;
 use asusd::ctrl_profiles::controller::CtrlPlatformProfile;
+use asusd::ctrl_profiles::ppd::{PowerProfilesZbus, PPD_NAME};
 use asusd::ctrl_profiles::trait_impls::ProfileZbus;
 use asusd::ctrl_supported::SupportedFunctions;
-use asusd::{print_board_info, CtrlTask, GetSupported, Reloadable, ZbusRun};
-use config_traits::{StdConfig, StdConfigLoad, StdConfigLoad2};
+use asusd::diagnostics;
+use asusd::error::RogError;
+use asusd::hotplug::watch_usb_devices;
+use asusd::{print_board_info, CtrlTask, CtrlTasks, GetSupported, Reloadable, ZbusRun};
+use config_traits::{StdConfig, StdConfigLoad1, StdConfigLoad2, StdConfigLoad3};
 use log::{error, info, warn};
 use rog_aura::aura_detection::LaptopLedData;
+use rog_aura::usb::AuraDevice;
 use rog_dbus::DBUS_NAME;
+use rog_platform::capture;
 use rog_profiles::Profile;
+use tokio::sync::mpsc::unbounded_channel;
 use tokio::time::sleep;
 use zbus::SignalContext;
 
+/// Time to wait after a USB device is added or removed before rescanning
+const HOTPLUG_SETTLE: Duration = Duration::from_millis(1500);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut logger = env_logger::Builder::new();
@@ -66,11 +78,15 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
     let supported = SupportedFunctions::get_supported();
     print_board_info();
     println!("{:?}", supported.supported_functions());
+    diagnostics::record_system();
 
     // Start zbus server
     let mut connection = Connection::system().await?;
 
     let config = Config::new().load();
+    // Must be enabled before any controller writes to the hardware
+    enable_capture(&config);
+    let ppd_compat = config.ppd_compat;
     let config = Arc::new(Mutex::new(config));
 
     supported.add_to_server(&mut connection).await;
@@ -95,11 +111,32 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    match CtrlFirmwareAttributes::new() {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlFirmwareAttributes::signal_context(&connection)?;
+            start_tasks(ctrl, &mut connection, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("CtrlFirmwareAttributes: {}", err);
+        }
+    }
+
+    let mut ppd_served = false;
     if Profile::is_platform_profile_supported() {
         let profile_config = ProfileConfig::new().load();
         match CtrlPlatformProfile::new(profile_config) {
             Ok(ctrl) => {
                 let zbus = ProfileZbus(Arc::new(Mutex::new(ctrl)));
+                if ppd_compat {
+                    match PowerProfilesZbus::new(&zbus, &connection) {
+                        Ok(ppd) => {
+                            let sig_ctx = PowerProfilesZbus::signal_context(&connection)?;
+                            start_tasks(ppd, &mut connection, sig_ctx).await?;
+                            ppd_served = true;
+                        }
+                        Err(err) => error!("PowerProfilesZbus: {}", err),
+                    }
+                }
                 let sig_ctx = ProfileZbus::signal_context(&connection)?;
                 start_tasks(zbus, &mut connection, sig_ctx).await?;
             }
@@ -109,35 +146,56 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
         }
     } else {
         warn!("platform_profile support not found");
+        diagnostics::record(diagnostics::platform_profile(None));
+        diagnostics::record(diagnostics::fan_curves(false));
     }
 
-    match CtrlAnime::new(AnimeConfig::new().load()) {
-        Ok(ctrl) => {
-            let zbus = CtrlAnimeZbus(Arc::new(Mutex::new(ctrl)));
-            let sig_ctx = CtrlAnimeZbus::signal_context(&connection)?;
-            start_tasks(zbus, &mut connection, sig_ctx).await?;
-        }
-        Err(err) => {
-            info!("AniMe control: {}", err);
-        }
-    }
+    // The keyboard and AniMe are USB devices which may appear or vanish while
+    // running, such as after resume on some models or when rebound
+    let mut usb_ctrls = UsbControllers::default();
+    usb_ctrls.rescan(&mut connection).await?;
 
-    let laptop = LaptopLedData::get_data();
-    // CtrlKbdLed deviates from the config pattern above due to requiring a keyboard
-    // detection first
-    match CtrlKbdLed::new(laptop) {
-        Ok(ctrl) => {
-            let zbus = CtrlKbdLedZbus(Arc::new(Mutex::new(ctrl)));
-            let sig_ctx = CtrlKbdLedZbus::signal_context(&connection)?;
-            start_tasks(zbus, &mut connection, sig_ctx).await?;
-        }
-        Err(err) => {
-            error!("Keyboard control: {}", err);
-        }
+    for probe in diagnostics::recorded().iter().filter(|p| !p.found) {
+        info!(
+            "Diagnostics: {} {} not found: {}",
+            probe.name, probe.looked_for, probe.detail
+        );
     }
 
     // Request dbus name after finishing initalizing all functions
     connection.request_name(DBUS_NAME).await?;
+    if ppd_served {
+        connection
+            .request_name(PPD_NAME)
+            .await
+            .map_err(|e| warn!("{PPD_NAME} not available, is power-profiles-daemon running? {e}"))
+            .ok();
+    }
+
+    let (tx, mut rx) = unbounded_channel();
+    match watch_usb_devices(tx) {
+        Ok(_) => {
+            let mut connection = connection.clone();
+            tokio::spawn(async move {
+                while rx.recv().await.is_some() {
+                    // Let the device nodes settle, and merge the events of one plug
+                    sleep(HOTPLUG_SETTLE).await;
+                    while rx.try_recv().is_ok() {}
+                    match usb_ctrls.rescan(&mut connection).await {
+                        Ok(true) => {
+                            SupportedFunctions::refresh(&connection)
+                                .await
+                                .map_err(|e| warn!("SupportedFunctions: {e}"))
+                                .ok();
+                        }
+                        Ok(false) => {}
+                        Err(e) => error!("USB hotplug: {e}"),
+                    }
+                }
+            });
+        }
+        Err(err) => warn!("USB hotplug: {err}"),
+    }
 
     loop {
         // This is just a blocker to idle and ensure the reator reacts
@@ -145,11 +203,121 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Record hardware writes if set by `ASUSD_CAPTURE` or the config, the
+/// environment takes priority
+fn enable_capture(config: &Config) {
+    match capture::enable_from_env() {
+        Ok(true) => {}
+        Ok(false) if !config.capture_file.is_empty() => {
+            capture::enable(Path::new(&config.capture_file), config.capture_passthrough)
+                .unwrap_or_else(|err| error!("Capture: {}", err));
+        }
+        Ok(false) => {}
+        Err(err) => error!("Capture: {}", err),
+    }
+}
+
+/// The controllers of USB devices. Once created a controller is kept and its
+/// device is opened again if it returns. The tasks of a controller are stopped
+/// while its device is gone.
+#[derive(Default)]
+struct UsbControllers {
+    anime: Option<CtrlAnimeZbus>,
+    anime_tasks: Option<CtrlTasks>,
+    aura: Option<CtrlKbdLedZbus>,
+    aura_tasks: Option<CtrlTasks>,
+}
+
+impl UsbControllers {
+    /// Add or remove the controllers and their dbus objects to match the
+    /// devices present. Returns true if anything changed.
+    async fn rescan(&mut self, connection: &mut Connection) -> Result<bool, RogError> {
+        let mut changed = false;
+
+        let present = CtrlAnime::get_supported().0;
+        if !present {
+            diagnostics::record(diagnostics::anime(None));
+        }
+        if present && self.anime_tasks.is_none() {
+            match CtrlAnime::new(AnimeConfig::new().load()) {
+                Ok(ctrl) => {
+                    let zbus = if let Some(zbus) = &self.anime {
+                        info!("AniMe device returned");
+                        *zbus.0.lock().await = ctrl;
+                        zbus.clone()
+                    } else {
+                        CtrlAnimeZbus(Arc::new(Mutex::new(ctrl)))
+                    };
+                    let sig_ctx = CtrlAnimeZbus::signal_context(connection)?;
+                    self.anime_tasks = Some(start_tasks(zbus.clone(), connection, sig_ctx).await?);
+                    self.anime = Some(zbus);
+                    changed = true;
+                }
+                Err(err) => {
+                    info!("AniMe control: {}", err);
+                }
+            }
+        } else if !present && self.anime_tasks.is_some() {
+            info!("AniMe device removed");
+            if let Some(tasks) = self.anime_tasks.take() {
+                tasks.abort_all();
+            }
+            connection
+                .object_server()
+                .remove::<CtrlAnimeZbus, _>(CtrlAnimeZbus::zbus_path())
+                .await?;
+            changed = true;
+        }
+
+        let present = CtrlKbdLed::get_supported().dev_id != AuraDevice::Unknown;
+        if !present {
+            diagnostics::record_no_keyboard();
+        }
+        if present && self.aura_tasks.is_none() {
+            let laptop = LaptopLedData::get_data();
+            // CtrlKbdLed deviates from the config pattern above due to requiring a
+            // keyboard detection first
+            match CtrlKbdLed::new(laptop) {
+                Ok(ctrl) => {
+                    let zbus = if let Some(zbus) = &self.aura {
+                        info!("Keyboard device returned");
+                        *zbus.0.lock().await = ctrl;
+                        zbus.clone()
+                    } else {
+                        CtrlKbdLedZbus(Arc::new(Mutex::new(ctrl)))
+                    };
+                    let sig_ctx = CtrlKbdLedZbus::signal_context(connection)?;
+                    self.aura_tasks = Some(start_tasks(zbus.clone(), connection, sig_ctx).await?);
+                    self.aura = Some(zbus);
+                    changed = true;
+                }
+                Err(err) => {
+                    error!("Keyboard control: {}", err);
+                }
+            }
+        } else if !present && self.aura_tasks.is_some() {
+            info!("Keyboard device removed");
+            if let Some(tasks) = self.aura_tasks.take() {
+                tasks.abort_all();
+            }
+            connection
+                .object_server()
+                .remove::<CtrlKbdLedZbus, _>(CtrlKbdLedZbus::zbus_path())
+                .await?;
+            changed = true;
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Reload the controller, put it on dbus and start its tasks. The returned
+/// `CtrlTasks` can stop the tasks again.
 async fn start_tasks<T>(
     mut zbus: T,
     connection: &mut Connection,
     signal_ctx: SignalContext<'static>,
-) -> Result<(), Box<dyn Error>>
+) -> Result<CtrlTasks, RogError>
 where
     T: ZbusRun + Reloadable + CtrlTask + Clone,
 {
@@ -160,6 +328,7 @@ where
         .unwrap_or_else(|err| warn!("Controller error: {}", err));
     zbus.add_to_server(connection).await;
 
-    task.create_tasks(signal_ctx).await.ok();
-    Ok(())
+    let tasks = CtrlTasks::default();
+    tasks.scope(task.create_tasks(signal_ctx)).await.ok();
+    Ok(tasks)
 }
@@ -0,0 +1,353 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rog_anime::AnimeType;
+use rog_aura::aura_detection::{
+    LedSupportDb, ASUS_KEYBOARD_DEVICES, ASUS_LED_MODE_CONF, ASUS_LED_MODE_USER_CONF,
+};
+use rog_aura::usb::AuraDevice;
+use rog_platform::error::PlatformError;
+use rog_platform::hid_raw::HidRaw;
+use rog_platform::keyboard_led::KeyboardLed;
+use rog_platform::power::AsusPower;
+use rog_platform::quirks::{get_anime_type, QuirksFile, QUIRKS_CONF, QUIRKS_USER_CONF};
+use rog_platform::supported::Probe;
+use rog_profiles::{find_fan_curve_node, Profile, PLATFORM_PROFILES};
+
+const KERNEL_RELEASE: &str = "/proc/sys/kernel/osrelease";
+const ASUS_NB_WMI: &str = "/sys/devices/platform/asus-nb-wmi";
+const FIRMWARE_ATTRIBUTES: &str = "/sys/class/firmware-attributes";
+/// Kernel modules used by asusd, with what they provide
+const MODULES: [(&str, &str); 3] = [
+    ("asus_wmi", "platform functions, LEDs and fan curves"),
+    ("asus_nb_wmi", "the asus-nb-wmi platform device"),
+    ("hid_asus", "keyboard hotkeys and AniMe on some models"),
+];
+/// The order probes are listed in, by name
+const ORDER: [&str; 12] = [
+    "Kernel",
+    "Kernel module",
+    "Platform device",
+    "Platform profile",
+    "Fan curves",
+    "Battery charge limit",
+    "Keyboard backlight",
+    "Aura keyboard",
+    "Aura modes",
+    "Laptop quirks",
+    "AniMe Matrix",
+    "Firmware attributes",
+];
+
+static RECORDED: Mutex<Vec<Probe>> = Mutex::new(Vec::new());
+
+fn probe(name: &str, looked_for: &str, location: &str, found: bool, detail: String) -> Probe {
+    Probe {
+        name: name.to_owned(),
+        looked_for: looked_for.to_owned(),
+        location: location.to_owned(),
+        found,
+        detail,
+    }
+}
+
+/// Record the result of a check, made by the controller using what was
+/// checked. Replaces an earlier result of the same check.
+pub fn record(probe: Probe) {
+    if let Ok(mut recorded) = RECORDED.lock() {
+        recorded.retain(|p| p.name != probe.name || p.looked_for != probe.looked_for);
+        recorded.push(probe);
+        recorded.sort_by_key(|p| ORDER.iter().position(|n| *n == p.name));
+    }
+}
+
+/// The checks recorded by asusd at startup, updated as USB devices come and go
+pub fn recorded() -> Vec<Probe> {
+    RECORDED.lock().map(|r| r.clone()).unwrap_or_default()
+}
+
+/// Record the checks that belong to no controller
+pub fn record_system() {
+    record(kernel());
+    for probe in modules() {
+        record(probe);
+    }
+    record(quirks());
+}
+
+/// Record the keyboard checks for when no keyboard controller could be made
+pub fn record_no_keyboard() {
+    record(keyboard_backlight(&KeyboardLed::new()));
+    record(aura_keyboard(None));
+    record(aura_modes());
+}
+
+fn dmi_names() -> (String, String) {
+    let dmi = sysfs_class::DmiId::default();
+    (
+        dmi.board_name().unwrap_or_default().trim().to_owned(),
+        dmi.product_family().unwrap_or_default().trim().to_owned(),
+    )
+}
+
+pub fn kernel() -> Probe {
+    let release = fs::read_to_string(KERNEL_RELEASE).unwrap_or_default();
+    probe(
+        "Kernel",
+        "kernel release",
+        KERNEL_RELEASE,
+        !release.is_empty(),
+        release.trim().to_owned(),
+    )
+}
+
+pub fn modules() -> Vec<Probe> {
+    MODULES
+        .into_iter()
+        .map(|(module, provides)| {
+            let path = format!("/sys/module/{module}");
+            let found = Path::new(&path).exists();
+            probe(
+                "Kernel module",
+                module,
+                &path,
+                found,
+                if found {
+                    format!("provides {provides}")
+                } else {
+                    format!("not loaded, it is needed for {provides}. Try `modprobe {module}`")
+                },
+            )
+        })
+        .collect()
+}
+
+pub fn platform_device(found: bool) -> Probe {
+    probe(
+        "Platform device",
+        "asus-nb-wmi",
+        ASUS_NB_WMI,
+        found,
+        if found {
+            String::new()
+        } else {
+            "dgpu_disable, egpu_enable, gpu_mux and panel_od are unavailable. Check that \
+             asus_nb_wmi is loaded"
+                .to_owned()
+        },
+    )
+}
+
+/// `choices` is `None` if `platform_profile` is missing
+pub fn platform_profile(choices: Option<&[Profile]>) -> Probe {
+    probe(
+        "Platform profile",
+        "platform_profile_choices",
+        PLATFORM_PROFILES,
+        choices.is_some(),
+        match choices {
+            Some(choices) => format!("{choices:?}"),
+            None => "profiles can't be changed, a kernel with asus-wmi platform_profile support \
+                     is required"
+                .to_owned(),
+        },
+    )
+}
+
+pub fn fan_curves(found: bool) -> Probe {
+    probe(
+        "Fan curves",
+        "hwmon named asus_custom_fan_curve",
+        "/sys/class/hwmon",
+        found,
+        if found {
+            String::new()
+        } else {
+            "custom fan curves are not supported by this laptop or kernel".to_owned()
+        },
+    )
+}
+
+pub fn charge_limit(found: bool) -> Probe {
+    probe(
+        "Battery charge limit",
+        "charge_control_end_threshold",
+        "/sys/class/power_supply/BAT*",
+        found,
+        if found {
+            String::new()
+        } else {
+            "the battery charge limit can't be set".to_owned()
+        },
+    )
+}
+
+pub fn keyboard_backlight(led: &Result<KeyboardLed, PlatformError>) -> Probe {
+    probe(
+        "Keyboard backlight",
+        "asus::kbd_backlight",
+        "/sys/class/leds",
+        led.is_ok(),
+        match led {
+            Ok(led) if led.has_kbd_rgb_mode() => "TUF RGB control with kbd_rgb_mode".to_owned(),
+            Ok(_) => String::new(),
+            Err(e) => format!("keyboard brightness can't be changed: {e}"),
+        },
+    )
+}
+
+/// `keyboard` is the USB keyboard found, if any
+pub fn aura_keyboard(keyboard: Option<AuraDevice>) -> Probe {
+    // TUF keyboards are found with asus::kbd_backlight
+    let ids: Vec<&str> = ASUS_KEYBOARD_DEVICES
+        .into_iter()
+        .filter(|prod| !matches!(prod, AuraDevice::Tuf))
+        .map(<&str>::from)
+        .collect();
+    probe(
+        "Aura keyboard",
+        "hidraw of an ASUS USB keyboard",
+        &format!("/dev/hidraw*, USB 0b05:{{{}}}", ids.join(",")),
+        keyboard.is_some(),
+        match keyboard {
+            Some(prod) => format!("found 0b05:{}", <&str>::from(prod)),
+            None => "no USB keyboard, only TUF keyboards through asus::kbd_backlight can be \
+                     controlled"
+                .to_owned(),
+        },
+    )
+}
+
+pub fn aura_modes() -> Probe {
+    let (board_name, product_family) = dmi_names();
+    let matched = LedSupportDb::load()
+        .map(|db| db.explain(&board_name, &product_family))
+        .filter(|m| m.is_match());
+    probe(
+        "Aura modes",
+        &format!("entry for board {board_name}"),
+        &format!("{ASUS_LED_MODE_CONF}, {ASUS_LED_MODE_USER_CONF}"),
+        matched.is_some(),
+        match matched {
+            Some(m) => {
+                let mut rules: Vec<String> = m.packaged.into_iter().collect();
+                rules.extend(m.user.into_iter().map(|r| format!("user {r}")));
+                format!("matched {}", rules.join(", then "))
+            }
+            None => format!(
+                "only brightness is controlled. Add an entry for {board_name} to \
+                 {ASUS_LED_MODE_USER_CONF} and report it upstream"
+            ),
+        },
+    )
+}
+
+pub fn quirks() -> Probe {
+    let (board_name, product_family) = dmi_names();
+    let matched: Vec<String> = QuirksFile::load()
+        .matching(&board_name, &product_family)
+        .iter()
+        .map(|q| format!("\"{}\" \"{}\"", q.board_name, q.product_family))
+        .collect();
+    probe(
+        "Laptop quirks",
+        &format!("entries for board {board_name}, family {product_family}"),
+        &format!("{QUIRKS_CONF}, {QUIRKS_USER_CONF}"),
+        !matched.is_empty(),
+        if matched.is_empty() {
+            "none, which is expected unless this model needs a workaround".to_owned()
+        } else {
+            format!("applied {}", matched.join(", "))
+        },
+    )
+}
+
+/// `found` is the bus the display was found on, `USB` or `hidraw`, and its
+/// layout
+pub fn anime(found: Option<(&str, AnimeType)>) -> Probe {
+    probe(
+        "AniMe Matrix",
+        "USB device 0b05:193b",
+        "/sys/bus/usb/devices, /dev/hidraw*",
+        found.is_some(),
+        match found {
+            Some((bus, anime_type)) => format!("found over {bus}, layout {anime_type:?}"),
+            None => "not present, which is expected unless the lid has an LED matrix".to_owned(),
+        },
+    )
+}
+
+pub fn firmware_attributes(found: bool) -> Probe {
+    probe(
+        "Firmware attributes",
+        "firmware-attributes device",
+        FIRMWARE_ATTRIBUTES,
+        found,
+        if found {
+            String::new()
+        } else {
+            "not provided by this kernel, which is not required".to_owned()
+        },
+    )
+}
+
+/// Make every check here, for when asusd is not running. Nothing is written
+/// to the hardware.
+pub fn run_probes() -> Vec<Probe> {
+    let mut probes = vec![kernel()];
+    probes.extend(modules());
+    probes.push(platform_device(Path::new(ASUS_NB_WMI).exists()));
+    let choices = Profile::get_profile_names().ok();
+    probes.push(platform_profile(choices.as_deref()));
+    probes.push(fan_curves(matches!(find_fan_curve_node(), Ok(Some(_)))));
+    probes.push(charge_limit(
+        AsusPower::new()
+            .map(|p| p.get_charge_control_end_threshold().is_ok())
+            .unwrap_or(false),
+    ));
+    probes.push(keyboard_backlight(&KeyboardLed::new()));
+    probes.push(aura_keyboard(
+        ASUS_KEYBOARD_DEVICES
+            .into_iter()
+            .filter(|prod| !matches!(prod, AuraDevice::Tuf))
+            .find(|prod| HidRaw::new((*prod).into()).is_ok()),
+    ));
+    probes.push(aura_modes());
+    probes.push(quirks());
+    let anime_usb = anime_usb_present();
+    let anime_bus = if anime_usb {
+        Some("USB")
+    } else if HidRaw::new("193b").is_ok() {
+        Some("hidraw")
+    } else {
+        None
+    };
+    probes.push(anime(
+        anime_bus.map(|bus| (bus, get_anime_type().unwrap_or(AnimeType::Unknown))),
+    ));
+    probes.push(firmware_attributes(
+        fs::read_dir(FIRMWARE_ATTRIBUTES)
+            .map(|mut d| d.next().is_some())
+            .unwrap_or(false),
+    ));
+    probes
+}
+
+/// Check for the AniMe USB device without opening it, as opening resets it
+fn anime_usb_present() -> bool {
+    let mut enumerator = match udev::Enumerator::new() {
+        Ok(enumerator) => enumerator,
+        Err(_) => return false,
+    };
+    if enumerator.match_subsystem("usb").is_err()
+        || enumerator.match_attribute("idVendor", "0b05").is_err()
+        || enumerator.match_attribute("idProduct", "193b").is_err()
+    {
+        return false;
+    }
+    enumerator
+        .scan_devices()
+        .map(|mut d| d.next().is_some())
+        .unwrap_or(false)
+}
@@ -0,0 +1,74 @@
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, info};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::error::RogError;
+
+const ASUS_VENDOR_ID: &str = "0b05";
+/// The udev socket is non-blocking, so is checked at this interval
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn is_asus_device(event: &udev::Event) -> bool {
+    if let Some(id) = event.property_value("ID_VENDOR_ID") {
+        return id == ASUS_VENDOR_ID;
+    }
+    // Set for all usb_device events, such as "b05/193b/100"
+    event
+        .property_value("PRODUCT")
+        .and_then(|p| p.to_str())
+        .and_then(|p| p.split('/').next())
+        .and_then(|v| u16::from_str_radix(v, 16).ok())
+        .map(|v| format!("{v:04x}") == ASUS_VENDOR_ID)
+        .unwrap_or(false)
+}
+
+/// Start a thread which sends on `tx` each time an ASUS USB device (such as
+/// the keyboard or AniMe) is added or removed. The receiver is expected to
+/// wait a moment for the device nodes to settle before rescanning.
+pub fn watch_usb_devices(tx: UnboundedSender<()>) -> Result<(), RogError> {
+    // The udev socket can't be sent between threads so is created in the
+    // thread, with the result passed back
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    thread::Builder::new()
+        .name("usb hotplug".into())
+        .spawn(move || {
+            let socket = match udev::MonitorBuilder::new()
+                .and_then(|m| m.match_subsystem_devtype("usb", "usb_device"))
+                .and_then(|m| m.listen())
+            {
+                Ok(socket) => {
+                    ready_tx.send(Ok(())).ok();
+                    socket
+                }
+                Err(e) => {
+                    ready_tx.send(Err(e)).ok();
+                    return;
+                }
+            };
+
+            loop {
+                for event in socket.iter() {
+                    let action = event.event_type();
+                    if !matches!(action, udev::EventType::Add | udev::EventType::Remove)
+                        || !is_asus_device(&event)
+                    {
+                        continue;
+                    }
+                    debug!("USB hotplug: {action} {:?}", event.syspath());
+                    if tx.send(()).is_err() {
+                        info!("USB hotplug: receiver dropped, stopping");
+                        return;
+                    }
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        })
+        .map_err(RogError::Io)?;
+
+    ready_rx
+        .recv()
+        .map_err(|_| RogError::DoTask("USB hotplug thread exited".into()))?
+        .map_err(|e| RogError::Udev("monitor usb devices".into(), e))
+}
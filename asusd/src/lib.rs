@@ -1,10 +1,14 @@
 #![deny(unused_must_use)]
+/// Turn LEDs and displays off on idle or lid close
+pub mod auto_off;
 /// Configuration loading, saving
 pub mod config;
 /// Control of anime matrix display
 pub mod ctrl_anime;
 /// Keyboard LED brightness control, RGB, and LED display modes
 pub mod ctrl_aura;
+/// Generic access to all `firmware-attributes` exposed by the kernel
+pub mod ctrl_firmware_attributes;
 /// Control ASUS bios function such as boot sound, Optimus/Dedicated gfx mode
 pub mod ctrl_platform;
 /// Control of battery charge level
@@ -14,19 +18,30 @@ pub mod ctrl_profiles;
 
 /// Fetch all supported functions for the laptop
 pub mod ctrl_supported;
+/// Checks of the kernel interfaces and devices used, for diagnosing problems
+pub mod diagnostics;
 
 pub mod error;
 
+/// Watch for USB devices being added or removed
+pub mod hotplug;
+
+/// Shared logind events for all controllers
+pub mod sys_events;
+
 use std::future::Future;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use log::{debug, info, warn};
-use logind_zbus::manager::ManagerProxy;
-use zbus::export::futures_util::StreamExt;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::{AbortHandle, JoinHandle};
 use zbus::zvariant::ObjectPath;
 use zbus::{Connection, SignalContext};
 
 use crate::error::RogError;
+use crate::sys_events::{SysEvent, SysEvents};
 
 const CONFIG_PATH_BASE: &str = "/etc/asusd/";
 
@@ -66,7 +81,7 @@ macro_rules! task_watch_item {
             concat_idents::concat_idents!(watch_fn = monitor_, $name {
                 match self.$self_inner.watch_fn() {
                     Ok(watch) => {
-                        tokio::spawn(async move {
+                        $crate::spawn_task(async move {
                             let mut buffer = [0; 32];
                             watch.into_event_stream(&mut buffer).unwrap().for_each(|_| async {
                                 let value = ctrl.$name();
@@ -87,6 +102,50 @@ macro_rules! task_watch_item {
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+tokio::task_local! {
+    static CTRL_TASKS: CtrlTasks;
+}
+
+/// The tasks spawned with `spawn_task` while running in `CtrlTasks::scope`,
+/// so that the tasks of a controller can be stopped with its device
+#[derive(Debug, Default, Clone)]
+pub struct CtrlTasks(Arc<Mutex<Vec<AbortHandle>>>);
+
+impl CtrlTasks {
+    /// Run `future`, tracking every task it spawns with `spawn_task`, and the
+    /// tasks those spawn in turn
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CTRL_TASKS.scope(self.clone(), future).await
+    }
+
+    pub fn abort_all(&self) {
+        if let Ok(mut tasks) = self.0.lock() {
+            for task in tasks.drain(..) {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// As `tokio::spawn`, the task is added to the current `CtrlTasks` if any
+pub fn spawn_task<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match CTRL_TASKS.try_with(|tasks| tasks.clone()) {
+        Ok(tasks) => {
+            let handle = tokio::spawn(CTRL_TASKS.scope(tasks.clone(), future));
+            if let Ok(mut list) = tasks.0.lock() {
+                list.retain(|t| !t.is_finished());
+                list.push(handle.abort_handle());
+            }
+            handle
+        }
+        Err(_) => tokio::spawn(future),
+    }
+}
+
 pub fn print_board_info() {
     let dmi = sysfs_class::DmiId::default();
     let board_name = dmi.board_name().expect("Could not get board_name");
@@ -147,6 +206,12 @@ pub trait CtrlTask {
     //     });
     // }
 
+    /// Subscribe to the logind events shared by all controllers: sleep, wake,
+    /// shutdown, boot, lid, lock, idle and AC changes
+    fn sys_events(&self) -> broadcast::Receiver<SysEvent> {
+        SysEvents::get().subscribe()
+    }
+
     /// Free helper method to create tasks to run on: sleep, wake, shutdown,
     /// boot
     ///
@@ -177,52 +242,104 @@ pub trait CtrlTask {
         Fut3: Future<Output = ()> + Send,
         Fut4: Future<Output = ()> + Send,
     {
-        let connection = Connection::system()
-            .await
-            .expect("Controller could not create dbus connection");
-
-        let manager = ManagerProxy::new(&connection)
-            .await
-            .expect("Controller could not create ManagerProxy");
-
-        tokio::spawn(async move {
-            if let Ok(mut notif) = manager.receive_prepare_for_sleep().await {
-                while let Some(event) = notif.next().await {
-                    if let Ok(args) = event.args() {
-                        if args.start {
-                            debug!("Doing on_sleep()");
-                            on_sleep().await;
-                        } else if !args.start() {
-                            debug!("Doing on_wake()");
-                            on_wake().await;
+        let mut events = self.sys_events();
+        spawn_task(async move {
+            // The state as of the last event handled, to catch up from if
+            // events are missed
+            let mut seen = SysEvents::get().state();
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(n)) => {
+                        warn!("Missed {n} system events, re-syncing");
+                        let state = SysEvents::get().state();
+                        let mut missed = Vec::new();
+                        if state.sleeping != seen.sleeping {
+                            missed.push(if state.sleeping {
+                                SysEvent::Sleep
+                            } else {
+                                SysEvent::Wake
+                            });
                         }
-                    }
-                }
-            }
-        });
-
-        let manager = ManagerProxy::new(&connection)
-            .await
-            .expect("Controller could not create ManagerProxy");
-
-        tokio::spawn(async move {
-            if let Ok(mut notif) = manager.receive_prepare_for_shutdown().await {
-                while let Some(event) = notif.next().await {
-                    if let Ok(args) = event.args() {
-                        if args.start {
-                            debug!("Doing on_shutdown()");
-                            on_shutdown().await;
-                        } else if !args.start() {
-                            debug!("Doing on_boot()");
-                            on_boot().await;
+                        if state.shutting_down != seen.shutting_down {
+                            missed.push(if state.shutting_down {
+                                SysEvent::Shutdown
+                            } else {
+                                SysEvent::Boot
+                            });
                         }
+                        seen = state;
+                        for event in missed {
+                            run_sys_event(
+                                event,
+                                &mut on_sleep,
+                                &mut on_wake,
+                                &mut on_shutdown,
+                                &mut on_boot,
+                            )
+                            .await;
+                        }
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                match event {
+                    SysEvent::Sleep | SysEvent::Wake => seen.sleeping = event == SysEvent::Sleep,
+                    SysEvent::Shutdown | SysEvent::Boot => {
+                        seen.shutting_down = event == SysEvent::Shutdown;
                     }
+                    _ => continue,
                 }
+                run_sys_event(
+                    event,
+                    &mut on_sleep,
+                    &mut on_wake,
+                    &mut on_shutdown,
+                    &mut on_boot,
+                )
+                .await;
             }
         });
     }
 }
 
+async fn run_sys_event<F1, F2, F3, F4, Fut1, Fut2, Fut3, Fut4>(
+    event: SysEvent,
+    on_sleep: &mut F1,
+    on_wake: &mut F2,
+    on_shutdown: &mut F3,
+    on_boot: &mut F4,
+) where
+    F1: FnMut() -> Fut1,
+    F2: FnMut() -> Fut2,
+    F3: FnMut() -> Fut3,
+    F4: FnMut() -> Fut4,
+    Fut1: Future<Output = ()>,
+    Fut2: Future<Output = ()>,
+    Fut3: Future<Output = ()>,
+    Fut4: Future<Output = ()>,
+{
+    match event {
+        SysEvent::Sleep => {
+            debug!("Doing on_sleep()");
+            on_sleep().await;
+        }
+        SysEvent::Wake => {
+            debug!("Doing on_wake()");
+            on_wake().await;
+        }
+        SysEvent::Shutdown => {
+            debug!("Doing on_shutdown()");
+            on_shutdown().await;
+        }
+        SysEvent::Boot => {
+            debug!("Doing on_boot()");
+            on_boot().await;
+        }
+        _ => {}
+    }
+}
+
 pub trait GetSupported {
     type A;
 
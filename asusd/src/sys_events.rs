@@ -0,0 +1,297 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use log::{debug, warn};
+use logind_zbus::manager::ManagerProxy;
+use logind_zbus::session::SessionProxy;
+use tokio::sync::{broadcast, watch};
+use tokio::time::sleep;
+use zbus::export::futures_util::StreamExt;
+use zbus::{CacheProperties, Connection, MatchRule, MessageStream, MessageType};
+
+use crate::error::RogError;
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+/// How often the logind properties are checked while a [`PollGuard`] is held.
+/// logind does not emit changes for `LidClosed` or `OnExternalPower`, the rest
+/// are re-read on any logind signal.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const CHANNEL_SIZE: usize = 32;
+
+/// Events from logind that controllers may react to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SysEvent {
+    /// `PrepareForSleep` with start true
+    Sleep,
+    /// `PrepareForSleep` with start false
+    Wake,
+    /// `PrepareForShutdown` with start true
+    Shutdown,
+    /// `PrepareForShutdown` with start false
+    Boot,
+    LidClosed,
+    LidOpened,
+    /// An active session set its `LockedHint`
+    Locked,
+    Unlocked,
+    /// The `IdleHint` of logind was set, all sessions are idle
+    IdleStarted,
+    IdleEnded,
+    AcConnected,
+    AcDisconnected,
+}
+
+/// The last known logind state
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SeatState {
+    pub lid_closed: bool,
+    /// The `IdleHint` of logind, which is set once all sessions are idle
+    pub idle: bool,
+    /// True if any active session is locked
+    pub locked: bool,
+    pub on_ac: bool,
+    /// Between `Sleep` and `Wake`
+    pub sleeping: bool,
+    /// Between `Shutdown` and `Boot`
+    pub shutting_down: bool,
+}
+
+impl SeatState {
+    /// The events required to go from `self` to `new`
+    fn changes(&self, new: &SeatState) -> Vec<SysEvent> {
+        let mut events = Vec::new();
+        let mut check = |old: bool, new: bool, on: SysEvent, off: SysEvent| {
+            if old != new {
+                events.push(if new { on } else { off });
+            }
+        };
+        check(
+            self.lid_closed,
+            new.lid_closed,
+            SysEvent::LidClosed,
+            SysEvent::LidOpened,
+        );
+        check(
+            self.locked,
+            new.locked,
+            SysEvent::Locked,
+            SysEvent::Unlocked,
+        );
+        check(
+            self.idle,
+            new.idle,
+            SysEvent::IdleStarted,
+            SysEvent::IdleEnded,
+        );
+        check(
+            self.on_ac,
+            new.on_ac,
+            SysEvent::AcConnected,
+            SysEvent::AcDisconnected,
+        );
+        events
+    }
+}
+
+/// A single logind connection shared by all controllers. Use
+/// `SysEvents::get()` to subscribe, the tasks are started on first use and so
+/// must be within the tokio runtime.
+pub struct SysEvents {
+    events: broadcast::Sender<SysEvent>,
+    state: watch::Receiver<SeatState>,
+    /// The number of [`PollGuard`] held
+    pollers: watch::Sender<usize>,
+}
+
+/// While held the logind state is polled, for consumers that need the lid or
+/// AC state to be current. See [`SysEvents::poll()`].
+pub struct PollGuard(());
+
+impl Drop for PollGuard {
+    fn drop(&mut self) {
+        SysEvents::get().pollers.send_modify(|n| *n -= 1);
+    }
+}
+
+static SYS_EVENTS: OnceLock<SysEvents> = OnceLock::new();
+
+impl SysEvents {
+    pub fn get() -> &'static SysEvents {
+        SYS_EVENTS.get_or_init(|| {
+            let (events, _) = broadcast::channel(CHANNEL_SIZE);
+            let (state_tx, state) = watch::channel(SeatState::default());
+            let (pollers, pollers_rx) = watch::channel(0);
+            let tx = events.clone();
+            tokio::spawn(async move {
+                if let Err(e) = run(tx, state_tx, pollers_rx).await {
+                    warn!("SysEvents: logind events unavailable: {e}");
+                }
+            });
+            SysEvents {
+                events,
+                state,
+                pollers,
+            }
+        })
+    }
+
+    /// Poll the logind state until the guard is dropped
+    pub fn poll(&self) -> PollGuard {
+        self.pollers.send_modify(|n| *n += 1);
+        PollGuard(())
+    }
+
+    /// A new receiver of all events sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SysEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn state(&self) -> SeatState {
+        *self.state.borrow()
+    }
+}
+
+async fn read_state(
+    connection: &Connection,
+    manager: &ManagerProxy<'_>,
+) -> Result<SeatState, RogError> {
+    let mut locked = false;
+    for (_, _, _, _, path) in manager.list_sessions().await? {
+        let session = SessionProxy::builder(connection)
+            .path(path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        if session.active().await.unwrap_or(false) && session.locked_hint().await.unwrap_or(false) {
+            locked = true;
+            break;
+        }
+    }
+    Ok(SeatState {
+        lid_closed: manager.lid_closed().await?,
+        idle: manager.idle_hint().await?,
+        locked,
+        on_ac: manager.on_external_power().await?,
+        ..Default::default()
+    })
+}
+
+/// Update the polled part of `state`, the sleep and shutdown state is only
+/// set from the signals
+fn update_state(state: &watch::Sender<SeatState>, new: &mut SeatState) {
+    state.send_modify(|s| {
+        new.sleeping = s.sleeping;
+        new.shutting_down = s.shutting_down;
+        *s = *new;
+    });
+}
+
+async fn run(
+    events: broadcast::Sender<SysEvent>,
+    state: watch::Sender<SeatState>,
+    mut pollers: watch::Receiver<usize>,
+) -> Result<(), RogError> {
+    let connection = Connection::system().await?;
+    let manager = ManagerProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let state = Arc::new(state);
+
+    let tx = events.clone();
+    let sleep_state = state.clone();
+    let mut notif = manager.receive_prepare_for_sleep().await?;
+    tokio::spawn(async move {
+        while let Some(event) = notif.next().await {
+            if let Ok(args) = event.args() {
+                let event = if args.start {
+                    SysEvent::Sleep
+                } else {
+                    SysEvent::Wake
+                };
+                debug!("SysEvents: {event:?}");
+                sleep_state.send_modify(|s| s.sleeping = args.start);
+                tx.send(event).ok();
+            }
+        }
+    });
+
+    let tx = events.clone();
+    let shutdown_state = state.clone();
+    let mut notif = manager.receive_prepare_for_shutdown().await?;
+    tokio::spawn(async move {
+        while let Some(event) = notif.next().await {
+            if let Ok(args) = event.args() {
+                let event = if args.start {
+                    SysEvent::Shutdown
+                } else {
+                    SysEvent::Boot
+                };
+                debug!("SysEvents: {event:?}");
+                shutdown_state.send_modify(|s| s.shutting_down = args.start);
+                tx.send(event).ok();
+            }
+        }
+    });
+
+    // Property changes of the manager and sessions, and sessions coming and
+    // going, are all signals from logind
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(LOGIND_NAME)?
+        .path_namespace(LOGIND_PATH)?
+        .build();
+    let mut signals = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    // The first read is the initial state and sends no events
+    let mut last = read_state(&connection, &manager).await?;
+    update_state(&state, &mut last);
+    loop {
+        let polling = *pollers.borrow_and_update() > 0;
+        tokio::select! {
+            Some(_) = signals.next() => {}
+            _ = sleep(POLL_INTERVAL), if polling => {}
+            Ok(()) = pollers.changed() => continue,
+            else => break,
+        }
+        let mut new = match read_state(&connection, &manager).await {
+            Ok(new) => new,
+            Err(e) => {
+                warn!("SysEvents: {e}");
+                continue;
+            }
+        };
+        update_state(&state, &mut new);
+        for event in last.changes(&new) {
+            debug!("SysEvents: {event:?}");
+            events.send(event).ok();
+        }
+        last = new;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SeatState, SysEvent};
+
+    #[test]
+    fn seat_state_changes() {
+        let old = SeatState {
+            lid_closed: true,
+            on_ac: true,
+            ..Default::default()
+        };
+        let new = SeatState {
+            idle: true,
+            on_ac: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            old.changes(&new),
+            [SysEvent::LidOpened, SysEvent::IdleStarted]
+        );
+        assert!(new.changes(&new).is_empty());
+    }
+}
@@ -103,6 +103,7 @@ desktop-file-validate %{buildroot}/%{_datadir}/applications/rog-control-center.d
 %{_udevrulesdir}/99-asusd.rules
 #%dir %{_sysconfdir}/asusd/
 %{_datadir}/asusd/aura_support.ron
+%{_datadir}/asusd/laptop_quirks.ron
 %{_datadir}/dbus-1/system.d/asusd.conf
 %{_datadir}/icons/hicolor/512x512/apps/asus_notif_yellow.png
 %{_datadir}/icons/hicolor/512x512/apps/asus_notif_green.png
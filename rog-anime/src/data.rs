@@ -179,6 +179,15 @@ impl TryFrom<AnimeDataBuffer> for AnimePacketType {
     }
 }
 
+/// The reverse of `AnimePacketType::try_from`, get the pane index and the
+/// pane data of one USB packet. Returns `None` if the packet is not pane data.
+pub fn pane_from_packet(packet: &[u8]) -> Option<(usize, &[u8])> {
+    let index = [USB_PREFIX1, USB_PREFIX2, USB_PREFIX3]
+        .iter()
+        .position(|prefix| packet.starts_with(prefix))?;
+    Some((index, packet.get(BLOCK_START..BLOCK_END)?))
+}
+
 /// This runs the animations as a blocking loop by using the `callback` to write
 /// data
 ///
@@ -166,6 +166,145 @@ pub enum LedCode {
 }
 
 impl LedCode {
+    /// Every `LedCode`, in order of declaration
+    pub const ALL: [LedCode; 135] = [
+        Self::VolUp,
+        Self::VolDown,
+        Self::MicMute,
+        Self::RogApp,
+        Self::RogFan,
+        Self::Esc,
+        Self::F1,
+        Self::F2,
+        Self::F3,
+        Self::F4,
+        Self::F5,
+        Self::F6,
+        Self::F7,
+        Self::F8,
+        Self::F9,
+        Self::F10,
+        Self::F11,
+        Self::F12,
+        Self::Del,
+        Self::Tilde,
+        Self::N1,
+        Self::N2,
+        Self::N3,
+        Self::N4,
+        Self::N5,
+        Self::N6,
+        Self::N7,
+        Self::N8,
+        Self::N9,
+        Self::N0,
+        Self::Hyphen,
+        Self::Equals,
+        Self::Backspace,
+        Self::Backspace3_1,
+        Self::Backspace3_2,
+        Self::Backspace3_3,
+        Self::Home,
+        Self::Tab,
+        Self::Q,
+        Self::W,
+        Self::E,
+        Self::R,
+        Self::T,
+        Self::Y,
+        Self::U,
+        Self::I,
+        Self::O,
+        Self::P,
+        Self::LBracket,
+        Self::RBracket,
+        Self::BackSlash,
+        Self::PgUp,
+        Self::Caps,
+        Self::A,
+        Self::S,
+        Self::D,
+        Self::F,
+        Self::G,
+        Self::H,
+        Self::J,
+        Self::K,
+        Self::L,
+        Self::SemiColon,
+        Self::Quote,
+        Self::Return,
+        Self::Return3_1,
+        Self::Return3_2,
+        Self::Return3_3,
+        Self::PgDn,
+        Self::LShift,
+        Self::LShift3_1,
+        Self::LShift3_2,
+        Self::LShift3_3,
+        Self::Z,
+        Self::X,
+        Self::C,
+        Self::V,
+        Self::B,
+        Self::N,
+        Self::M,
+        Self::Comma,
+        Self::Period,
+        Self::FwdSlash,
+        Self::Star,
+        Self::NumPadDel,
+        Self::NumPadPlus,
+        Self::NumPadEnter,
+        Self::NumPadPause,
+        Self::NumPadPrtSc,
+        Self::NumPadHome,
+        Self::NumLock,
+        Self::Rshift,
+        Self::Rshift3_1,
+        Self::Rshift3_2,
+        Self::Rshift3_3,
+        Self::End,
+        Self::LCtrl,
+        Self::LFn,
+        Self::Meta,
+        Self::LAlt,
+        Self::Spacebar,
+        Self::Spacebar5_1,
+        Self::Spacebar5_2,
+        Self::Spacebar5_3,
+        Self::Spacebar5_4,
+        Self::Spacebar5_5,
+        Self::Pause,
+        Self::RAlt,
+        Self::PrtSc,
+        Self::RCtrl,
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::RFn,
+        Self::MediaPlay,
+        Self::MediaStop,
+        Self::MediaNext,
+        Self::MediaPrev,
+        Self::LidLogo,
+        Self::LidLeft,
+        Self::LidRight,
+        Self::LightbarRight,
+        Self::LightbarRightCorner,
+        Self::LightbarRightBottom,
+        Self::LightbarLeftBottom,
+        Self::LightbarLeftCorner,
+        Self::LightbarLeft,
+        Self::SingleZone,
+        Self::ZonedKbLeft,
+        Self::ZonedKbLeftMid,
+        Self::ZonedKbRightMid,
+        Self::ZonedKbRight,
+        Self::Spacing,
+        Self::Blocking,
+    ];
+
     pub fn is_placeholder(&self) -> bool {
         matches!(self, Self::Spacing | Self::Blocking)
     }
@@ -304,7 +443,20 @@ impl LedUsbPackets {
     /// Indexing is different for `zoned` and assumes that only one packet is
     /// generated for all the zones
     fn rgb_for_led_code(&mut self, led_code: LedCode) -> Option<&mut [u8]> {
-        let zoned = self.zoned;
+        let (row, col) = Self::packet_index(led_code, self.zoned)?;
+        if self.zoned && row > 0 {
+            warn!(
+                "LedCode {led_code:?} for zoned is not correct or out of Zone range. Setting to 0",
+            );
+            return None;
+        }
+
+        Some(&mut self.usb_packets[row][col..=col + 2])
+    }
+
+    /// The row and column of the first of the three R,G,B bytes for an
+    /// `LedCode`
+    fn packet_index(led_code: LedCode, zoned: bool) -> Option<(usize, usize)> {
         // Tuples are indexes in to array
         #[allow(clippy::match_same_arms)]
         let (row, col) = match led_code {
@@ -457,15 +609,24 @@ impl LedUsbPackets {
             | LedCode::Spacebar
             | LedCode::Backspace => return None,
         };
+        Some((row, col))
+    }
 
-        if self.zoned && row > 0 {
-            warn!(
-                "LedCode {led_code:?} for zoned is not correct or out of Zone range. Setting to 0",
-            );
-            return None;
-        }
+    /// If `led_code` has a colour in per-key or `zoned` packets
+    pub fn is_addressable(led_code: LedCode, zoned: bool) -> bool {
+        Self::packet_index(led_code, zoned).is_some()
+    }
 
-        Some(&mut self.usb_packets[row][col..=col + 2])
+    /// Find the `LedCode` whose colour starts at `col` in packet `row`, the
+    /// reverse of `set()`. Where several codes share an index the first
+    /// declared is returned.
+    pub fn led_code_at(zoned: bool, row: usize, col: usize) -> Option<LedCode> {
+        LedCode::ALL.into_iter().find(|code| {
+            let zone = code.is_keyboard_zone() || *code == LedCode::SingleZone;
+            // Lightbar codes are used by both
+            (zoned == zone || code.is_lightbar_zone())
+                && Self::packet_index(*code, zoned) == Some((row, col))
+        })
     }
 
     #[inline]
@@ -577,4 +738,27 @@ mod tests {
         assert_eq!(pkt[7][14], 0xff); // M
         assert_eq!(pkt[7][15], 0x00); // M
     }
+
+    #[test]
+    fn led_code_at_check() {
+        assert_eq!(LedUsbPackets::led_code_at(false, 5, 30), Some(LedCode::D));
+        assert_eq!(LedUsbPackets::led_code_at(false, 5, 31), None);
+        assert_eq!(LedUsbPackets::led_code_at(false, 0, 9), None);
+        assert_eq!(
+            LedUsbPackets::led_code_at(false, 0, 15),
+            Some(LedCode::VolDown)
+        );
+        assert_eq!(
+            LedUsbPackets::led_code_at(false, 11, 15),
+            Some(LedCode::LightbarRight)
+        );
+        assert_eq!(
+            LedUsbPackets::led_code_at(true, 0, 27),
+            Some(LedCode::LightbarRight)
+        );
+        assert_eq!(
+            LedUsbPackets::led_code_at(true, 0, 9),
+            Some(LedCode::SingleZone)
+        );
+    }
 }
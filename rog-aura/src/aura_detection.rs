@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
 use log::{error, info, warn};
+use regex::Regex;
 use serde_derive::{Deserialize, Serialize};
 use typeshare::typeshare;
 use zbus::zvariant::Type;
@@ -39,6 +43,128 @@ pub enum PowerZones {
     RearGlow,
 }
 
+/// How an entry's `board_name` is compared to the laptop board name. When
+/// several entries match the later kinds here are preferred.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Copy, Clone)]
+pub enum MatchKind {
+    /// The board name contains `board_name`
+    #[default]
+    Contains,
+    /// The board name starts with `board_name`
+    Prefix,
+    /// `board_name` is a regular expression matching the board name
+    Regex,
+    /// The board name is exactly `board_name`
+    Exact,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// The fields of an entry which decide if it applies to a laptop
+struct MatchRule<'a> {
+    board_name: &'a str,
+    kind: MatchKind,
+    product_family: &'a str,
+    priority: i32,
+}
+
+impl MatchRule<'_> {
+    fn matches(&self, board_name: &str, product_family: &str, regexes: &Regexes) -> bool {
+        if !product_family.contains(self.product_family) {
+            return false;
+        }
+        match self.kind {
+            MatchKind::Contains => board_name.contains(self.board_name),
+            MatchKind::Prefix => board_name.starts_with(self.board_name),
+            MatchKind::Exact => board_name == self.board_name,
+            MatchKind::Regex => regexes
+                .0
+                .get(self.board_name)
+                .is_some_and(|regex| regex.is_match(board_name)),
+        }
+    }
+
+    /// Higher is preferred: the priority, then the kind of match, then if the
+    /// product family is also checked, then the longer name
+    fn score(&self) -> (i32, MatchKind, bool, usize) {
+        (
+            self.priority,
+            self.kind,
+            !self.product_family.is_empty(),
+            self.board_name.len(),
+        )
+    }
+}
+
+impl Display for MatchRule<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} \"{}\"", self.kind, self.board_name)?;
+        if !self.product_family.is_empty() {
+            write!(f, ", product_family \"{}\"", self.product_family)?;
+        }
+        if self.priority != 0 {
+            write!(f, ", priority {}", self.priority)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `Regex` entries compiled once when the files are loaded, by pattern.
+/// Invalid patterns are left out so never match.
+#[derive(Debug, Default, Clone)]
+struct Regexes(HashMap<String, Regex>);
+
+impl Regexes {
+    fn compile<'a>(rules: impl Iterator<Item = MatchRule<'a>>) -> Self {
+        let mut regexes = HashMap::new();
+        for rule in rules.filter(|r| r.kind == MatchKind::Regex) {
+            if regexes.contains_key(rule.board_name) {
+                continue;
+            }
+            match Regex::new(rule.board_name) {
+                Ok(regex) => {
+                    regexes.insert(rule.board_name.to_owned(), regex);
+                }
+                Err(e) => warn!("LedSupport: invalid regex {}: {e}", rule.board_name),
+            }
+        }
+        Self(regexes)
+    }
+}
+
+impl PartialEq for Regexes {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.keys().all(|k| other.0.contains_key(k))
+    }
+}
+
+impl Eq for Regexes {}
+
+/// (De)serialise an `Option` field of the user file as the bare value, a
+/// missing field being `None`. Unlike ron's `IMPLICIT_SOME` this keeps
+/// `advanced_type: None` as `Some(AdvancedAuraType::None)`.
+mod bare {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        T::deserialize(deserializer).map(Some)
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct LaptopLedData {
     /// Found via `cat /sys/class/dmi/id/board_name`, e.g `GU603ZW`.
@@ -49,6 +175,15 @@ pub struct LaptopLedData {
     /// - Z = variant/year or perhaps dGPU model (such as RTX 3xxx)
     /// - W = possibly dGPU model (such as RTX 3060Ti)
     pub board_name: String,
+    /// How `board_name` is matched, as part of the board name by default
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub match_kind: MatchKind,
+    /// If set, `/sys/class/dmi/id/product_family` must also contain this
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub product_family: String,
+    /// Used first to choose between several matching entries
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub priority: i32,
     pub layout_name: String,
     pub basic_modes: Vec<AuraModeNum>,
     pub basic_zones: Vec<AuraZone>,
@@ -60,81 +195,331 @@ impl LaptopLedData {
     pub fn get_data() -> Self {
         let dmi = sysfs_class::DmiId::default();
         let board_name = dmi.board_name().expect("Could not get board_name");
-        // let prod_family = dmi.product_family().expect("Could not get
-        // product_family");
+        let prod_family = dmi.product_family().unwrap_or_default();
 
-        if let Some(modes) = LedSupportFile::load_from_supoprt_db() {
-            if let Some(data) = modes.matcher(&board_name) {
+        if let Some(db) = LedSupportDb::load() {
+            if let Some(data) = db.matcher(board_name.trim(), prod_family.trim()) {
                 return data;
             }
         }
         info!("Using generic LED control for keyboard brightness only");
         LaptopLedData::default()
     }
+
+    fn rule(&self) -> MatchRule<'_> {
+        MatchRule {
+            board_name: &self.board_name,
+            kind: self.match_kind,
+            product_family: &self.product_family,
+            priority: self.priority,
+        }
+    }
+}
+
+/// An entry of `/etc/asusd/asusd_user_ledmodes.ron`. The fields which are
+/// set replace those of the matching packaged entry, others are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LaptopLedOverride {
+    pub board_name: String,
+    #[serde(skip_serializing_if = "is_default")]
+    pub match_kind: MatchKind,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub product_family: String,
+    #[serde(skip_serializing_if = "is_default")]
+    pub priority: i32,
+    #[serde(with = "bare", skip_serializing_if = "Option::is_none")]
+    pub layout_name: Option<String>,
+    #[serde(with = "bare", skip_serializing_if = "Option::is_none")]
+    pub basic_modes: Option<Vec<AuraModeNum>>,
+    #[serde(with = "bare", skip_serializing_if = "Option::is_none")]
+    pub basic_zones: Option<Vec<AuraZone>>,
+    #[serde(with = "bare", skip_serializing_if = "Option::is_none")]
+    pub advanced_type: Option<AdvancedAuraType>,
+    #[serde(with = "bare", skip_serializing_if = "Option::is_none")]
+    pub power_zones: Option<Vec<PowerZones>>,
+}
+
+impl LaptopLedOverride {
+    fn rule(&self) -> MatchRule<'_> {
+        MatchRule {
+            board_name: &self.board_name,
+            kind: self.match_kind,
+            product_family: &self.product_family,
+            priority: self.priority,
+        }
+    }
+
+    /// Set the fields of `data` which this overrides, returning their names
+    fn apply(&self, data: &mut LaptopLedData) -> Vec<&'static str> {
+        let mut set = Vec::new();
+        if let Some(layout_name) = &self.layout_name {
+            data.layout_name = layout_name.clone();
+            set.push("layout_name");
+        }
+        if let Some(basic_modes) = &self.basic_modes {
+            data.basic_modes = basic_modes.clone();
+            set.push("basic_modes");
+        }
+        if let Some(basic_zones) = &self.basic_zones {
+            data.basic_zones = basic_zones.clone();
+            set.push("basic_zones");
+        }
+        if let Some(advanced_type) = &self.advanced_type {
+            data.advanced_type = advanced_type.clone();
+            set.push("advanced_type");
+        }
+        if let Some(power_zones) = &self.power_zones {
+            data.power_zones = power_zones.clone();
+            set.push("power_zones");
+        }
+        set
+    }
+}
+
+impl From<LaptopLedData> for LaptopLedOverride {
+    fn from(data: LaptopLedData) -> Self {
+        Self {
+            board_name: data.board_name,
+            match_kind: data.match_kind,
+            product_family: data.product_family,
+            priority: data.priority,
+            layout_name: Some(data.layout_name),
+            basic_modes: Some(data.basic_modes),
+            basic_zones: Some(data.basic_zones),
+            advanced_type: Some(data.advanced_type),
+            power_zones: Some(data.power_zones),
+        }
+    }
 }
 
 impl LedSupportFile {
     pub fn get(&self) -> &[LaptopLedData] {
         &self.0
     }
+}
 
-    /// The list is stored in ordered format, so the iterator must be reversed
-    /// to ensure we match to *whole names* first before doing a glob match
-    pub fn matcher(self, board_name: &str) -> Option<LaptopLedData> {
-        for config in self.0.iter().rev() {
-            if board_name.contains(&config.board_name) {
-                info!("LedSupport: Matched to {}", config.board_name);
-                return Some(config.clone());
-            }
+/// The contents of `/etc/asusd/asusd_user_ledmodes.ron`. Values are written
+/// without `Some()`, a field left out is not overridden.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LedUserFile(Vec<LaptopLedOverride>);
+
+impl LedUserFile {
+    pub fn get(&self) -> &[LaptopLedOverride] {
+        &self.0
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        let pretty = ron::ser::PrettyConfig::new().depth_limit(2);
+        ron::ser::to_string_pretty(self, pretty)
+    }
+
+    /// Add an entry, replacing any with the same match rule, and keep the
+    /// list sorted
+    pub fn insert(&mut self, data: LaptopLedOverride) {
+        self.0.retain(|d| {
+            d.board_name != data.board_name
+                || d.match_kind != data.match_kind
+                || d.product_family != data.product_family
+        });
+        self.0.push(data);
+        self.0.sort_by(|a, b| a.board_name.cmp(&b.board_name));
+    }
+}
+
+/// Where the value of a `LaptopLedData` field came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldSource {
+    /// Nothing matched, generic brightness only control
+    Default,
+    /// The packaged entry in `aura_support.ron`
+    Packaged,
+    /// The user entry with this rule
+    User(String),
+}
+
+impl Display for FieldSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Packaged => write!(f, "{ASUS_LED_MODE_CONF}"),
+            Self::User(rule) => write!(f, "{ASUS_LED_MODE_USER_CONF} {rule}"),
         }
-        None
     }
+}
+
+/// The result of matching a laptop, with the reasons for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedMatch {
+    pub data: LaptopLedData,
+    /// The rule of the packaged entry used, if any
+    pub packaged: Option<String>,
+    /// The rules of the user entries applied, the last taking precedence
+    pub user: Vec<String>,
+    /// Each field with where its value came from
+    pub fields: Vec<(&'static str, FieldSource)>,
+}
 
-    /// Load `LedSupportFile` from the `aura_support.ron` file at
-    /// `/usr/share/asusd/aura_support.ron` and append with data from
-    /// `/etc/asusd/asusd_user_ledmodes.ron` if that file is available.
+impl LedMatch {
+    pub fn is_match(&self) -> bool {
+        self.packaged.is_some() || !self.user.is_empty()
+    }
+}
+
+impl Display for LedMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.packaged {
+            Some(rule) => writeln!(f, "Packaged entry: {rule}")?,
+            None => writeln!(f, "Packaged entry: none")?,
+        }
+        for rule in &self.user {
+            writeln!(f, "User entry: {rule}")?;
+        }
+        for (field, source) in &self.fields {
+            writeln!(f, "\t{field}: {source}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The packaged entries and the user entries merged over them
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LedSupportDb {
+    pub packaged: LedSupportFile,
+    pub user: LedUserFile,
+    regexes: Regexes,
+}
+
+impl LedSupportDb {
+    pub fn new(packaged: LedSupportFile, user: LedUserFile) -> Self {
+        let regexes = Regexes::compile(
+            packaged
+                .0
+                .iter()
+                .map(LaptopLedData::rule)
+                .chain(user.0.iter().map(LaptopLedOverride::rule)),
+        );
+        Self {
+            packaged,
+            user,
+            regexes,
+        }
+    }
+
+    /// Load the packaged entries from `/usr/share/asusd/aura_support.ron` and
+    /// the user entries from `/etc/asusd/asusd_user_ledmodes.ron` if that file
+    /// is available.
     ///
-    /// Returns `None` if neither file exists or does not parse correctly.
-    pub fn load_from_supoprt_db() -> Option<Self> {
-        let mut loaded = false;
-        let mut data = LedSupportFile::default();
-        // Load user configs first so they are first to be checked
+    /// Returns `None` if the packaged file does not exist.
+    pub fn load() -> Option<Self> {
+        let mut user = LedUserFile::default();
         if let Ok(file) = std::fs::read_to_string(ASUS_LED_MODE_USER_CONF) {
             if file.is_empty() {
                 warn!("{} is empty", ASUS_LED_MODE_USER_CONF);
             } else {
-                if let Ok(mut tmp) = ron::from_str::<LedSupportFile>(&file) {
-                    data.0.append(&mut tmp.0);
+                match LedUserFile::parse(&file) {
+                    Ok(file) => {
+                        user = file;
+                        info!(
+                            "Loaded user-defined LED support data from {}",
+                            ASUS_LED_MODE_USER_CONF
+                        );
+                    }
+                    Err(e) => error!("Could not parse {}: {e}", ASUS_LED_MODE_USER_CONF),
                 }
-                info!(
-                    "Loaded user-defined LED support data from {}",
-                    ASUS_LED_MODE_USER_CONF
-                );
             }
         }
-        // Load and append the default LED support data
         if let Ok(file) = std::fs::read_to_string(ASUS_LED_MODE_CONF) {
             if file.is_empty() {
                 warn!("{} is empty", ASUS_LED_MODE_CONF);
             } else {
-                let mut tmp: LedSupportFile = ron::from_str(&file)
+                let packaged = ron::from_str(&file)
                     .map_err(|e| error!("{e}"))
                     .unwrap_or_else(|_| panic!("Could not deserialise {}", ASUS_LED_MODE_CONF));
-                data.0.append(&mut tmp.0);
-                loaded = true;
                 info!(
                     "Loaded default LED support data from {}",
                     ASUS_LED_MODE_CONF
                 );
+                return Some(Self::new(packaged, user));
             }
         }
-        data.0.sort_by(|a, b| a.board_name.cmp(&b.board_name));
+        warn!("Does {} exist?", ASUS_LED_MODE_CONF);
+        None
+    }
+
+    /// Find the best packaged entry, then apply the matching user entries
+    /// over it field by field, the most preferred last
+    pub fn explain(&self, board_name: &str, product_family: &str) -> LedMatch {
+        let packaged = self
+            .packaged
+            .0
+            .iter()
+            .filter(|d| d.rule().matches(board_name, product_family, &self.regexes))
+            .max_by_key(|d| d.rule().score());
+        let mut data = packaged.cloned().unwrap_or_default();
+        let source = if packaged.is_some() {
+            FieldSource::Packaged
+        } else {
+            FieldSource::Default
+        };
+        let mut fields: Vec<(&'static str, FieldSource)> = [
+            "layout_name",
+            "basic_modes",
+            "basic_zones",
+            "advanced_type",
+            "power_zones",
+        ]
+        .into_iter()
+        .map(|field| (field, source.clone()))
+        .collect();
+
+        let mut user: Vec<&LaptopLedOverride> = self
+            .user
+            .0
+            .iter()
+            .filter(|d| d.rule().matches(board_name, product_family, &self.regexes))
+            .collect();
+        user.sort_by_key(|d| d.rule().score());
+        for entry in &user {
+            let rule = entry.rule().to_string();
+            for field in entry.apply(&mut data) {
+                if let Some(f) = fields.iter_mut().find(|(name, _)| *name == field) {
+                    f.1 = FieldSource::User(rule.clone());
+                }
+            }
+        }
+        if let (None, Some(last)) = (packaged, user.last()) {
+            data.board_name = last.board_name.clone();
+            data.match_kind = last.match_kind;
+            data.product_family = last.product_family.clone();
+            data.priority = last.priority;
+        }
 
-        if loaded {
-            return Some(data);
+        let result = LedMatch {
+            data,
+            packaged: packaged.map(|d| d.rule().to_string()),
+            user: user.iter().map(|d| d.rule().to_string()).collect(),
+            fields,
+        };
+        if let Some(rule) = &result.packaged {
+            info!("LedSupport: Matched to {rule}");
         }
+        for rule in &result.user {
+            info!("LedSupport: Applied user entry {rule}");
+        }
+        result
+    }
 
-        warn!("Does {} exist?", ASUS_LED_MODE_USER_CONF);
+    /// The merged entry for this laptop, if any entry matched
+    pub fn matcher(&self, board_name: &str, product_family: &str) -> Option<LaptopLedData> {
+        let result = self.explain(board_name, product_family);
+        if result.is_match() {
+            return Some(result.data);
+        }
         None
     }
 }
@@ -149,7 +534,10 @@ mod tests {
 
     use super::LaptopLedData;
     use crate::advanced::LedCode;
-    use crate::aura_detection::{LedSupportFile, PowerZones};
+    use crate::aura_detection::{
+        FieldSource, LaptopLedOverride, LedSupportDb, LedSupportFile, LedUserFile, MatchKind,
+        PowerZones,
+    };
     // use crate::zoned::Zone;
     use crate::{AdvancedAuraType, AuraModeNum, AuraZone};
 
@@ -162,12 +550,114 @@ mod tests {
             basic_zones: vec![AuraZone::Key1, AuraZone::Logo, AuraZone::BarLeft],
             advanced_type: AdvancedAuraType::Zoned(vec![LedCode::LightbarRight]),
             power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+            ..Default::default()
         };
 
         assert!(ron::to_string(&led).is_ok());
         // assert_eq!(json, String::new());
     }
 
+    #[test]
+    fn insert_replaces_entry() {
+        let mut file = LedUserFile::default();
+        for name in ["GU603", "GA401", "GU603"] {
+            file.insert(LaptopLedOverride {
+                board_name: name.to_owned(),
+                ..Default::default()
+            });
+        }
+        let names: Vec<&str> = file.get().iter().map(|d| d.board_name.as_str()).collect();
+        assert_eq!(names, ["GA401", "GU603"]);
+        assert_eq!(LedUserFile::parse(&file.to_ron().unwrap()).unwrap(), file);
+    }
+
+    #[test]
+    fn user_file_keeps_advanced_none() {
+        let mut file = LedUserFile::default();
+        file.insert(LaptopLedOverride {
+            board_name: "GA401".to_owned(),
+            advanced_type: Some(AdvancedAuraType::None),
+            power_zones: Some(vec![PowerZones::Keyboard]),
+            ..Default::default()
+        });
+        file.insert(LaptopLedOverride {
+            board_name: "GU603".to_owned(),
+            layout_name: Some("gu603".to_owned()),
+            ..Default::default()
+        });
+        let text = file.to_ron().unwrap();
+        let parsed = LedUserFile::parse(&text).unwrap();
+        assert_eq!(parsed, file);
+        assert_eq!(parsed.get()[0].advanced_type, Some(AdvancedAuraType::None));
+        assert_eq!(parsed.get()[1].advanced_type, None);
+    }
+
+    #[test]
+    fn match_rules_and_merge() {
+        let packaged: LedSupportFile = ron::from_str(
+            r#"([
+                (board_name: "GU603", layout_name: "contains", basic_modes: [Static],
+                 basic_zones: [], advanced_type: None, power_zones: [Keyboard]),
+                (board_name: "GU603Z", match_kind: Prefix, layout_name: "prefix",
+                 basic_modes: [Static, Breathe], basic_zones: [], advanced_type: PerKey,
+                 power_zones: [Keyboard]),
+                (board_name: "^GU603Z[VW]$", match_kind: Regex, product_family: "Zephyrus",
+                 layout_name: "regex", basic_modes: [Static, Pulse], basic_zones: [],
+                 advanced_type: PerKey, power_zones: [Keyboard, Lightbar]),
+                (board_name: "GU603ZW", match_kind: Exact, priority: -1, layout_name: "exact",
+                 basic_modes: [], basic_zones: [], advanced_type: None, power_zones: []),
+            ])"#,
+        )
+        .unwrap();
+        // Values don't need Some() in the user file
+        let user = LedUserFile::parse(
+            r#"([
+                (board_name: "GU603Z", basic_modes: [Static, Rainbow]),
+                (board_name: "GU603ZW", match_kind: Exact, layout_name: "mine"),
+            ])"#,
+        )
+        .unwrap();
+        let db = LedSupportDb::new(packaged, user);
+
+        let m = db.explain("GU603ZW", "ROG Zephyrus M16");
+        assert_eq!(
+            m.packaged.as_deref(),
+            Some(r#"Regex "^GU603Z[VW]$", product_family "Zephyrus""#)
+        );
+        assert_eq!(m.data.layout_name, "mine");
+        assert_eq!(
+            m.data.basic_modes,
+            vec![AuraModeNum::Static, AuraModeNum::Rainbow]
+        );
+        assert_eq!(
+            m.data.power_zones,
+            vec![PowerZones::Keyboard, PowerZones::Lightbar]
+        );
+        assert_eq!(m.user, [r#"Contains "GU603Z""#, r#"Exact "GU603ZW""#]);
+        assert_eq!(
+            m.fields[0].1,
+            FieldSource::User(r#"Exact "GU603ZW""#.to_owned())
+        );
+        assert_eq!(m.fields[3], ("advanced_type", FieldSource::Packaged));
+
+        // The family is required by the regex entry
+        let m = db.explain("GU603ZW", "ROG Strix");
+        assert_eq!(m.packaged.as_deref(), Some(r#"Prefix "GU603Z""#));
+
+        let m = db.explain("GU603H", "");
+        assert_eq!(m.data.layout_name, "contains");
+        assert!(m.user.is_empty());
+
+        let m = db.explain("XGU603Z", "");
+        assert_eq!(m.packaged.as_deref(), Some(r#"Contains "GU603""#));
+        assert_eq!(m.data.match_kind, MatchKind::Contains);
+
+        let m = db.explain("GA401", "");
+        assert!(!m.is_match());
+        assert!(db.matcher("GA401", "").is_none());
+        assert_eq!(m.fields[0].1, FieldSource::Default);
+    }
+
     #[test]
     fn check_data_file_parse() {
         let mut data = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
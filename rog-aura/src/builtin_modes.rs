@@ -300,7 +300,7 @@ impl FromStr for AuraZone {
 /// ```
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type))]
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct AuraEffect {
     /// The effect type
     pub mode: AuraModeNum,
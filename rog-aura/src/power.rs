@@ -160,6 +160,35 @@ impl AuraPower {
             ((a & 0xff000000) >> 24) as u8,
         ]
     }
+
+    /// The reverse of `to_bytes()`. Bits that are not known are ignored.
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        let a = u32::from_le_bytes(bytes);
+        let state = |zone: PowerZones| {
+            let bit = |on: KbAuraPowerState| a & on.to_byte(zone) != 0;
+            let off = KbAuraPowerState {
+                zone,
+                ..Default::default()
+            };
+            KbAuraPowerState {
+                zone,
+                boot: bit(KbAuraPowerState { boot: true, ..off }),
+                awake: bit(KbAuraPowerState { awake: true, ..off }),
+                sleep: bit(KbAuraPowerState { sleep: true, ..off }),
+                shutdown: bit(KbAuraPowerState {
+                    shutdown: true,
+                    ..off
+                }),
+            }
+        };
+        Self {
+            keyboard: state(PowerZones::Keyboard),
+            logo: state(PowerZones::Logo),
+            lightbar: state(PowerZones::Lightbar),
+            lid: state(PowerZones::Lid),
+            rear_glow: state(PowerZones::RearGlow),
+        }
+    }
 }
 
 #[test]
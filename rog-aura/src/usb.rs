@@ -88,7 +88,7 @@ impl Debug for AuraDevice {
 /// This struct is intended as a helper to pass args to generic dbus interface
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type))]
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AuraPowerDev {
     /// TUF laptops use a similar style of control to the older ROG devices but
     /// through WMI
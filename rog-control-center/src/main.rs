@@ -9,7 +9,7 @@ use std::time::Duration;
 use eframe::IconData;
 use gumdrop::Options;
 use log::{debug, error, info, warn, LevelFilter};
-use rog_aura::aura_detection::{LaptopLedData, LedSupportFile};
+use rog_aura::aura_detection::{LaptopLedData, LedSupportDb};
 use rog_aura::layouts::KeyLayout;
 use rog_control_center::cli_options::CliStart;
 use rog_control_center::config::Config;
@@ -127,8 +127,8 @@ fn main() -> Result<()> {
         layouts = KeyLayout::layout_files(path.clone()).unwrap();
 
         if let Some(name) = &cli_parsed.board_name {
-            if let Some(modes) = LedSupportFile::load_from_supoprt_db() {
-                if let Some(data) = modes.matcher(name) {
+            if let Some(modes) = LedSupportDb::load() {
+                if let Some(data) = modes.matcher(name, "") {
                     led_support = data;
                 }
             }
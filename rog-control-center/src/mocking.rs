@@ -268,6 +268,7 @@ impl Supported {
                 dgpu_disable: true,
                 mini_led_mode: true,
                 egpu_enable: true,
+                tunables: vec![],
             },
         })
     }
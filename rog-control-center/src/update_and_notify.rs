@@ -530,9 +530,9 @@ fn ac_power_notification(message: &str, on: &bool) -> Result<NotificationHandle>
 
 fn do_thermal_notif(message: &str, profile: &Profile) -> Result<NotificationHandle> {
     let icon = match profile {
-        Profile::Balanced => "asus_notif_yellow",
-        Profile::Performance => "asus_notif_red",
-        Profile::Quiet => "asus_notif_green",
+        Profile::Balanced | Profile::Custom | Profile::Other(_) => "asus_notif_yellow",
+        Profile::Performance | Profile::BalancedPerformance | Profile::MaxPower => "asus_notif_red",
+        Profile::Quiet | Profile::LowPower | Profile::Cool => "asus_notif_green",
     };
     let profile: &str = (*profile).into();
     let mut notif = base_notification(message, &profile.to_uppercase());
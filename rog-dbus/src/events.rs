@@ -0,0 +1,144 @@
+//! A single stream of every signal the daemon emits, for async clients that
+//! want to react to changes without subscribing to each signal.
+
+use rog_anime::DeviceState as AnimeDeviceState;
+use rog_aura::usb::AuraPowerDev;
+use rog_aura::AuraEffect;
+use rog_platform::firmware_attributes::FirmwareAttribute;
+use rog_platform::platform::{GpuMode, PlatformTunable};
+use rog_platform::supported::SupportedFunctions;
+use rog_profiles::thermal_guard::ThermalGuardEvent;
+use rog_profiles::Profile;
+use serde_derive::Serialize;
+use zbus::export::futures_util::stream::{self, BoxStream};
+use zbus::export::futures_util::{future, StreamExt};
+use zbus::Result;
+
+use crate::DbusProxies;
+
+/// A `notify_*` signal from the daemon, with its argument. Serialized as
+/// `{"event": "<variant>", "data": <argument>}`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum DaemonEvent {
+    AnimeDeviceState(AnimeDeviceState),
+    FirmwareAttribute(FirmwareAttribute),
+    Led(AuraEffect),
+    LedPowerStates(AuraPowerDev),
+    DgpuDisable(bool),
+    EgpuEnable(bool),
+    GpuMuxMode(GpuMode),
+    PanelOd(bool),
+    MiniLedMode(bool),
+    PostBootSound(bool),
+    Tunable(PlatformTunable, u8),
+    ChargeControlEndThreshold(u8),
+    MainsOnline(bool),
+    Profile(Profile),
+    ThermalGuard(ThermalGuardEvent),
+    SupportedFunctions(SupportedFunctions),
+}
+
+impl DaemonEvent {
+    /// The names returned by [`DaemonEvent::subsystem()`]
+    pub const SUBSYSTEMS: [&'static str; 7] = [
+        "anime",
+        "aura",
+        "platform",
+        "power",
+        "profile",
+        "firmware",
+        "supported",
+    ];
+
+    /// The short name of the daemon interface which emitted this event
+    pub fn subsystem(&self) -> &'static str {
+        match self {
+            Self::AnimeDeviceState(_) => "anime",
+            Self::FirmwareAttribute(_) => "firmware",
+            Self::Led(_) | Self::LedPowerStates(_) => "aura",
+            Self::DgpuDisable(_)
+            | Self::EgpuEnable(_)
+            | Self::GpuMuxMode(_)
+            | Self::PanelOd(_)
+            | Self::MiniLedMode(_)
+            | Self::PostBootSound(_)
+            | Self::Tunable(..) => "platform",
+            Self::ChargeControlEndThreshold(_) | Self::MainsOnline(_) => "power",
+            Self::Profile(_) | Self::ThermalGuard(_) => "profile",
+            Self::SupportedFunctions(_) => "supported",
+        }
+    }
+}
+
+/// Subscribe to `$receive` on `$proxy` and map each signal to an event.
+/// Signals with arguments which can't be parsed are dropped.
+macro_rules! event_stream {
+    ($proxy:expr, $receive:ident, |$args:ident| $event:expr) => {
+        $proxy
+            .$receive()
+            .await?
+            .filter_map(|signal| future::ready(signal.args().ok().map(|$args| $event)))
+            .boxed()
+    };
+}
+
+impl<'a> DbusProxies<'a> {
+    /// All daemon signals merged in to one stream. The stream only ends if the
+    /// connection is closed.
+    pub async fn events(&self) -> Result<BoxStream<'static, DaemonEvent>> {
+        let streams = vec![
+            event_stream!(self.anime(), receive_device_state, |a| {
+                DaemonEvent::AnimeDeviceState(*a.data())
+            }),
+            event_stream!(self.firmware_attributes(), receive_notify_attribute, |a| {
+                DaemonEvent::FirmwareAttribute(a.attribute().clone())
+            }),
+            event_stream!(self.led(), receive_notify_led, |a| {
+                DaemonEvent::Led(a.data().clone())
+            }),
+            event_stream!(self.led(), receive_notify_power_states, |a| {
+                DaemonEvent::LedPowerStates(a.data().clone())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_dgpu_disable, |a| {
+                DaemonEvent::DgpuDisable(*a.disable())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_egpu_enable, |a| {
+                DaemonEvent::EgpuEnable(*a.enable())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_gpu_mux_mode, |a| {
+                DaemonEvent::GpuMuxMode(*a.mode())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_panel_od, |a| {
+                DaemonEvent::PanelOd(*a.overdrive())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_mini_led_mode, |a| {
+                DaemonEvent::MiniLedMode(*a.on())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_post_boot_sound, |a| {
+                DaemonEvent::PostBootSound(*a.on())
+            }),
+            event_stream!(self.rog_bios(), receive_notify_tunable, |a| {
+                DaemonEvent::Tunable(*a.tunable(), *a.value())
+            }),
+            event_stream!(
+                self.charge(),
+                receive_notify_charge_control_end_threshold,
+                |a| DaemonEvent::ChargeControlEndThreshold(*a.limit())
+            ),
+            event_stream!(self.charge(), receive_notify_mains_online, |a| {
+                DaemonEvent::MainsOnline(*a.on())
+            }),
+            event_stream!(self.profile(), receive_notify_profile, |a| {
+                DaemonEvent::Profile(*a.profile())
+            }),
+            event_stream!(self.profile(), receive_notify_thermal_guard, |a| {
+                DaemonEvent::ThermalGuard(*a.event())
+            }),
+            event_stream!(self.supported(), receive_notify_supported_functions, |a| {
+                DaemonEvent::SupportedFunctions(a.data().clone())
+            }),
+        ];
+        Ok(stream::select_all(streams).boxed())
+    }
+}
@@ -2,7 +2,10 @@ pub static DBUS_NAME: &str = "org.asuslinux.Daemon";
 pub static DBUS_PATH: &str = "/org/asuslinux/Daemon";
 pub static DBUS_IFACE: &str = "org.asuslinux.Daemon";
 
+pub mod events;
 pub mod zbus_anime;
+pub mod zbus_app_profiles;
+pub mod zbus_firmware_attributes;
 pub mod zbus_led;
 pub mod zbus_platform;
 pub mod zbus_power;
@@ -20,6 +23,7 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub struct DbusProxiesBlocking<'a> {
     anime: zbus_anime::AnimeProxyBlocking<'a>,
     charge: zbus_power::PowerProxyBlocking<'a>,
+    firmware_attributes: zbus_firmware_attributes::FirmwareAttributesProxyBlocking<'a>,
     led: zbus_led::LedProxyBlocking<'a>,
     profile: zbus_profile::ProfileProxyBlocking<'a>,
     rog_bios: zbus_platform::RogBiosProxyBlocking<'a>,
@@ -36,6 +40,8 @@ impl<'a> DbusProxiesBlocking<'a> {
                 anime: zbus_anime::AnimeProxyBlocking::new(&conn)?,
                 led: zbus_led::LedProxyBlocking::new(&conn)?,
                 charge: zbus_power::PowerProxyBlocking::new(&conn)?,
+                firmware_attributes:
+                    zbus_firmware_attributes::FirmwareAttributesProxyBlocking::new(&conn)?,
                 profile: zbus_profile::ProfileProxyBlocking::new(&conn)?,
                 rog_bios: zbus_platform::RogBiosProxyBlocking::new(&conn)?,
                 supported: zbus_supported::SupportedProxyBlocking::new(&conn)?,
@@ -52,6 +58,12 @@ impl<'a> DbusProxiesBlocking<'a> {
         &self.charge
     }
 
+    pub fn firmware_attributes(
+        &self,
+    ) -> &zbus_firmware_attributes::FirmwareAttributesProxyBlocking<'a> {
+        &self.firmware_attributes
+    }
+
     pub fn led(&self) -> &zbus_led::LedProxyBlocking<'a> {
         &self.led
     }
@@ -89,6 +101,7 @@ impl<'a> RogDbusClientBlocking<'a> {
 pub struct DbusProxies<'a> {
     anime: zbus_anime::AnimeProxy<'a>,
     charge: zbus_power::PowerProxy<'a>,
+    firmware_attributes: zbus_firmware_attributes::FirmwareAttributesProxy<'a>,
     led: zbus_led::LedProxy<'a>,
     profile: zbus_profile::ProfileProxy<'a>,
     rog_bios: zbus_platform::RogBiosProxy<'a>,
@@ -105,6 +118,8 @@ impl<'a> DbusProxies<'a> {
                 anime: zbus_anime::AnimeProxy::new(&conn).await?,
                 led: zbus_led::LedProxy::new(&conn).await?,
                 charge: zbus_power::PowerProxy::new(&conn).await?,
+                firmware_attributes: zbus_firmware_attributes::FirmwareAttributesProxy::new(&conn)
+                    .await?,
                 profile: zbus_profile::ProfileProxy::new(&conn).await?,
                 rog_bios: zbus_platform::RogBiosProxy::new(&conn).await?,
                 supported: zbus_supported::SupportedProxy::new(&conn).await?,
@@ -121,6 +136,10 @@ impl<'a> DbusProxies<'a> {
         &self.charge
     }
 
+    pub fn firmware_attributes(&self) -> &zbus_firmware_attributes::FirmwareAttributesProxy<'a> {
+        &self.firmware_attributes
+    }
+
     pub fn led(&self) -> &zbus_led::LedProxy<'a> {
         &self.led
     }
@@ -153,4 +172,12 @@ impl<'a> RogDbusClient<'a> {
     pub fn proxies(&self) -> &DbusProxies<'_> {
         &self.proxies
     }
+
+    /// Every daemon signal as a [`events::DaemonEvent`], see
+    /// [`DbusProxies::events()`]
+    pub async fn events(
+        &self,
+    ) -> Result<zbus::export::futures_util::stream::BoxStream<'static, events::DaemonEvent>> {
+        self.proxies.events().await
+    }
 }
@@ -0,0 +1,24 @@
+//! # `DBus` interface proxy for: `org.asuslinux.Daemon`
+//!
+//! Interface `/org/asuslinux/AppProfiles` from service `org.asuslinux.Daemon`
+//! on the session bus, served by asusd-user.
+use rog_profiles::app_profile::AppProfileRule;
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    interface = "org.asuslinux.Daemon",
+    default_path = "/org/asuslinux/AppProfiles"
+)]
+trait AppProfiles {
+    /// Rules method
+    fn rules(&self) -> zbus::Result<Vec<AppProfileRule>>;
+
+    /// SetRule method
+    fn set_rule(&self, rule: &AppProfileRule) -> zbus::Result<()>;
+
+    /// RemoveRule method
+    fn remove_rule(&self, exe: &str) -> zbus::Result<()>;
+
+    /// ActiveRule method
+    fn active_rule(&self) -> zbus::Result<String>;
+}
@@ -0,0 +1,27 @@
+//! # `DBus` interface proxy for: `org.asuslinux.Daemon`
+//!
+//! Interface `/org/asuslinux/FirmwareAttributes` from service
+//! `org.asuslinux.Daemon` on the system bus. Exposes all attributes under
+//! `/sys/class/firmware-attributes`.
+
+use rog_platform::firmware_attributes::FirmwareAttribute;
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    interface = "org.asuslinux.Daemon",
+    default_path = "/org/asuslinux/FirmwareAttributes"
+)]
+trait FirmwareAttributes {
+    /// List all attributes with their type, limits and current value
+    fn attributes(&self) -> zbus::Result<Vec<FirmwareAttribute>>;
+
+    /// Get a single attribute by the name of its device and its own name
+    fn attribute(&self, device: &str, name: &str) -> zbus::Result<FirmwareAttribute>;
+
+    /// Set the current value of an attribute
+    fn set_attribute(&self, device: &str, name: &str, value: &str) -> zbus::Result<()>;
+
+    /// NotifyAttribute signal
+    #[dbus_proxy(signal)]
+    fn notify_attribute(&self, attribute: FirmwareAttribute) -> zbus::Result<()>;
+}
@@ -20,7 +20,10 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
-use rog_platform::platform::GpuMode;
+use std::collections::BTreeMap;
+
+use rog_platform::platform::{GpuMode, PlatformTunable};
+use rog_profiles::Profile;
 use zbus::dbus_proxy;
 
 #[dbus_proxy(
@@ -64,6 +67,33 @@ trait RogBios {
     /// SetPostBootSound method
     fn set_post_boot_sound(&self, on: bool) -> zbus::Result<()>;
 
+    /// Tunable method
+    fn tunable(&self, tunable: PlatformTunable) -> zbus::Result<u8>;
+
+    /// TunableRange method
+    fn tunable_range(&self, tunable: PlatformTunable) -> zbus::Result<(u8, u8)>;
+
+    /// SetTunable method
+    fn set_tunable(&self, tunable: PlatformTunable, value: u8) -> zbus::Result<()>;
+
+    /// ProfileTunables method
+    fn profile_tunables(&self, profile: Profile) -> zbus::Result<BTreeMap<PlatformTunable, u8>>;
+
+    /// SetProfileTunable method
+    fn set_profile_tunable(
+        &self,
+        profile: Profile,
+        tunable: PlatformTunable,
+        value: u8,
+    ) -> zbus::Result<()>;
+
+    /// RemoveProfileTunable method
+    fn remove_profile_tunable(
+        &self,
+        profile: Profile,
+        tunable: PlatformTunable,
+    ) -> zbus::Result<()>;
+
     /// NotifyDgpuDisable signal
     #[dbus_proxy(signal)]
     fn notify_dgpu_disable(&self, disable: bool) -> zbus::Result<()>;
@@ -88,4 +118,8 @@ trait RogBios {
     #[inline]
     #[dbus_proxy(signal)]
     fn notify_post_boot_sound(&self, on: bool) -> zbus::Result<()>;
+
+    /// NotifyTunable signal
+    #[dbus_proxy(signal)]
+    fn notify_tunable(&self, tunable: PlatformTunable, value: u8) -> zbus::Result<()>;
 }
@@ -20,8 +20,12 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
+use std::collections::BTreeMap;
+
+use rog_platform::cpu::CpuPolicy;
 use rog_profiles::fan_curve_set::CurveData;
-use rog_profiles::{FanCurvePU, Profile};
+use rog_profiles::thermal_guard::{ThermalGuard, ThermalGuardEvent};
+use rog_profiles::{FanCurvePU, Profile, ProfileCycle};
 use zbus::dbus_proxy;
 
 #[dbus_proxy(
@@ -45,6 +49,36 @@ trait Profile {
     /// Set this platform_profile name as active
     fn set_active_profile(&self, profile: Profile) -> zbus::Result<()>;
 
+    /// Fetch the order and skip list used by `NextProfile`, for AC power if
+    /// `mains` is true otherwise for battery
+    fn profile_cycle(&self, mains: bool) -> zbus::Result<ProfileCycle>;
+
+    /// Set the order and skip list used by `NextProfile`, for AC power if
+    /// `mains` is true otherwise for battery
+    fn set_profile_cycle(&self, mains: bool, cycle: ProfileCycle) -> zbus::Result<()>;
+
+    /// Fetch the thermal guards, keyed by the profile each watches
+    fn thermal_guards(&self) -> zbus::Result<BTreeMap<Profile, ThermalGuard>>;
+
+    /// Set the thermal guard for a profile
+    fn set_thermal_guard(&self, profile: Profile, guard: &ThermalGuard) -> zbus::Result<()>;
+
+    /// Remove the thermal guard for a profile
+    fn remove_thermal_guard(&self, profile: Profile) -> zbus::Result<()>;
+
+    /// Fetch the cpufreq settings, keyed by the profile they are applied with
+    fn cpu_policies(&self) -> zbus::Result<BTreeMap<Profile, CpuPolicy>>;
+
+    /// Set the cpufreq governor, EPP and boost to use with a profile
+    fn set_cpu_policy(&self, profile: Profile, policy: &CpuPolicy) -> zbus::Result<()>;
+
+    /// Remove the cpufreq settings of a profile
+    fn remove_cpu_policy(&self, profile: Profile) -> zbus::Result<()>;
+
+    /// Fetch the available cpufreq governors and energy performance
+    /// preferences
+    fn cpu_policy_choices(&self) -> zbus::Result<(Vec<String>, Vec<String>)>;
+
     /// Set a profile fan curve enabled status. Will also activate a fan curve.
     fn set_fan_curves_enabled(&self, profile: Profile, enabled: bool) -> zbus::Result<()>;
 
@@ -78,4 +112,8 @@ trait Profile {
     /// NotifyProfile signal
     #[dbus_proxy(signal)]
     async fn notify_profile(&self, profile: Profile) -> zbus::Result<Profile>;
+
+    /// NotifyThermalGuard signal
+    #[dbus_proxy(signal)]
+    async fn notify_thermal_guard(&self, event: ThermalGuardEvent) -> zbus::Result<()>;
 }
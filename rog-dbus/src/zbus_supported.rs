@@ -20,7 +20,7 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
-use rog_platform::supported::SupportedFunctions;
+use rog_platform::supported::{Probe, SupportedFunctions};
 use zbus::dbus_proxy;
 
 #[dbus_proxy(
@@ -30,4 +30,11 @@ use zbus::dbus_proxy;
 trait Supported {
     /// SupportedFunctions method
     fn supported_functions(&self) -> zbus::Result<SupportedFunctions>;
+
+    /// Diagnostics method
+    fn diagnostics(&self) -> zbus::Result<Vec<Probe>>;
+
+    /// NotifySupportedFunctions signal
+    #[dbus_proxy(signal)]
+    fn notify_supported_functions(&self, data: SupportedFunctions) -> zbus::Result<()>;
 }
@@ -0,0 +1,396 @@
+//! Recording of everything written to the hardware, for checking what asusd
+//! would send to a new board. When enabled the writes of `HidRaw`, `USBRaw`
+//! and sysfs attributes are appended to a capture file, and optionally also
+//! passed through to the hardware.
+//!
+//! Each line of the file is one write:
+//!
+//! ```text
+//! <seconds since epoch> <hidraw|usb|sysfs> <target> <hex data> # <description>
+//! ```
+//!
+//! where target is the device node for `hidraw`, `vendor:product` for `usb`,
+//! and the attribute path for `sysfs`.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+
+use crate::error::{PlatformError, Result};
+use crate::usb_raw::USBRaw;
+
+/// Path of the capture file, enables capture if set
+pub const CAPTURE_ENV: &str = "ASUSD_CAPTURE";
+/// If set to `1` writes are also sent to the hardware while capturing
+pub const CAPTURE_PASSTHROUGH_ENV: &str = "ASUSD_CAPTURE_PASSTHROUGH";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CaptureKind {
+    HidRaw,
+    Usb,
+    Sysfs,
+}
+
+impl Display for CaptureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HidRaw => write!(f, "hidraw"),
+            Self::Usb => write!(f, "usb"),
+            Self::Sysfs => write!(f, "sysfs"),
+        }
+    }
+}
+
+impl FromStr for CaptureKind {
+    type Err = PlatformError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hidraw" => Ok(Self::HidRaw),
+            "usb" => Ok(Self::Usb),
+            "sysfs" => Ok(Self::Sysfs),
+            _ => Err(PlatformError::InvalidValue(
+                "capture kind".to_owned(),
+                s.to_owned(),
+            )),
+        }
+    }
+}
+
+/// Name the packet by its report ID and command byte
+pub(crate) fn describe_packet(data: &[u8]) -> &'static str {
+    match data {
+        [0x5d, 0xb3, ..] => "aura: builtin mode",
+        [0x5d, 0xb4, ..] => "aura: apply",
+        [0x5d, 0xb5, ..] => "aura: set",
+        [0x5d, 0xb9, ..] | [0x5d, 0x05, ..] | [0x5e, 0x05, ..] => "aura: init",
+        [0x5d, 0xbc, ..] => "aura: direct/per-key colours",
+        [0x5d, 0xbd, ..] => "aura: power states",
+        [0x5a, 0xba, 0xc5, 0xc4, ..] => "aura: brightness",
+        [0x5d, b'A', ..] | [0x5e, b'A', ..] => "init: vendor string",
+        [0x5e, 0xc0, 0x02, ..] => "anime: pane data",
+        [0x5e, 0xc0, 0x03, ..] => "anime: flush",
+        [0x5e, 0xc0, 0x04, ..] => "anime: brightness",
+        [0x5e, 0xc2, ..] => "anime: init",
+        [0x5e, 0xc3, ..] => "anime: display enable",
+        [0x5e, 0xc4, ..] => "anime: builtin animations enable",
+        [0x5e, 0xc5, ..] => "anime: builtin animations",
+        _ => "unknown",
+    }
+}
+
+/// Parse `<secs>.<micros>`
+fn parse_timestamp(s: &str) -> Option<Duration> {
+    let (secs, micros) = s.split_once('.').unwrap_or((s, "0"));
+    let secs = secs.parse::<u64>().ok()?;
+    let micros = format!("{micros:0<6}").get(..6)?.parse::<u64>().ok()?;
+    Some(Duration::from_secs(secs) + Duration::from_micros(micros))
+}
+
+/// A single recorded write
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CaptureRecord {
+    /// Time since the unix epoch
+    pub timestamp: Duration,
+    pub kind: CaptureKind,
+    pub target: String,
+    pub data: Vec<u8>,
+}
+
+impl CaptureRecord {
+    pub fn new(kind: CaptureKind, target: &str, data: &[u8]) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            kind,
+            target: target.to_owned(),
+            data: data.to_vec(),
+        }
+    }
+
+    /// A short human readable meaning of the data
+    pub fn describe(&self) -> String {
+        match self.kind {
+            CaptureKind::Sysfs => format!("= {}", String::from_utf8_lossy(&self.data).trim()),
+            _ => format!("{} bytes, {}", self.data.len(), describe_packet(&self.data)),
+        }
+    }
+
+    /// Parse a line as written by `Display`. The description is ignored.
+    pub fn parse(line: &str) -> Result<Self> {
+        let invalid = || PlatformError::InvalidValue("capture line".to_owned(), line.to_owned());
+        let line = line.split('#').next().unwrap_or_default();
+        let mut parts = line.split_whitespace();
+        let timestamp = parts.next().and_then(parse_timestamp).ok_or_else(invalid)?;
+        let kind = parts.next().ok_or_else(invalid)?.parse()?;
+        let target = parts.next().ok_or_else(invalid)?.to_owned();
+        let hex = parts.next().unwrap_or_default();
+        if hex.len() % 2 == 1 {
+            return Err(invalid());
+        }
+        let data = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        Ok(Self {
+            timestamp,
+            kind,
+            target,
+            data,
+        })
+    }
+}
+
+impl Display for CaptureRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex: String = self.data.iter().map(|b| format!("{b:02x}")).collect();
+        write!(
+            f,
+            "{}.{:06} {} {} {} # {}",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros(),
+            self.kind,
+            self.target,
+            hex,
+            self.describe()
+        )
+    }
+}
+
+/// Writes recorded data back to the hardware. Requires root, and asusd should
+/// be stopped.
+#[derive(Default)]
+pub struct Replayer {
+    /// Opening resets the device so handles are kept
+    usb: HashMap<u16, USBRaw>,
+}
+
+/// Resolve the target of a record, refusing anything other than a sysfs
+/// attribute or a hidraw node. A capture file can name any path and replay
+/// runs as root.
+fn replay_path(record: &CaptureRecord) -> Result<PathBuf> {
+    let path = fs::canonicalize(&record.target)
+        .map_err(|e| PlatformError::Path(record.target.clone(), e))?;
+    let allowed = match record.kind {
+        CaptureKind::Sysfs => path.starts_with("/sys/"),
+        CaptureKind::HidRaw => {
+            path.parent() == Some(Path::new("/dev"))
+                && path
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with("hidraw"))
+        }
+        CaptureKind::Usb => false,
+    };
+    if !allowed {
+        return Err(PlatformError::InvalidValue(
+            format!("{} replay target", record.kind),
+            path.to_string_lossy().to_string(),
+        ));
+    }
+    Ok(path)
+}
+
+impl Replayer {
+    /// Write the record to its target, which must be under `/sys/` or a
+    /// `/dev/hidraw*` node
+    pub fn write(&mut self, record: &CaptureRecord) -> Result<()> {
+        match record.kind {
+            CaptureKind::HidRaw | CaptureKind::Sysfs => {
+                let path = replay_path(record)?;
+                fs::write(&path, &record.data)
+                    .map_err(|e| PlatformError::Write(path.to_string_lossy().to_string(), e))
+            }
+            CaptureKind::Usb => {
+                let product = record
+                    .target
+                    .split(':')
+                    .nth(1)
+                    .and_then(|p| u16::from_str_radix(p, 16).ok())
+                    .ok_or_else(|| {
+                        PlatformError::InvalidValue("usb target".to_owned(), record.target.clone())
+                    })?;
+                let usb = match self.usb.entry(product) {
+                    Entry::Occupied(e) => e.into_mut(),
+                    Entry::Vacant(e) => e.insert(USBRaw::new(product)?),
+                };
+                usb.write_bytes(&record.data).map(|_| ())
+            }
+        }
+    }
+}
+
+/// Read all records of a capture file, skipping comments and empty lines
+pub fn read_capture(path: &Path) -> Result<Vec<CaptureRecord>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| PlatformError::Read(path.to_string_lossy().to_string(), e))?;
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(CaptureRecord::parse)
+        .collect()
+}
+
+struct Capture {
+    path: PathBuf,
+    file: Mutex<File>,
+    passthrough: bool,
+}
+
+static CAPTURE: OnceLock<Capture> = OnceLock::new();
+
+/// Start capturing all writes to `path`. If `passthrough` is false nothing is
+/// written to the hardware. Can only be enabled once per process.
+pub fn enable(path: &Path, passthrough: bool) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| PlatformError::IoPath(path.to_string_lossy().to_string(), e))?;
+    let capture = Capture {
+        path: path.to_owned(),
+        file: Mutex::new(file),
+        passthrough,
+    };
+    if CAPTURE.set(capture).is_err() {
+        warn!("Capture is already enabled");
+        return Ok(());
+    }
+    // Profiles and fan curves are written by rog_profiles
+    rog_profiles::set_write_hook(|path, value| {
+        record(
+            CaptureKind::Sysfs,
+            &path.to_string_lossy(),
+            value.as_bytes(),
+        )
+    });
+    info!(
+        "Capturing hardware writes to {}{}",
+        path.display(),
+        if passthrough {
+            ", passing through to hardware"
+        } else {
+            ", hardware will not be written"
+        }
+    );
+    Ok(())
+}
+
+/// Enable capture if `ASUSD_CAPTURE` is set. Returns true if enabled.
+pub fn enable_from_env() -> Result<bool> {
+    if let Some(path) = std::env::var_os(CAPTURE_ENV) {
+        let passthrough = std::env::var(CAPTURE_PASSTHROUGH_ENV)
+            .map(|v| v == "1")
+            .unwrap_or(false);
+        enable(Path::new(&path), passthrough)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// The capture file in use, if any
+pub fn capture_path() -> Option<&'static Path> {
+    CAPTURE.get().map(|c| c.path.as_path())
+}
+
+/// Record a write if capture is enabled. Returns true if the write should
+/// still go to the hardware.
+pub(crate) fn record(kind: CaptureKind, target: &str, data: &[u8]) -> bool {
+    let capture = match CAPTURE.get() {
+        Some(capture) => capture,
+        None => return true,
+    };
+    let record = CaptureRecord::new(kind, target, data);
+    if let Ok(mut file) = capture.file.lock() {
+        writeln!(file, "{record}")
+            .map_err(|e| warn!("Capture write failed: {e}"))
+            .ok();
+    }
+    capture.passthrough
+}
+
+/// Record a sysfs attribute write, see `record()`
+pub(crate) fn record_attr(device: &udev::Device, attr: &str, value: &str) -> bool {
+    let target = device.syspath().join(attr);
+    record(
+        CaptureKind::Sysfs,
+        &target.to_string_lossy(),
+        value.as_bytes(),
+    )
+}
+
+/// Write a file, or only record it while capturing
+pub(crate) fn write_file(path: &Path, value: &str) -> std::io::Result<()> {
+    if record(
+        CaptureKind::Sysfs,
+        &path.to_string_lossy(),
+        value.as_bytes(),
+    ) {
+        fs::write(path, value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{replay_path, CaptureKind, CaptureRecord};
+
+    #[test]
+    fn capture_line_round_trip() {
+        let record = CaptureRecord {
+            timestamp: Duration::from_micros(1_697_712_345_123_456),
+            kind: CaptureKind::HidRaw,
+            target: "/dev/hidraw2".to_owned(),
+            data: vec![0x5d, 0xb3, 0x00, 0xff],
+        };
+        let line = record.to_string();
+        assert_eq!(
+            line,
+            "1697712345.123456 hidraw /dev/hidraw2 5db300ff # 4 bytes, aura: builtin mode"
+        );
+        let parsed = CaptureRecord::parse(&line).unwrap();
+        assert_eq!(parsed.kind, record.kind);
+        assert_eq!(parsed.target, record.target);
+        assert_eq!(parsed.data, record.data);
+        assert_eq!(parsed.timestamp, record.timestamp);
+
+        let sysfs =
+            CaptureRecord::parse("1.0 sysfs /sys/devices/platform/asus-nb-wmi/panel_od 31 # = 1")
+                .unwrap();
+        assert_eq!(sysfs.data, b"1");
+        assert!(CaptureRecord::parse("1.0 serial /dev/tty 00").is_err());
+        assert!(CaptureRecord::parse("1.0 usb 0b05:193b 5").is_err());
+    }
+
+    #[test]
+    fn replay_only_sysfs_and_hidraw() {
+        let record = |kind, target: &str| CaptureRecord {
+            timestamp: Duration::ZERO,
+            kind,
+            target: target.to_owned(),
+            data: vec![],
+        };
+        assert!(replay_path(&record(CaptureKind::Sysfs, "/etc/passwd")).is_err());
+        assert!(replay_path(&record(CaptureKind::Sysfs, "/sys/../etc/passwd")).is_err());
+        assert!(replay_path(&record(CaptureKind::HidRaw, "/dev/null")).is_err());
+        assert!(replay_path(&record(CaptureKind::HidRaw, "/sys/kernel")).is_err());
+        assert!(replay_path(&record(CaptureKind::Sysfs, "/sys/kernel")).is_ok());
+
+        // A link out of /sys is refused once resolved
+        let dir = tempfile::tempdir().unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink("/etc/passwd", &link).unwrap();
+        assert!(replay_path(&record(CaptureKind::Sysfs, &link.to_string_lossy())).is_err());
+    }
+}
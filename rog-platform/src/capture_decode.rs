@@ -0,0 +1,748 @@
+//! Import of USB captures of other software, such as Armoury Crate, and
+//! decoding of the Aura and AniMe packets found in them. This is meant to help
+//! with adding support for new keyboards.
+//!
+//! Supported capture formats are:
+//! - pcap and pcapng as written by Wireshark or tcpdump, from either Linux
+//!   usbmon or Windows USBPcap
+//! - usbmon text as read from `/sys/kernel/debug/usb/usbmon/<bus>u`. The
+//!   kernel only includes the first 32 bytes of each transfer in the text.
+//! - capture files written by asusd, see `capture`
+//!
+//! Only data sent to a device is imported.
+
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rog_anime::{pane_from_packet, AnimeDataBuffer, AnimeType};
+use rog_aura::advanced::{LedCode, LedUsbPackets};
+use rog_aura::power::{AuraPower, KbAuraPowerState};
+use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, Direction, Speed};
+
+use crate::capture::{describe_packet, CaptureKind, CaptureRecord};
+use crate::error::{PlatformError, Result};
+
+const PCAP_MAGIC_LE: [u8; 4] = [0xd4, 0xc3, 0xb2, 0xa1];
+const PCAP_MAGIC_BE: [u8; 4] = [0xa1, 0xb2, 0xc3, 0xd4];
+const PCAP_MAGIC_NS_LE: [u8; 4] = [0x4d, 0x3c, 0xb2, 0xa1];
+const PCAP_MAGIC_NS_BE: [u8; 4] = [0xa1, 0xb2, 0x3c, 0x4d];
+const PCAPNG_SECTION: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+/// Linux usbmon with the 48 byte header
+const LINKTYPE_USB_LINUX: u32 = 189;
+/// Linux usbmon with the 64 byte header
+const LINKTYPE_USB_LINUX_MMAPPED: u32 = 220;
+/// Windows USBPcap
+const LINKTYPE_USBPCAP: u32 = 249;
+
+/// Length of a full AniMe USB packet
+const ANIME_PACKET_LEN: usize = 640;
+
+/// One transfer of data to a device
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UsbTransfer {
+    /// As recorded in the capture, the epoch varies by format
+    pub timestamp: Duration,
+    /// `bus:device` for USB captures, or the target of an asusd capture
+    pub device: String,
+    pub data: Vec<u8>,
+}
+
+/// Little helper for reading fields of either byte order
+struct Fields<'a> {
+    data: &'a [u8],
+    le: bool,
+}
+
+impl Fields<'_> {
+    fn u16(&self, at: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.le {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+}
+
+fn truncated_error(what: &str) -> PlatformError {
+    PlatformError::InvalidValue("capture".to_owned(), format!("truncated {what}"))
+}
+
+/// Get the device and outgoing data of a captured packet, `None` if it is not
+/// data sent to a device
+fn usb_transfer(linktype: u32, packet: &[u8], le: bool) -> Option<(String, Vec<u8>)> {
+    match linktype {
+        LINKTYPE_USB_LINUX | LINKTYPE_USB_LINUX_MMAPPED => {
+            let header_len = if linktype == LINKTYPE_USB_LINUX {
+                48
+            } else {
+                64
+            };
+            let fields = Fields { data: packet, le };
+            let (event, transfer, endpoint, device) = (
+                packet.get(8)?,
+                packet.get(9)?,
+                packet.get(10)?,
+                packet.get(11)?,
+            );
+            // Only submissions of OUT transfers, and no isochronous
+            if *event != b'S' || endpoint & 0x80 != 0 || *transfer == 0 {
+                return None;
+            }
+            let bus = fields.u16(12)?;
+            let data = packet.get(header_len..)?;
+            (!data.is_empty()).then(|| (format!("{bus}:{device:03}"), data.to_vec()))
+        }
+        LINKTYPE_USBPCAP => {
+            // Always little endian
+            let fields = Fields {
+                data: packet,
+                le: true,
+            };
+            let header_len = fields.u16(0)? as usize;
+            let (info, endpoint, transfer) = (packet.get(16)?, packet.get(21)?, packet.get(22)?);
+            // Bit 0 of info is set for completions, from device to host
+            if info & 0x01 != 0 || endpoint & 0x80 != 0 {
+                return None;
+            }
+            let bus = fields.u16(17)?;
+            let device = fields.u16(19)?;
+            let mut data = packet.get(header_len..)?;
+            if *transfer == 2 {
+                // Control transfers have a stage, the setup stage starts with
+                // the 8 byte setup packet and the status stage has no data
+                match packet.get(27)? {
+                    0 => data = data.get(8..)?,
+                    1 => {}
+                    _ => return None,
+                }
+            }
+            (!data.is_empty()).then(|| (format!("{bus}:{device:03}"), data.to_vec()))
+        }
+        _ => None,
+    }
+}
+
+fn parse_pcap(data: &[u8]) -> Result<Vec<UsbTransfer>> {
+    let magic = &data[..4];
+    let le = magic == PCAP_MAGIC_LE || magic == PCAP_MAGIC_NS_LE;
+    let nanos = magic == PCAP_MAGIC_NS_LE || magic == PCAP_MAGIC_NS_BE;
+    let fields = Fields { data, le };
+    let linktype = fields
+        .u32(20)
+        .ok_or_else(|| truncated_error("pcap header"))?;
+
+    let mut transfers = Vec::new();
+    let mut pos = 24;
+    while pos < data.len() {
+        let (secs, frac, len) = fields
+            .u32(pos)
+            .zip(fields.u32(pos + 4))
+            .zip(fields.u32(pos + 8))
+            .map(|((s, f), l)| (s, f, l as usize))
+            .ok_or_else(|| truncated_error("pcap record"))?;
+        let start = pos + 16;
+        let packet = data
+            .get(start..start + len)
+            .ok_or_else(|| truncated_error("pcap record"))?;
+        pos = start + len;
+
+        if let Some((device, data)) = usb_transfer(linktype, packet, le) {
+            let frac = if nanos {
+                Duration::from_nanos(frac as u64)
+            } else {
+                Duration::from_micros(frac as u64)
+            };
+            transfers.push(UsbTransfer {
+                timestamp: Duration::from_secs(secs as u64) + frac,
+                device,
+                data,
+            });
+        }
+    }
+    Ok(transfers)
+}
+
+fn parse_pcapng(data: &[u8]) -> Result<Vec<UsbTransfer>> {
+    let mut transfers = Vec::new();
+    let mut le = true;
+    // Link type of each interface in the current section
+    let mut interfaces = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        if data.get(pos..pos + 4) == Some(&PCAPNG_SECTION[..]) {
+            le = match data.get(pos + 8..pos + 12) {
+                Some([0x4d, 0x3c, 0x2b, 0x1a]) => true,
+                Some([0x1a, 0x2b, 0x3c, 0x4d]) => false,
+                _ => return Err(truncated_error("pcapng section")),
+            };
+            interfaces.clear();
+        }
+        let fields = Fields { data, le };
+        let (block_type, len) = fields
+            .u32(pos)
+            .zip(fields.u32(pos + 4))
+            .ok_or_else(|| truncated_error("pcapng block"))?;
+        let len = len as usize;
+        if len < 12 {
+            return Err(truncated_error("pcapng block"));
+        }
+        let body = data
+            .get(pos + 8..pos + len - 4)
+            .ok_or_else(|| truncated_error("pcapng block"))?;
+        pos += len;
+
+        let body_fields = Fields { data: body, le };
+        let packet = match block_type {
+            // Interface description
+            1 => {
+                interfaces.push(body_fields.u16(0).unwrap_or_default() as u32);
+                continue;
+            }
+            // Enhanced packet, timestamps are assumed to be the default
+            // microseconds
+            6 => {
+                let (interface, high, low, cap_len) = (
+                    body_fields.u32(0),
+                    body_fields.u32(4),
+                    body_fields.u32(8),
+                    body_fields.u32(12),
+                );
+                let (interface, high, low, cap_len) = interface
+                    .zip(high)
+                    .zip(low)
+                    .zip(cap_len)
+                    .map(|(((i, h), l), c)| (i as usize, h as u64, l as u64, c as usize))
+                    .ok_or_else(|| truncated_error("pcapng packet"))?;
+                let packet = body
+                    .get(16..16 + cap_len)
+                    .ok_or_else(|| truncated_error("pcapng packet"))?;
+                let timestamp = Duration::from_micros(high << 32 | low);
+                interfaces
+                    .get(interface)
+                    .map(|linktype| (*linktype, timestamp, packet))
+            }
+            // Simple packet, has no timestamp and is always the first
+            // interface
+            3 => interfaces
+                .first()
+                .and_then(|linktype| Some((*linktype, Duration::ZERO, body.get(4..)?))),
+            _ => None,
+        };
+
+        if let Some((linktype, timestamp, packet)) = packet {
+            if let Some((device, data)) = usb_transfer(linktype, packet, le) {
+                transfers.push(UsbTransfer {
+                    timestamp,
+                    device,
+                    data,
+                });
+            }
+        }
+    }
+    Ok(transfers)
+}
+
+fn parse_hex(words: &[&str]) -> Option<Vec<u8>> {
+    let hex: String = words.concat();
+    if hex.len() % 2 == 1 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parse one line of usbmon text, such as
+///
+/// ```text
+/// ffff8c2a01cb6a80 1184862417 S Co:1:002:0 s 21 09 035d 0002 0040 64 = 5db30000 ff000000
+/// ```
+fn parse_usbmon_line(line: &str) -> Option<UsbTransfer> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (timestamp, event, address) = (words.get(1)?, words.get(2)?, words.get(3)?);
+    if *event != "S" {
+        return None;
+    }
+    // Such as "Co:1:002:0", or "Co:002:0" for older kernels
+    let address: Vec<&str> = address.split(':').collect();
+    if !matches!(address.first(), Some(&"Co" | &"Io" | &"Bo")) {
+        return None;
+    }
+    let device = match address.as_slice() {
+        [_, bus, device, _] => format!("{bus}:{device}"),
+        [_, device, _] => (*device).to_owned(),
+        _ => return None,
+    };
+    let start = words.iter().position(|w| *w == "=")? + 1;
+    Some(UsbTransfer {
+        timestamp: Duration::from_micros(timestamp.parse().ok()?),
+        device,
+        data: parse_hex(&words[start..])?,
+    })
+}
+
+/// Lines of either usbmon text or an asusd capture, lines that are neither
+/// are skipped
+fn parse_text(text: &str) -> Vec<UsbTransfer> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| match CaptureRecord::parse(line) {
+            Ok(record) if record.kind != CaptureKind::Sysfs => Some(UsbTransfer {
+                timestamp: record.timestamp,
+                device: record.target,
+                data: record.data,
+            }),
+            Ok(_) => None,
+            Err(_) => parse_usbmon_line(line),
+        })
+        .collect()
+}
+
+/// Read all transfers to devices from a capture file, the format is detected
+/// from the content
+pub fn read_usb_capture(path: &Path) -> Result<Vec<UsbTransfer>> {
+    let data =
+        fs::read(path).map_err(|e| PlatformError::Read(path.to_string_lossy().to_string(), e))?;
+    match data.get(..4) {
+        Some(magic)
+            if [
+                PCAP_MAGIC_LE,
+                PCAP_MAGIC_BE,
+                PCAP_MAGIC_NS_LE,
+                PCAP_MAGIC_NS_BE,
+            ]
+            .iter()
+            .any(|m| m == magic) =>
+        {
+            parse_pcap(&data)
+        }
+        Some(magic) if magic == PCAPNG_SECTION => parse_pcapng(&data),
+        _ => Ok(parse_text(&String::from_utf8_lossy(&data))),
+    }
+}
+
+/// The meaning of a packet
+#[derive(Debug, Clone)]
+pub enum Decoded {
+    /// `0x5d 0xb3`
+    Effect(AuraEffect),
+    /// `0x5d 0xbc` with per-key colours of one group of keys
+    PerKey {
+        group: u8,
+        colours: Vec<(LedCode, Colour)>,
+    },
+    /// `0x5d 0xbc` with zone colours
+    Zoned(Vec<(LedCode, Colour)>),
+    /// `0x5d 0xbd`
+    Power(AuraPower),
+    /// `0x5e 0xc0 0x02`, a pane of an AniMe frame that is not complete yet
+    AnimePane(usize),
+    /// The last pane of an AniMe frame completes the frame
+    AnimeFrame(AnimeDataBuffer),
+    /// Known by the report ID and command but the data is not decoded
+    Known(&'static str),
+    Unknown,
+}
+
+impl Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colour = |c: &Colour| format!("{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+        let colours = |colours: &[(LedCode, Colour)]| {
+            let lit: Vec<String> = colours
+                .iter()
+                .filter(|(_, c)| c.r != 0 || c.g != 0 || c.b != 0)
+                .map(|(code, c)| format!("{code:?}={}", colour(c)))
+                .collect();
+            if lit.is_empty() {
+                "all off".to_owned()
+            } else {
+                lit.join(" ")
+            }
+        };
+        match self {
+            Self::Effect(effect) => write!(
+                f,
+                "aura: builtin mode {} zone {:?} colour1 {} colour2 {} speed {:?} direction {:?}",
+                effect.mode_name(),
+                effect.zone,
+                colour(&effect.colour1),
+                colour(&effect.colour2),
+                effect.speed,
+                effect.direction
+            ),
+            Self::PerKey { group, colours: c } => {
+                write!(f, "aura: per-key group {group}: {}", colours(c))
+            }
+            Self::Zoned(c) => write!(f, "aura: zones: {}", colours(c)),
+            Self::Power(power) => {
+                let state = |s: &KbAuraPowerState| {
+                    let mut on = Vec::new();
+                    for (set, name) in [
+                        (s.boot, "boot"),
+                        (s.awake, "awake"),
+                        (s.sleep, "sleep"),
+                        (s.shutdown, "shutdown"),
+                    ] {
+                        if set {
+                            on.push(name);
+                        }
+                    }
+                    on.join(",")
+                };
+                write!(
+                    f,
+                    "aura: power keyboard [{}] logo [{}] lightbar [{}] lid [{}] rear glow [{}]",
+                    state(&power.keyboard),
+                    state(&power.logo),
+                    state(&power.lightbar),
+                    state(&power.lid),
+                    state(&power.rear_glow)
+                )
+            }
+            Self::AnimePane(index) => write!(f, "anime: pane {index}"),
+            Self::AnimeFrame(frame) => write!(
+                f,
+                "anime: frame complete, {} of {} LEDs lit",
+                frame.data().iter().filter(|b| **b != 0).count(),
+                frame.data().len()
+            ),
+            Self::Known(name) => write!(f, "{name}"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A decoded packet
+#[derive(Debug, Clone)]
+pub struct DecodedPacket {
+    pub decoded: Decoded,
+    /// Bytes that are set but have no known meaning, or have an unknown
+    /// value, as `(offset, value)`
+    pub unknown: Vec<(usize, u8)>,
+    /// The packet is shorter than the device expects, such as with usbmon
+    /// text. Missing bytes are decoded as zero.
+    pub truncated: bool,
+}
+
+/// Decodes packets in order. AniMe frames are split over several packets so
+/// are assembled from the panes seen.
+pub struct Decoder {
+    anime: AnimeType,
+    panes: Vec<Option<Vec<u8>>>,
+}
+
+impl Decoder {
+    pub fn new(anime: AnimeType) -> Self {
+        let panes = match anime {
+            AnimeType::GA401 => vec![None; 2],
+            _ => vec![None; 3],
+        };
+        Self { anime, panes }
+    }
+
+    pub fn decode(&mut self, data: &[u8]) -> DecodedPacket {
+        // Aura packets are decoded as if zero padded, which also makes
+        // reading them easier
+        let mut aura = [0u8; 64];
+        let len = data.len().min(aura.len());
+        aura[..len].copy_from_slice(&data[..len]);
+        let shorter = |len: usize| data.len() < len;
+
+        let mut known = vec![false; data.len()];
+        let mut unknown = Vec::new();
+        let mut set_known = |range: std::ops::Range<usize>| {
+            for k in known.iter_mut().take(range.end).skip(range.start) {
+                *k = true;
+            }
+        };
+
+        let (decoded, truncated) = match data {
+            [0x5d, 0xb3, ..] => {
+                set_known(0..13);
+                let mut effect = AuraEffect {
+                    mode: AuraModeNum::from(aura[3]),
+                    colour1: Colour::from(&[aura[4], aura[5], aura[6]]),
+                    colour2: Colour::from(&[aura[10], aura[11], aura[12]]),
+                    ..Default::default()
+                };
+                if effect.mode as u8 != aura[3] {
+                    unknown.push((3, aura[3]));
+                }
+                match aura_zone(aura[2]) {
+                    Some(zone) => effect.zone = zone,
+                    None => unknown.push((2, aura[2])),
+                }
+                match speed(aura[7]) {
+                    Some(speed) => effect.speed = speed,
+                    // Zero is sent for modes without a speed
+                    None if aura[7] == 0 => {}
+                    None => unknown.push((7, aura[7])),
+                }
+                match direction(aura[8]) {
+                    Some(direction) => effect.direction = direction,
+                    None => unknown.push((8, aura[8])),
+                }
+                if aura[9] != 0 {
+                    unknown.push((9, aura[9]));
+                }
+                (Decoded::Effect(effect), shorter(13))
+            }
+            [0x5d, 0xbc, 0x00, 0x00, ..] => {
+                set_known(0..2);
+                (Decoded::Known("aura: direct/per-key init"), false)
+            }
+            [0x5d, 0xbc, 0x00, ..] => {
+                set_known(0..9);
+                let group = aura[6] >> 4;
+                let colours = led_colours(&aura, false, group as usize, &mut set_known);
+                (Decoded::PerKey { group, colours }, shorter(64))
+            }
+            [0x5d, 0xbc, 0x01, ..] => {
+                set_known(0..5);
+                let colours = led_colours(&aura, true, 0, &mut set_known);
+                (Decoded::Zoned(colours), shorter(64))
+            }
+            [0x5d, 0xbd, 0x01, ..] => {
+                set_known(0..7);
+                let bytes = [aura[3], aura[4], aura[5], aura[6]];
+                let power = AuraPower::from_bytes(bytes);
+                for (i, (byte, known)) in bytes.iter().zip(power.to_bytes()).enumerate() {
+                    if byte & !known != 0 {
+                        unknown.push((i + 3, byte & !known));
+                    }
+                }
+                (Decoded::Power(power), shorter(7))
+            }
+            [0x5e, 0xc0, 0x02, ..] => match pane_from_packet(data) {
+                Some((index, pane)) if index < self.panes.len() => {
+                    set_known(0..ANIME_PACKET_LEN);
+                    self.panes[index] = Some(pane.to_vec());
+                    (
+                        self.anime_frame().unwrap_or(Decoded::AnimePane(index)),
+                        false,
+                    )
+                }
+                _ => (
+                    Decoded::Known("anime: pane data"),
+                    shorter(ANIME_PACKET_LEN),
+                ),
+            },
+            _ => match describe_packet(data) {
+                "unknown" => (Decoded::Unknown, false),
+                name => (Decoded::Known(name), false),
+            },
+        };
+
+        // Everything set but not understood in decoded packets is flagged
+        if !matches!(decoded, Decoded::Known(_) | Decoded::Unknown) {
+            for (i, (byte, known)) in data.iter().zip(known).enumerate() {
+                if !known && *byte != 0 {
+                    unknown.push((i, *byte));
+                }
+            }
+            unknown.sort_unstable();
+        }
+
+        DecodedPacket {
+            decoded,
+            unknown,
+            truncated,
+        }
+    }
+
+    /// Take the frame if all panes are set
+    fn anime_frame(&mut self) -> Option<Decoded> {
+        if self.panes.iter().any(Option::is_none) {
+            return None;
+        }
+        let data: Vec<u8> = self
+            .panes
+            .iter_mut()
+            .filter_map(Option::take)
+            .flatten()
+            .collect();
+        AnimeDataBuffer::from_vec(self.anime, data)
+            .ok()
+            .map(Decoded::AnimeFrame)
+    }
+}
+
+/// Colours of every `LedCode` in a per-key or zoned packet, the colour bytes
+/// are marked as known
+fn led_colours(
+    packet: &[u8; 64],
+    zoned: bool,
+    row: usize,
+    set_known: &mut impl FnMut(std::ops::Range<usize>),
+) -> Vec<(LedCode, Colour)> {
+    let mut colours = Vec::new();
+    for col in 0..packet.len() - 2 {
+        if let Some(code) = LedUsbPackets::led_code_at(zoned, row, col) {
+            set_known(col..col + 3);
+            colours.push((
+                code,
+                Colour::from(&[packet[col], packet[col + 1], packet[col + 2]]),
+            ));
+        }
+    }
+    colours
+}
+
+fn aura_zone(byte: u8) -> Option<AuraZone> {
+    Some(match byte {
+        0 => AuraZone::None,
+        1 => AuraZone::Key1,
+        2 => AuraZone::Key2,
+        3 => AuraZone::Key3,
+        4 => AuraZone::Key4,
+        5 => AuraZone::Logo,
+        6 => AuraZone::BarLeft,
+        7 => AuraZone::BarRight,
+        _ => return None,
+    })
+}
+
+fn speed(byte: u8) -> Option<Speed> {
+    [Speed::Low, Speed::Med, Speed::High]
+        .into_iter()
+        .find(|s| *s as u8 == byte)
+}
+
+fn direction(byte: u8) -> Option<Direction> {
+    [
+        Direction::Right,
+        Direction::Left,
+        Direction::Up,
+        Direction::Down,
+    ]
+    .into_iter()
+    .find(|d| *d as u8 == byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use rog_anime::{AnimeDataBuffer, AnimePacketType, AnimeType};
+    use rog_aura::advanced::{LedCode, LedUsbPackets, UsbPackets};
+    use rog_aura::{AuraEffect, AuraModeNum, Colour, Speed};
+
+    use super::{parse_pcap, parse_usbmon_line, Decoded, Decoder, LINKTYPE_USB_LINUX};
+
+    #[test]
+    fn decode_aura_and_anime() {
+        let mut decoder = Decoder::new(AnimeType::GA401);
+
+        let effect = AuraEffect {
+            mode: AuraModeNum::Breathe,
+            speed: Speed::High,
+            ..Default::default()
+        };
+        let mut packet: Vec<u8> = (&effect).into();
+        packet[14] = 0x42;
+        let decoded = decoder.decode(&packet);
+        match decoded.decoded {
+            Decoded::Effect(e) => {
+                assert_eq!(e.mode, AuraModeNum::Breathe);
+                assert_eq!(e.speed, Speed::High);
+                assert_eq!(e.colour1, effect.colour1);
+            }
+            d => panic!("wrong decode {d:?}"),
+        }
+        assert_eq!(decoded.unknown, [(14, 0x42)]);
+        assert!(!decoded.truncated);
+
+        let mut per_key = LedUsbPackets::new_per_key();
+        per_key.set(LedCode::D, 0xff, 0x00, 0x80);
+        let mut packets: UsbPackets = per_key.into();
+        packets[5][58] = 0x07;
+        let decoded = decoder.decode(&packets[5]);
+        match decoded.decoded {
+            Decoded::PerKey { group, colours } => {
+                assert_eq!(group, 5);
+                assert!(colours.contains(&(
+                    LedCode::D,
+                    Colour {
+                        r: 0xff,
+                        g: 0,
+                        b: 0x80
+                    }
+                )));
+            }
+            d => panic!("wrong decode {d:?}"),
+        }
+        assert_eq!(decoded.unknown, [(58, 0x07)]);
+
+        let power = [0x5d, 0xbd, 0x01, 0xff, 0x1f, 0x0f, 0x0f];
+        let decoded = decoder.decode(&power);
+        match decoded.decoded {
+            Decoded::Power(p) => assert!(p.keyboard.awake && p.lightbar.sleep && p.lid.boot),
+            d => panic!("wrong decode {d:?}"),
+        }
+        // Bit 0 of the lightbar byte is not used
+        assert_eq!(decoded.unknown, [(4, 0x01)]);
+        assert!(!decoded.truncated);
+
+        let mut frame = AnimeDataBuffer::new(AnimeType::GA401);
+        frame.data_mut()[10] = 0xff;
+        let packets = AnimePacketType::try_from(frame).unwrap();
+        assert!(matches!(
+            decoder.decode(&packets[0]).decoded,
+            Decoded::AnimePane(0)
+        ));
+        match decoder.decode(&packets[1]).decoded {
+            Decoded::AnimeFrame(f) => assert_eq!(f.data()[10], 0xff),
+            d => panic!("wrong decode {d:?}"),
+        }
+
+        assert!(matches!(
+            decoder.decode(&[0x01, 0x02]).decoded,
+            Decoded::Unknown
+        ));
+    }
+
+    #[test]
+    fn import_usbmon() {
+        let line = "ffff8c2a01cb6a80 1184862417 S Co:1:002:0 s 21 09 035d 0002 0040 64 = 5db30001 \
+                    ff000000";
+        let transfer = parse_usbmon_line(line).unwrap();
+        assert_eq!(transfer.device, "1:002");
+        assert_eq!(transfer.data, [0x5d, 0xb3, 0x00, 0x01, 0xff, 0, 0, 0]);
+        // Completions and IN transfers are skipped
+        assert!(parse_usbmon_line("ffff 1184862417 C Co:1:002:0 0 64 >").is_none());
+        assert!(parse_usbmon_line("ffff 1184862417 S Ii:1:002:1 -115:8 8 <").is_none());
+
+        let mut pcap = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+        pcap.extend([0; 12]);
+        pcap.extend(LINKTYPE_USB_LINUX.to_le_bytes());
+        let mut packet = vec![0u8; 48];
+        packet[8] = b'S';
+        packet[9] = 2;
+        packet[11] = 3;
+        packet[12] = 1;
+        packet.extend([0x5d, 0xb3]);
+        pcap.extend(10u32.to_le_bytes());
+        pcap.extend(20u32.to_le_bytes());
+        pcap.extend((packet.len() as u32).to_le_bytes());
+        pcap.extend((packet.len() as u32).to_le_bytes());
+        pcap.extend(&packet);
+        let transfers = parse_pcap(&pcap).unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].device, "1:003");
+        assert_eq!(transfers[0].data, [0x5d, 0xb3]);
+        assert_eq!(transfers[0].timestamp.as_micros(), 10_000_020);
+    }
+}
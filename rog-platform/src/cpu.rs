@@ -0,0 +1,255 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use typeshare::typeshare;
+use zbus::zvariant::Type;
+
+use crate::capture;
+use crate::error::{PlatformError, Result};
+
+const CPUFREQ_PATH: &str = "/sys/devices/system/cpu/cpufreq";
+const INTEL_NO_TURBO: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+
+const GOVERNOR: &str = "scaling_governor";
+const GOVERNORS_AVAILABLE: &str = "scaling_available_governors";
+const EPP: &str = "energy_performance_preference";
+const EPP_AVAILABLE: &str = "energy_performance_available_preferences";
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[zvariant(signature = "s")]
+pub enum CpuBoost {
+    /// Leave boost as it is
+    #[default]
+    Unchanged,
+    Enabled,
+    Disabled,
+}
+
+/// The cpufreq settings to apply to every policy. Empty strings leave that
+/// setting unchanged.
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct CpuPolicy {
+    pub governor: String,
+    pub energy_performance_preference: String,
+    pub boost: CpuBoost,
+}
+
+/// Where boost is toggled, differs between cpufreq drivers
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum BoostPath {
+    /// `cpufreq/boost` as used by `acpi-cpufreq` and `amd-pstate`, 1 is on
+    Boost(PathBuf),
+    /// `intel_pstate/no_turbo`, 1 is off
+    NoTurbo(PathBuf),
+}
+
+/// Control of the `cpufreq` governor, EPP and boost. Settings are applied to
+/// all `policy*` directories under `/sys/devices/system/cpu/cpufreq`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CpuControl {
+    policies: Vec<PathBuf>,
+    boost: Option<BoostPath>,
+}
+
+fn read_trimmed(path: &Path) -> Result<String> {
+    fs::read_to_string(path)
+        .map(|s| s.trim().to_owned())
+        .map_err(|e| PlatformError::Read(path.to_string_lossy().to_string(), e))
+}
+
+fn write_value(path: &Path, value: &str) -> Result<()> {
+    capture::write_file(path, value)
+        .map_err(|e| PlatformError::Write(path.to_string_lossy().to_string(), e))
+}
+
+impl CpuControl {
+    pub fn new() -> Result<Self> {
+        Self::from_paths(Path::new(CPUFREQ_PATH), Path::new(INTEL_NO_TURBO))
+    }
+
+    fn from_paths(cpufreq: &Path, no_turbo: &Path) -> Result<Self> {
+        let mut policies: Vec<PathBuf> = fs::read_dir(cpufreq)
+            .map_err(|e| PlatformError::Path(cpufreq.to_string_lossy().to_string(), e))?
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with("policy"))
+            .map(|e| e.path())
+            .collect();
+        if policies.is_empty() {
+            return Err(PlatformError::MissingFunction(
+                "cpufreq policies not found".to_owned(),
+            ));
+        }
+        policies.sort();
+
+        let boost = if cpufreq.join("boost").exists() {
+            Some(BoostPath::Boost(cpufreq.join("boost")))
+        } else if no_turbo.exists() {
+            Some(BoostPath::NoTurbo(no_turbo.to_path_buf()))
+        } else {
+            None
+        };
+        info!("CpuControl: found {} cpufreq policies", policies.len());
+
+        Ok(Self { policies, boost })
+    }
+
+    fn read_first(&self, attr: &str) -> Result<String> {
+        read_trimmed(&self.policies[0].join(attr))
+    }
+
+    fn read_available(&self, attr: &str) -> Vec<String> {
+        self.read_first(attr)
+            .map(|s| s.split_whitespace().map(|s| s.to_owned()).collect())
+            .unwrap_or_default()
+    }
+
+    fn write_all(&self, attr: &str, value: &str) -> Result<()> {
+        for policy in &self.policies {
+            write_value(&policy.join(attr), value)?;
+        }
+        Ok(())
+    }
+
+    pub fn available_governors(&self) -> Vec<String> {
+        self.read_available(GOVERNORS_AVAILABLE)
+    }
+
+    /// Empty if the cpufreq driver has no EPP support
+    pub fn available_energy_performance_preferences(&self) -> Vec<String> {
+        self.read_available(EPP_AVAILABLE)
+    }
+
+    pub fn has_boost(&self) -> bool {
+        self.boost.is_some()
+    }
+
+    /// The current settings, read from the first policy
+    pub fn get_policy(&self) -> Result<CpuPolicy> {
+        let boost = match &self.boost {
+            Some(BoostPath::Boost(path)) => match read_trimmed(path)?.as_str() {
+                "0" => CpuBoost::Disabled,
+                _ => CpuBoost::Enabled,
+            },
+            Some(BoostPath::NoTurbo(path)) => match read_trimmed(path)?.as_str() {
+                "0" => CpuBoost::Enabled,
+                _ => CpuBoost::Disabled,
+            },
+            None => CpuBoost::Unchanged,
+        };
+        Ok(CpuPolicy {
+            governor: self.read_first(GOVERNOR)?,
+            energy_performance_preference: self.read_first(EPP).unwrap_or_default(),
+            boost,
+        })
+    }
+
+    /// Check each setting in the policy is available on this machine
+    pub fn validate(&self, policy: &CpuPolicy) -> Result<()> {
+        if !policy.governor.is_empty() && !self.available_governors().contains(&policy.governor) {
+            return Err(PlatformError::InvalidValue(
+                GOVERNOR.to_owned(),
+                policy.governor.clone(),
+            ));
+        }
+        if !policy.energy_performance_preference.is_empty()
+            && !self
+                .available_energy_performance_preferences()
+                .contains(&policy.energy_performance_preference)
+        {
+            return Err(PlatformError::InvalidValue(
+                EPP.to_owned(),
+                policy.energy_performance_preference.clone(),
+            ));
+        }
+        if policy.boost != CpuBoost::Unchanged && self.boost.is_none() {
+            return Err(PlatformError::MissingFunction("cpufreq boost".to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Validate then apply the policy to all cpufreq policies. The governor is
+    /// set first as some drivers only accept an EPP for certain governors.
+    pub fn set_policy(&self, policy: &CpuPolicy) -> Result<()> {
+        self.validate(policy)?;
+        if !policy.governor.is_empty() {
+            self.write_all(GOVERNOR, &policy.governor)?;
+        }
+        if !policy.energy_performance_preference.is_empty() {
+            self.write_all(EPP, &policy.energy_performance_preference)?;
+        }
+        match (&self.boost, policy.boost) {
+            (_, CpuBoost::Unchanged) | (None, _) => {}
+            (Some(BoostPath::Boost(path)), boost) => {
+                write_value(path, if boost == CpuBoost::Enabled { "1" } else { "0" })?;
+            }
+            (Some(BoostPath::NoTurbo(path)), boost) => {
+                write_value(path, if boost == CpuBoost::Enabled { "0" } else { "1" })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{CpuBoost, CpuControl, CpuPolicy};
+
+    #[test]
+    fn cpu_policy_validate_and_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        for policy in ["policy0", "policy1"] {
+            let dir = base.join(policy);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("scaling_governor"), "powersave\n").unwrap();
+            fs::write(
+                dir.join("scaling_available_governors"),
+                "performance powersave\n",
+            )
+            .unwrap();
+            fs::write(dir.join("energy_performance_preference"), "balance_power\n").unwrap();
+            fs::write(
+                dir.join("energy_performance_available_preferences"),
+                "default performance balance_performance balance_power power\n",
+            )
+            .unwrap();
+        }
+        fs::write(base.join("boost"), "1\n").unwrap();
+
+        let cpu = CpuControl::from_paths(base, &base.join("no_turbo")).unwrap();
+        assert_eq!(
+            cpu.get_policy().unwrap(),
+            CpuPolicy {
+                governor: "powersave".to_owned(),
+                energy_performance_preference: "balance_power".to_owned(),
+                boost: CpuBoost::Enabled,
+            }
+        );
+
+        let bad = CpuPolicy {
+            governor: "ondemand".to_owned(),
+            ..Default::default()
+        };
+        assert!(cpu.set_policy(&bad).is_err());
+
+        let policy = CpuPolicy {
+            governor: String::new(),
+            energy_performance_preference: "power".to_owned(),
+            boost: CpuBoost::Disabled,
+        };
+        cpu.set_policy(&policy).unwrap();
+        for p in ["policy0", "policy1"] {
+            let epp =
+                fs::read_to_string(base.join(p).join("energy_performance_preference")).unwrap();
+            assert_eq!(epp, "power");
+        }
+        assert_eq!(fs::read_to_string(base.join("boost")).unwrap(), "0");
+    }
+}
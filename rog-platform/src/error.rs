@@ -13,6 +13,8 @@ pub enum PlatformError {
     Write(String, std::io::Error),
     NotSupported,
     AttrNotFound(String),
+    /// (attribute, value)
+    InvalidValue(String, String),
     MissingFunction(String),
     MissingLedBrightNode(String, std::io::Error),
     IoPath(String, std::io::Error),
@@ -34,6 +36,9 @@ impl fmt::Display for PlatformError {
             PlatformError::Write(path, error) => write!(f, "Write {}: {}", path, error),
             PlatformError::NotSupported => write!(f, "Not supported"),
             PlatformError::AttrNotFound(deets) => write!(f, "Attribute not found: {}", deets),
+            PlatformError::InvalidValue(attr, value) => {
+                write!(f, "{} is not an available value for {}", value, attr)
+            }
             PlatformError::Io(deets) => write!(f, "std::io error: {}", deets),
             PlatformError::MissingFunction(deets) => write!(f, "Missing functionality: {}", deets),
             PlatformError::MissingLedBrightNode(path, error) => write!(
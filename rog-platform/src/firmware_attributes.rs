@@ -0,0 +1,291 @@
+//! Generic access to the kernel `firmware-attributes` class, see
+//! `Documentation/ABI/testing/sysfs-class-firmware-attributes`. Each
+//! attribute is a directory holding its `type`, `current_value` and any
+//! metadata such as `min_value` or `possible_values`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+use typeshare::typeshare;
+use zbus::zvariant::Type;
+
+use crate::capture;
+use crate::error::{PlatformError, Result};
+
+const FIRMWARE_ATTRIBUTES_PATH: &str = "/sys/class/firmware-attributes";
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[zvariant(signature = "s")]
+pub enum AttributeKind {
+    Integer,
+    Enumeration,
+    String,
+    OrderedList,
+    #[default]
+    Unknown,
+}
+
+impl From<&str> for AttributeKind {
+    fn from(s: &str) -> Self {
+        match s.trim() {
+            "integer" => Self::Integer,
+            "enumeration" => Self::Enumeration,
+            "string" => Self::String,
+            "ordered-list" => Self::OrderedList,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A snapshot of one firmware attribute. Metadata not provided by the driver
+/// is left empty or zeroed.
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone)]
+pub struct FirmwareAttribute {
+    /// The `firmware-attributes` device this belongs to
+    pub device: String,
+    pub name: String,
+    pub display_name: String,
+    pub kind: AttributeKind,
+    pub current_value: String,
+    pub default_value: String,
+    /// Only used for `AttributeKind::Integer`
+    pub min_value: i64,
+    /// Only used for `AttributeKind::Integer`
+    pub max_value: i64,
+    /// Only used for `AttributeKind::Integer`
+    pub scalar_increment: i64,
+    /// Used for `AttributeKind::Enumeration` and `AttributeKind::OrderedList`
+    pub possible_values: Vec<String>,
+    /// Only used for `AttributeKind::String`
+    pub min_length: i64,
+    /// Only used for `AttributeKind::String`
+    pub max_length: i64,
+}
+
+fn read_string(dir: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dir.join(file))
+        .ok()
+        .map(|s| s.trim_end_matches('\n').to_owned())
+}
+
+fn read_i64(dir: &Path, file: &str) -> i64 {
+    read_string(dir, file)
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+impl FirmwareAttribute {
+    fn read(device: &str, dir: &Path) -> Result<Self> {
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let kind = read_string(dir, "type")
+            .ok_or_else(|| PlatformError::AttrNotFound(format!("{name}/type")))?;
+        Ok(Self {
+            device: device.to_owned(),
+            display_name: read_string(dir, "display_name").unwrap_or_else(|| name.clone()),
+            kind: AttributeKind::from(kind.as_str()),
+            current_value: read_string(dir, "current_value").unwrap_or_default(),
+            default_value: read_string(dir, "default_value").unwrap_or_default(),
+            min_value: read_i64(dir, "min_value"),
+            max_value: read_i64(dir, "max_value"),
+            scalar_increment: read_i64(dir, "scalar_increment"),
+            possible_values: read_string(dir, "possible_values")
+                .map(|s| {
+                    s.split(';')
+                        .map(|v| v.trim().to_owned())
+                        .filter(|v| !v.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            min_length: read_i64(dir, "min_length"),
+            max_length: read_i64(dir, "max_length"),
+            name,
+        })
+    }
+
+    /// Check a value against the attribute metadata before writing it
+    pub fn validate(&self, value: &str) -> Result<()> {
+        let invalid = || PlatformError::InvalidValue(self.name.clone(), value.to_owned());
+        match self.kind {
+            AttributeKind::Integer => {
+                let v: i64 = value.trim().parse().map_err(|_| invalid())?;
+                if v < self.min_value || (self.max_value > self.min_value && v > self.max_value) {
+                    return Err(invalid());
+                }
+                if self.scalar_increment > 1 && (v - self.min_value) % self.scalar_increment != 0 {
+                    return Err(invalid());
+                }
+            }
+            AttributeKind::Enumeration => {
+                if !self.possible_values.iter().any(|v| v == value) {
+                    return Err(invalid());
+                }
+            }
+            AttributeKind::OrderedList => {
+                if !value
+                    .split(';')
+                    .all(|item| self.possible_values.iter().any(|v| v == item))
+                {
+                    return Err(invalid());
+                }
+            }
+            AttributeKind::String => {
+                let len = value.chars().count() as i64;
+                if len < self.min_length || (self.max_length > 0 && len > self.max_length) {
+                    return Err(invalid());
+                }
+            }
+            AttributeKind::Unknown => {}
+        }
+        Ok(())
+    }
+}
+
+/// Enumerates `/sys/class/firmware-attributes/*/attributes/*`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FirmwareAttributes {
+    base: PathBuf,
+}
+
+impl Default for FirmwareAttributes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FirmwareAttributes {
+    pub fn new() -> Self {
+        Self {
+            base: PathBuf::from(FIRMWARE_ATTRIBUTES_PATH),
+        }
+    }
+
+    /// Attribute directories with the name of their device
+    fn attribute_dirs(&self) -> Vec<(String, PathBuf)> {
+        let mut dirs = Vec::new();
+        for device in fs::read_dir(&self.base).into_iter().flatten().flatten() {
+            let dev_name = device.file_name().to_string_lossy().to_string();
+            for attr in fs::read_dir(device.path().join("attributes"))
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                // Skips files such as `pending_reboot`
+                if attr.path().is_dir() {
+                    dirs.push((dev_name.clone(), attr.path()));
+                }
+            }
+        }
+        dirs.sort();
+        dirs
+    }
+
+    /// Attribute names are only unique within a device
+    fn find(&self, device: &str, name: &str) -> Result<PathBuf> {
+        self.attribute_dirs()
+            .into_iter()
+            .find(|(dev, path)| {
+                dev == device && path.file_name().map(|n| n == name).unwrap_or(false)
+            })
+            .map(|(_, path)| path)
+            .ok_or_else(|| PlatformError::AttrNotFound(format!("{device}/{name}")))
+    }
+
+    /// All attributes of all devices. Attributes that can't be read are
+    /// skipped.
+    pub fn list(&self) -> Vec<FirmwareAttribute> {
+        self.attribute_dirs()
+            .iter()
+            .filter_map(|(device, path)| FirmwareAttribute::read(device, path).ok())
+            .collect()
+    }
+
+    pub fn get(&self, device: &str, name: &str) -> Result<FirmwareAttribute> {
+        let path = self.find(device, name)?;
+        FirmwareAttribute::read(device, &path)
+    }
+
+    /// Validate then write the `current_value` of an attribute
+    pub fn set(&self, device: &str, name: &str, value: &str) -> Result<()> {
+        let path = self.find(device, name)?;
+        FirmwareAttribute::read(device, &path)?.validate(value)?;
+        let path = path.join("current_value");
+        capture::write_file(&path, value)
+            .map_err(|e| PlatformError::Write(path.to_string_lossy().into(), e))
+    }
+
+    pub fn monitor(&self, device: &str, name: &str) -> Result<inotify::Inotify> {
+        let path = self.find(device, name)?;
+        let path = path.join("current_value");
+        let inotify = inotify::Inotify::init()?;
+        inotify
+            .watches()
+            .add(&path, inotify::WatchMask::MODIFY)
+            .map_err(|e| PlatformError::IoPath(path.to_string_lossy().into(), e))?;
+        Ok(inotify)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{AttributeKind, FirmwareAttributes};
+
+    #[test]
+    fn firmware_attributes_list_and_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let attrs = dir.path().join("asus-armoury/attributes");
+        let ppt = attrs.join("ppt_pl1_spl");
+        fs::create_dir_all(&ppt).unwrap();
+        fs::write(ppt.join("type"), "integer\n").unwrap();
+        fs::write(ppt.join("current_value"), "45\n").unwrap();
+        fs::write(ppt.join("min_value"), "5\n").unwrap();
+        fs::write(ppt.join("max_value"), "80\n").unwrap();
+        fs::write(ppt.join("scalar_increment"), "1\n").unwrap();
+        let od = attrs.join("panel_overdrive");
+        fs::create_dir_all(&od).unwrap();
+        fs::write(od.join("type"), "enumeration\n").unwrap();
+        fs::write(od.join("current_value"), "0\n").unwrap();
+        fs::write(od.join("possible_values"), "0;1\n").unwrap();
+        fs::write(attrs.join("pending_reboot"), "0\n").unwrap();
+        // The same name on another device is a different attribute
+        let other = dir.path().join("other-wmi/attributes/ppt_pl1_spl");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("type"), "integer\n").unwrap();
+        fs::write(other.join("current_value"), "10\n").unwrap();
+
+        let fw = FirmwareAttributes {
+            base: dir.path().to_path_buf(),
+        };
+        let list = fw.list();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list[0].name, "panel_overdrive");
+        assert_eq!(list[0].kind, AttributeKind::Enumeration);
+        assert_eq!(list[0].possible_values, ["0", "1"]);
+        assert_eq!(list[1].device, "asus-armoury");
+        assert_eq!((list[1].min_value, list[1].max_value), (5, 80));
+        assert_eq!(list[1].current_value, "45");
+
+        assert_eq!(list[2].device, "other-wmi");
+
+        assert!(fw.set("asus-armoury", "ppt_pl1_spl", "90").is_err());
+        assert!(fw.set("asus-armoury", "panel_overdrive", "2").is_err());
+        assert!(fw.set("asus-armoury", "missing", "1").is_err());
+        assert!(fw.set("missing", "ppt_pl1_spl", "60").is_err());
+        fw.set("asus-armoury", "ppt_pl1_spl", "60").unwrap();
+        assert_eq!(
+            fw.get("asus-armoury", "ppt_pl1_spl").unwrap().current_value,
+            "60"
+        );
+        assert_eq!(
+            fw.get("other-wmi", "ppt_pl1_spl").unwrap().current_value,
+            "10"
+        );
+    }
+}
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 
 use log::{info, warn};
 
+use crate::capture::{self, CaptureKind};
 use crate::error::{PlatformError, Result};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
@@ -59,7 +60,48 @@ impl HidRaw {
         )))
     }
 
+    /// All hidraw nodes of USB devices from `id_vendor`, with the product ID
+    /// of each. Only the first node of each product is listed.
+    pub fn find_vendor(id_vendor: &str) -> Result<Vec<(String, Self)>> {
+        let mut enumerator = udev::Enumerator::new().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("enumerator failed".into(), err)
+        })?;
+
+        enumerator.match_subsystem("hidraw").map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("match_subsystem failed".into(), err)
+        })?;
+
+        let mut found: Vec<(String, Self)> = Vec::new();
+        for device in enumerator
+            .scan_devices()
+            .map_err(|e| PlatformError::IoPath("enumerator".to_owned(), e))?
+        {
+            let parent = match device.parent_with_subsystem_devtype("usb", "usb_device") {
+                Ok(Some(parent)) => parent,
+                _ => continue,
+            };
+            if parent.attribute_value("idVendor") != Some(id_vendor.as_ref()) {
+                continue;
+            }
+            let product = parent
+                .attribute_value("idProduct")
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if let Some(dev_node) = device.devnode() {
+                if !found.iter().any(|(p, _)| *p == product) {
+                    found.push((product, Self(dev_node.to_owned())));
+                }
+            }
+        }
+        Ok(found)
+    }
+
     pub fn write_bytes(&self, message: &[u8]) -> Result<()> {
+        if !capture::record(CaptureKind::HidRaw, &self.0.to_string_lossy(), message) {
+            return Ok(());
+        }
         let mut file = OpenOptions::new()
             .write(true)
             .open(&self.0)
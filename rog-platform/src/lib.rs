@@ -1,12 +1,17 @@
 //! This crate functions as a wrapper of all the relevant ASUS functionality
 //! on ROG, Strix, and TUF laptops.
 
+pub mod capture;
+pub mod capture_decode;
+pub mod cpu;
 pub mod error;
+pub mod firmware_attributes;
 pub mod hid_raw;
 pub mod keyboard_led;
 pub(crate) mod macros;
 pub mod platform;
 pub mod power;
+pub mod quirks;
 pub mod supported;
 pub mod usb_raw;
 
@@ -42,12 +47,20 @@ pub fn read_attr_bool(device: &Device, attr_name: &str) -> Result<bool> {
     Err(PlatformError::AttrNotFound(attr_name.to_owned()))
 }
 
-pub fn write_attr_bool(device: &mut Device, attr: &str, value: bool) -> Result<()> {
+/// All attribute writes go through here so that they can be captured
+fn set_attr(device: &mut Device, attr: &str, value: &str) -> Result<()> {
+    if !capture::record_attr(device, attr, value) {
+        return Ok(());
+    }
     device
-        .set_attribute_value(attr, &(value as u8).to_string())
+        .set_attribute_value(attr, value)
         .map_err(|e| PlatformError::IoPath(attr.into(), e))
 }
 
+pub fn write_attr_bool(device: &mut Device, attr: &str, value: bool) -> Result<()> {
+    set_attr(device, attr, &(value as u8).to_string())
+}
+
 pub fn read_attr_u8(device: &Device, attr_name: &str) -> Result<u8> {
     if let Some(value) = device.attribute_value(attr_name) {
         let tmp = value.to_string_lossy();
@@ -57,9 +70,7 @@ pub fn read_attr_u8(device: &Device, attr_name: &str) -> Result<u8> {
 }
 
 pub fn write_attr_u8(device: &mut Device, attr: &str, value: u8) -> Result<()> {
-    device
-        .set_attribute_value(attr, &(value).to_string())
-        .map_err(|e| PlatformError::IoPath(attr.into(), e))
+    set_attr(device, attr, &(value).to_string())
 }
 
 pub fn read_attr_u8_array(device: &Device, attr_name: &str) -> Result<Vec<u8>> {
@@ -77,9 +88,7 @@ pub fn read_attr_u8_array(device: &Device, attr_name: &str) -> Result<Vec<u8>> {
 pub fn write_attr_u8_array(device: &mut Device, attr: &str, values: &[u8]) -> Result<()> {
     let tmp: String = values.iter().map(|v| format!("{} ", v)).collect();
     let tmp = tmp.trim();
-    device
-        .set_attribute_value(attr, tmp)
-        .map_err(|e| PlatformError::IoPath(attr.into(), e))
+    set_attr(device, attr, tmp)
 }
 
 pub fn read_attr_string(device: &Device, attr_name: &str) -> Result<String> {
@@ -92,9 +101,7 @@ pub fn read_attr_string(device: &Device, attr_name: &str) -> Result<String> {
 
 pub fn write_attr_string(device: &mut Device, attr: &str, value: &str) -> Result<()> {
     let tmp = value.trim();
-    device
-        .set_attribute_value(attr, tmp)
-        .map_err(|e| PlatformError::IoPath(attr.into(), e))
+    set_attr(device, attr, tmp)
 }
 
 #[cfg(test)]
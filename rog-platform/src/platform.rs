@@ -17,6 +17,7 @@ use crate::{attr_bool, attr_string, attr_u8, to_device};
 /// - `gpu_mux`
 /// - `keyboard_mode`, set keyboard RGB mode and speed
 /// - `keyboard_state`, set keyboard power states
+/// - `ppt_*` power limits and `nv_*` Nvidia boost, see `PlatformTunable`
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
 pub struct AsusPlatform {
     path: PathBuf,
@@ -41,6 +42,20 @@ impl AsusPlatform {
     // The acpi platform_profile support
     attr_string!("platform_profile", pp_path);
 
+    attr_u8!("ppt_pl1_spl", path);
+
+    attr_u8!("ppt_pl2_sppt", path);
+
+    attr_u8!("ppt_fppt", path);
+
+    attr_u8!("ppt_apu_sppt", path);
+
+    attr_u8!("ppt_platform_sppt", path);
+
+    attr_u8!("nv_dynamic_boost", path);
+
+    attr_u8!("nv_temp_target", path);
+
     pub fn new() -> Result<Self> {
         let mut enumerator = udev::Enumerator::new().map_err(|err| {
             warn!("{}", err);
@@ -71,6 +86,173 @@ impl AsusPlatform {
             "asus-nb-wmi not found".into(),
         ))
     }
+
+    pub fn has_tunable(&self, tunable: PlatformTunable) -> bool {
+        match tunable {
+            PlatformTunable::PptPl1Spl => self.has_ppt_pl1_spl(),
+            PlatformTunable::PptPl2Sppt => self.has_ppt_pl2_sppt(),
+            PlatformTunable::PptFppt => self.has_ppt_fppt(),
+            PlatformTunable::PptApuSppt => self.has_ppt_apu_sppt(),
+            PlatformTunable::PptPlatformSppt => self.has_ppt_platform_sppt(),
+            PlatformTunable::NvDynamicBoost => self.has_nv_dynamic_boost(),
+            PlatformTunable::NvTempTarget => self.has_nv_temp_target(),
+        }
+    }
+
+    /// All tunables the driver exposes on this laptop
+    pub fn supported_tunables(&self) -> Vec<PlatformTunable> {
+        PlatformTunable::ALL
+            .into_iter()
+            .filter(|t| self.has_tunable(*t))
+            .collect()
+    }
+
+    /// Older kernels have these attributes as write-only so reading may fail
+    pub fn get_tunable(&self, tunable: PlatformTunable) -> Result<u8> {
+        match tunable {
+            PlatformTunable::PptPl1Spl => self.get_ppt_pl1_spl(),
+            PlatformTunable::PptPl2Sppt => self.get_ppt_pl2_sppt(),
+            PlatformTunable::PptFppt => self.get_ppt_fppt(),
+            PlatformTunable::PptApuSppt => self.get_ppt_apu_sppt(),
+            PlatformTunable::PptPlatformSppt => self.get_ppt_platform_sppt(),
+            PlatformTunable::NvDynamicBoost => self.get_nv_dynamic_boost(),
+            PlatformTunable::NvTempTarget => self.get_nv_temp_target(),
+        }
+    }
+
+    /// The inclusive (min, max) range of a tunable, from the `<attr>_min` and
+    /// `<attr>_max` files where the driver exposes them, else
+    /// `PlatformTunable::range()`
+    pub fn tunable_range(&self, tunable: PlatformTunable) -> (u8, u8) {
+        let (min, max) = tunable.range();
+        let read = |suffix: &str| -> Option<u8> {
+            let path = self.path.join(format!("{}_{suffix}", tunable.attr_name()));
+            std::fs::read_to_string(path).ok()?.trim().parse().ok()
+        };
+        (read("min").unwrap_or(min), read("max").unwrap_or(max))
+    }
+
+    /// Set a tunable, the value is checked against `tunable_range()` first
+    pub fn set_tunable(&self, tunable: PlatformTunable, value: u8) -> Result<()> {
+        let (min, max) = self.tunable_range(tunable);
+        if !(min..=max).contains(&value) {
+            return Err(PlatformError::InvalidValue(
+                tunable.attr_name().to_owned(),
+                value.to_string(),
+            ));
+        }
+        match tunable {
+            PlatformTunable::PptPl1Spl => self.set_ppt_pl1_spl(value),
+            PlatformTunable::PptPl2Sppt => self.set_ppt_pl2_sppt(value),
+            PlatformTunable::PptFppt => self.set_ppt_fppt(value),
+            PlatformTunable::PptApuSppt => self.set_ppt_apu_sppt(value),
+            PlatformTunable::PptPlatformSppt => self.set_ppt_platform_sppt(value),
+            PlatformTunable::NvDynamicBoost => self.set_nv_dynamic_boost(value),
+            PlatformTunable::NvTempTarget => self.set_nv_temp_target(value),
+        }
+    }
+
+    pub fn monitor_tunable(&self, tunable: PlatformTunable) -> Result<inotify::Inotify> {
+        match tunable {
+            PlatformTunable::PptPl1Spl => self.monitor_ppt_pl1_spl(),
+            PlatformTunable::PptPl2Sppt => self.monitor_ppt_pl2_sppt(),
+            PlatformTunable::PptFppt => self.monitor_ppt_fppt(),
+            PlatformTunable::PptApuSppt => self.monitor_ppt_apu_sppt(),
+            PlatformTunable::PptPlatformSppt => self.monitor_ppt_platform_sppt(),
+            PlatformTunable::NvDynamicBoost => self.monitor_nv_dynamic_boost(),
+            PlatformTunable::NvTempTarget => self.monitor_nv_temp_target(),
+        }
+    }
+}
+
+/// The power limit and Nvidia boost attributes of `asus-wmi`
+#[typeshare]
+#[derive(
+    Serialize, Deserialize, Type, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy,
+)]
+#[zvariant(signature = "s")]
+pub enum PlatformTunable {
+    /// Sustained CPU/platform power limit (PL1/SPL), watts
+    PptPl1Spl,
+    /// Short term power limit (PL2/SPPT), watts
+    PptPl2Sppt,
+    /// Fast power limit (FPPT), watts
+    PptFppt,
+    /// APU SPPT power limit, watts
+    PptApuSppt,
+    /// Platform SPPT power limit, watts
+    PptPlatformSppt,
+    /// Nvidia dynamic boost, watts
+    NvDynamicBoost,
+    /// Nvidia GPU temperature target, degrees celsius
+    NvTempTarget,
+}
+
+impl PlatformTunable {
+    pub const ALL: [Self; 7] = [
+        Self::PptPl1Spl,
+        Self::PptPl2Sppt,
+        Self::PptFppt,
+        Self::PptApuSppt,
+        Self::PptPlatformSppt,
+        Self::NvDynamicBoost,
+        Self::NvTempTarget,
+    ];
+
+    /// The sysfs attribute name
+    pub const fn attr_name(&self) -> &'static str {
+        match self {
+            Self::PptPl1Spl => "ppt_pl1_spl",
+            Self::PptPl2Sppt => "ppt_pl2_sppt",
+            Self::PptFppt => "ppt_fppt",
+            Self::PptApuSppt => "ppt_apu_sppt",
+            Self::PptPlatformSppt => "ppt_platform_sppt",
+            Self::NvDynamicBoost => "nv_dynamic_boost",
+            Self::NvTempTarget => "nv_temp_target",
+        }
+    }
+
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::PptPl1Spl => "Sustained CPU/platform power limit (PL1/SPL), watts",
+            Self::PptPl2Sppt => "Short term power limit (PL2/SPPT), watts",
+            Self::PptFppt => "Fast power limit (FPPT), watts",
+            Self::PptApuSppt => "APU SPPT power limit, watts",
+            Self::PptPlatformSppt => "Platform SPPT power limit, watts",
+            Self::NvDynamicBoost => "Nvidia dynamic boost, watts",
+            Self::NvTempTarget => "Nvidia GPU temperature target, degrees celsius",
+        }
+    }
+
+    /// The inclusive (min, max) range the `asus-wmi` driver accepts, used
+    /// where the driver doesn't expose its own limits
+    pub const fn range(&self) -> (u8, u8) {
+        match self {
+            Self::PptPl1Spl | Self::PptPl2Sppt | Self::PptFppt => (5, 250),
+            Self::PptApuSppt | Self::PptPlatformSppt => (5, 130),
+            Self::NvDynamicBoost => (5, 25),
+            Self::NvTempTarget => (75, 87),
+        }
+    }
+}
+
+impl Display for PlatformTunable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.attr_name())
+    }
+}
+
+impl FromStr for PlatformTunable {
+    type Err = PlatformError;
+
+    /// Accepts the attribute name, such as `ppt_pl1_spl`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase().replace('-', "_");
+        Self::ALL
+            .into_iter()
+            .find(|t| t.attr_name() == s)
+            .ok_or(PlatformError::InvalidValue("tunable".to_owned(), s))
+    }
 }
 
 #[typeshare]
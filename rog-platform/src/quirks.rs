@@ -0,0 +1,258 @@
+//! Per-model facts which can't be probed reliably, such as the AniMe layout,
+//! what each fan is called, attributes the firmware exposes but which don't
+//! work, and the charge limit to start with.
+//!
+//! Entries are loaded from `/usr/share/asusd/laptop_quirks.ron` and from
+//! `/etc/asusd/laptop_quirks.ron` for local overrides. Every entry that
+//! matches the DMI `board_name` and `product_family` is applied, the least
+//! specific first, so a `product_family` entry can be refined for a single
+//! board. User entries are applied after those of the same specificity.
+
+use std::sync::OnceLock;
+
+use log::{error, info, warn};
+use rog_anime::error::AnimeError;
+use rog_anime::AnimeType;
+use rog_profiles::FanCurvePU;
+use serde_derive::{Deserialize, Serialize};
+
+pub const QUIRKS_CONF: &str = "/usr/share/asusd/laptop_quirks.ron";
+pub const QUIRKS_USER_CONF: &str = "/etc/asusd/laptop_quirks.ron";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LaptopQuirks {
+    /// Part of `/sys/class/dmi/id/board_name`, e.g `GA402R`. Empty matches
+    /// any board.
+    pub board_name: String,
+    /// Part of `/sys/class/dmi/id/product_family`, e.g `ROG Zephyrus G14`.
+    /// Empty matches any family.
+    pub product_family: String,
+    /// The AniMe Matrix layout, if the laptop has one
+    pub anime: Option<AnimeType>,
+    /// The fans which have curves, if the hwmon lists fans which don't work
+    pub fans: Option<Vec<FanCurvePU>>,
+    /// Names to show for fans, such as `MID` being the `System` fan
+    pub fan_labels: Vec<(FanCurvePU, String)>,
+    /// Attributes such as `panel_od` or `ppt_fppt` which exist but do
+    /// nothing, or misbehave, on this model
+    pub broken_attributes: Vec<String>,
+    /// The battery charge limit to use before one is configured
+    pub default_charge_limit: Option<u8>,
+}
+
+impl LaptopQuirks {
+    /// The quirks of this laptop, loaded once
+    pub fn get() -> &'static Self {
+        static QUIRKS: OnceLock<LaptopQuirks> = OnceLock::new();
+        QUIRKS.get_or_init(|| {
+            let dmi = sysfs_class::DmiId::default();
+            let board_name = dmi.board_name().unwrap_or_default();
+            let product_family = dmi.product_family().unwrap_or_default();
+            QuirksFile::load().resolve(board_name.trim(), product_family.trim())
+        })
+    }
+
+    fn matches(&self, board_name: &str, product_family: &str) -> bool {
+        board_name.contains(&self.board_name) && product_family.contains(&self.product_family)
+    }
+
+    /// Longer keys are more specific, and a board name is more specific than
+    /// any family
+    fn specificity(&self) -> (usize, usize) {
+        (self.board_name.len(), self.product_family.len())
+    }
+
+    /// Apply the values set in `other` over those of `self`
+    fn merge(&mut self, other: &Self) {
+        if other.anime.is_some() {
+            self.anime = other.anime;
+        }
+        if other.fans.is_some() {
+            self.fans = other.fans.clone();
+        }
+        for (fan, label) in &other.fan_labels {
+            self.fan_labels.retain(|(f, _)| f != fan);
+            self.fan_labels.push((*fan, label.clone()));
+        }
+        for attr in &other.broken_attributes {
+            if !self.is_broken(attr) {
+                self.broken_attributes.push(attr.clone());
+            }
+        }
+        if other.default_charge_limit.is_some() {
+            self.default_charge_limit = other.default_charge_limit;
+        }
+    }
+
+    /// If the attribute `attr` should be treated as missing
+    pub fn is_broken(&self, attr: &str) -> bool {
+        self.broken_attributes.iter().any(|a| a == attr)
+    }
+
+    /// The name the laptop maker uses for a fan, if it isn't obvious from
+    /// `CPU`, `GPU` or `MID`
+    pub fn fan_label(&self, fan: FanCurvePU) -> Option<&str> {
+        self.fan_labels
+            .iter()
+            .find(|(f, _)| *f == fan)
+            .map(|(_, label)| label.as_str())
+    }
+
+    /// Remove fans which aren't listed in `fans`, if it is set
+    pub fn filter_fans(&self, fans: Vec<FanCurvePU>) -> Vec<FanCurvePU> {
+        match &self.fans {
+            Some(working) => fans.into_iter().filter(|f| working.contains(f)).collect(),
+            None => fans,
+        }
+    }
+}
+
+/// The AniMe layout from the quirks, falling back to the board name check of
+/// `rog_anime::usb::get_anime_type()`
+pub fn get_anime_type() -> Result<AnimeType, AnimeError> {
+    match LaptopQuirks::get().anime {
+        Some(anime) => Ok(anime),
+        None => rog_anime::usb::get_anime_type(),
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuirksFile(Vec<LaptopQuirks>);
+
+impl QuirksFile {
+    pub fn get(&self) -> &[LaptopQuirks] {
+        &self.0
+    }
+
+    fn load_file(path: &str) -> Vec<LaptopQuirks> {
+        match std::fs::read_to_string(path) {
+            Ok(file) => match ron::from_str::<QuirksFile>(&file) {
+                Ok(data) => {
+                    info!("Loaded laptop quirks from {path}");
+                    data.0
+                }
+                Err(e) => {
+                    error!("Could not parse {path}: {e}");
+                    Vec::new()
+                }
+            },
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Load the system quirks from `/usr/share/asusd/laptop_quirks.ron`
+    /// followed by the user quirks from `/etc/asusd/laptop_quirks.ron`.
+    /// Missing files are treated as empty.
+    pub fn load() -> Self {
+        let mut data = Self::load_file(QUIRKS_CONF);
+        if data.is_empty() {
+            warn!("No laptop quirks loaded, does {QUIRKS_CONF} exist?");
+        }
+        data.append(&mut Self::load_file(QUIRKS_USER_CONF));
+        Self(data)
+    }
+
+    /// The entries matching this laptop, in the order they are applied
+    pub fn matching(&self, board_name: &str, product_family: &str) -> Vec<&LaptopQuirks> {
+        let mut matched: Vec<&LaptopQuirks> = self
+            .0
+            .iter()
+            .filter(|q| q.matches(board_name, product_family))
+            .collect();
+        // Stable, so user entries stay after system entries of equal length
+        matched.sort_by_key(|q| q.specificity());
+        matched
+    }
+
+    /// Combine all entries matching this laptop
+    pub fn resolve(&self, board_name: &str, product_family: &str) -> LaptopQuirks {
+        let mut quirks = LaptopQuirks {
+            board_name: board_name.to_owned(),
+            product_family: product_family.to_owned(),
+            ..Default::default()
+        };
+        for entry in self.matching(board_name, product_family) {
+            info!(
+                "Quirks: applying entry for board \"{}\" family \"{}\"",
+                entry.board_name, entry.product_family
+            );
+            quirks.merge(entry);
+        }
+        quirks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rog_anime::AnimeType;
+    use rog_profiles::FanCurvePU;
+
+    use super::{LaptopQuirks, QuirksFile};
+
+    #[test]
+    fn check_data_file_parse() {
+        let mut data = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        data.push("data/laptop_quirks.ron");
+        let buf = std::fs::read_to_string(&data).unwrap();
+        let quirks = ron::from_str::<QuirksFile>(&buf).unwrap();
+        assert!(!quirks.get().is_empty());
+        assert_eq!(
+            quirks.resolve("GA402RJ", "ROG Zephyrus G14").anime,
+            Some(AnimeType::GA402)
+        );
+    }
+
+    #[test]
+    fn resolve_most_specific_last() {
+        let quirks = QuirksFile(vec![
+            LaptopQuirks {
+                board_name: "GU604VI".to_owned(),
+                fans: Some(vec![FanCurvePU::CPU, FanCurvePU::GPU]),
+                broken_attributes: vec!["ppt_fppt".to_owned()],
+                ..Default::default()
+            },
+            LaptopQuirks {
+                product_family: "ROG Zephyrus M16".to_owned(),
+                fan_labels: vec![(FanCurvePU::MID, "System".to_owned())],
+                default_charge_limit: Some(80),
+                broken_attributes: vec!["panel_od".to_owned()],
+                ..Default::default()
+            },
+            LaptopQuirks {
+                board_name: "GU604".to_owned(),
+                anime: Some(AnimeType::GU604),
+                default_charge_limit: Some(90),
+                ..Default::default()
+            },
+            // A user override, which has the same key as a system entry
+            LaptopQuirks {
+                board_name: "GU604".to_owned(),
+                default_charge_limit: Some(60),
+                ..Default::default()
+            },
+        ]);
+
+        let resolved = quirks.resolve("GU604VI", "ROG Zephyrus M16");
+        assert_eq!(resolved.anime, Some(AnimeType::GU604));
+        assert_eq!(resolved.default_charge_limit, Some(60));
+        assert_eq!(resolved.fan_label(FanCurvePU::MID), Some("System"));
+        assert_eq!(resolved.fan_label(FanCurvePU::CPU), None);
+        assert!(resolved.is_broken("panel_od"));
+        assert!(resolved.is_broken("ppt_fppt"));
+        assert_eq!(
+            resolved.filter_fans(vec![FanCurvePU::CPU, FanCurvePU::GPU, FanCurvePU::MID]),
+            vec![FanCurvePU::CPU, FanCurvePU::GPU]
+        );
+
+        let other = quirks.resolve("GU604VY", "ROG Zephyrus M16");
+        assert!(!other.is_broken("ppt_fppt"));
+        assert_eq!(other.fans, None);
+
+        let none = quirks.resolve("GA401IV", "ROG Zephyrus G14");
+        assert_eq!(none.anime, None);
+        assert!(none.broken_attributes.is_empty());
+    }
+}
@@ -8,6 +8,8 @@ use serde_derive::{Deserialize, Serialize};
 use typeshare::typeshare;
 use zbus::zvariant::Type;
 
+use crate::platform::PlatformTunable;
+
 #[typeshare]
 #[derive(Serialize, Deserialize, Type, Debug, Default, Clone)]
 pub struct SupportedFunctions {
@@ -75,6 +77,37 @@ pub struct RogBiosSupportedFunctions {
     pub dgpu_disable: bool,
     pub egpu_enable: bool,
     pub mini_led_mode: bool,
+    /// Power limit and Nvidia boost attributes available
+    pub tunables: Vec<PlatformTunable>,
+}
+
+/// The result of one check made by asusd at startup, such as looking for a
+/// kernel module or device node
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// Short name of the function, such as "Keyboard backlight"
+    pub name: String,
+    /// What was looked for
+    pub looked_for: String,
+    /// Where it was looked for
+    pub location: String,
+    pub found: bool,
+    /// What was found, or why it is missing and what can be done about it
+    pub detail: String,
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.found { "ok" } else { "missing" };
+        writeln!(f, "[{status:>7}] {}: {}", self.name, self.looked_for)?;
+        // Lined up with the text after the status
+        writeln!(f, "{:10}in {}", "", self.location)?;
+        if !self.detail.is_empty() {
+            writeln!(f, "{:10}{}", "", self.detail)?;
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for SupportedFunctions {
@@ -129,6 +162,7 @@ impl fmt::Display for RogBiosSupportedFunctions {
         writeln!(f, "\tdGPU disable switch: {}", self.dgpu_disable)?;
         writeln!(f, "\teGPU enable switch: {}", self.egpu_enable)?;
         writeln!(f, "\tGPU MUX control: {}", self.gpu_mux)?;
+        writeln!(f, "\tPower and Nvidia tunables: {:?}", self.tunables)?;
         Ok(())
     }
 }
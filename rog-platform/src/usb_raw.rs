@@ -2,10 +2,11 @@ use std::time::Duration;
 
 use rusb::{Device, DeviceHandle};
 
+use crate::capture::{self, CaptureKind};
 use crate::error::{PlatformError, Result};
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct USBRaw(DeviceHandle<rusb::GlobalContext>);
+pub struct USBRaw(DeviceHandle<rusb::GlobalContext>, u16);
 
 impl USBRaw {
     pub fn new(id_product: u16) -> Result<Self> {
@@ -13,7 +14,7 @@ impl USBRaw {
             let device_desc = device.device_descriptor()?;
             if device_desc.vendor_id() == 0x0b05 && device_desc.product_id() == id_product {
                 let handle = Self::get_dev_handle(&device)?;
-                return Ok(Self(handle));
+                return Ok(Self(handle, id_product));
             }
         }
 
@@ -35,6 +36,10 @@ impl USBRaw {
     }
 
     pub fn write_bytes(&self, message: &[u8]) -> Result<usize> {
+        let target = format!("{:04x}:{:04x}", 0x0b05, self.1);
+        if !capture::record(CaptureKind::Usb, &target, message) {
+            return Ok(message.len());
+        }
         self.0
             .write_control(
                 0x21,  // request_type
@@ -0,0 +1,89 @@
+//! Rules used by asusd-user to switch the profile while an application runs
+
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+use typeshare::typeshare;
+#[cfg(feature = "dbus")]
+use zbus::zvariant::Type;
+
+use crate::Profile;
+
+/// A rule to switch the system state while a matching process is running
+#[typeshare]
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AppProfileRule {
+    /// Executable to match. A bare name is compared against the file name of
+    /// each process executable and its `comm`, a path must match exactly
+    pub exe: String,
+    /// The platform profile to switch to
+    pub profile: Profile,
+    /// Enable the fan curves of `profile` while the process runs
+    pub fan_curves: bool,
+    /// Enable panel overdrive while the process runs
+    pub panel_od: bool,
+}
+
+impl AppProfileRule {
+    /// Check if the rule matches a process by executable path or `comm` name
+    pub fn matches(&self, exe: Option<&Path>, comm: &str) -> bool {
+        if self.exe.contains('/') {
+            return exe.map(|e| e == Path::new(&self.exe)).unwrap_or(false);
+        }
+        if let Some(name) = exe.and_then(|e| e.file_name()) {
+            if name == self.exe.as_str() {
+                return true;
+            }
+        }
+        // comm is truncated by the kernel to 15 chars
+        comm == self.exe || (comm.len() == 15 && self.exe.starts_with(comm))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::AppProfileRule;
+    use crate::Profile;
+
+    fn rule(exe: &str) -> AppProfileRule {
+        AppProfileRule {
+            exe: exe.to_owned(),
+            profile: Profile::Performance,
+            fan_curves: false,
+            panel_od: false,
+        }
+    }
+
+    #[test]
+    fn rule_matches_exe_file_name() {
+        let rule = rule("blender");
+        assert!(rule.matches(Some(Path::new("/usr/bin/blender")), "blender"));
+        assert!(rule.matches(Some(Path::new("/opt/blender/blender")), "other"));
+        assert!(!rule.matches(Some(Path::new("/usr/bin/blender-thumbnailer")), "x"));
+        assert!(!rule.matches(Some(Path::new("/usr/bin/blend")), "blend"));
+    }
+
+    #[test]
+    fn rule_matches_path_exactly() {
+        let rule = rule("/opt/game/bin/game");
+        assert!(rule.matches(Some(Path::new("/opt/game/bin/game")), "game"));
+        assert!(!rule.matches(Some(Path::new("/usr/bin/game")), "game"));
+        // A path rule never falls back to comm
+        assert!(!rule.matches(None, "game"));
+    }
+
+    #[test]
+    fn rule_matches_comm() {
+        // The exe link can't be read for processes of other users
+        assert!(rule("steam").matches(None, "steam"));
+        assert!(!rule("steam").matches(None, "steamwebhelper"));
+
+        // comm is cut to 15 characters by the kernel
+        let rule = rule("FactoryGameSteam-Win64");
+        assert!(rule.matches(None, "FactoryGameStea"));
+        assert!(!rule.matches(None, "FactoryGame"));
+    }
+}
@@ -6,7 +6,7 @@ use udev::Device;
 use zbus::zvariant::Type;
 
 use crate::error::ProfileError;
-use crate::FanCurvePU;
+use crate::{set_attr, FanCurvePU};
 
 fn set_sysfs_name(string: &mut [u8], fan: char, index: usize) {
     string[3] = fan as u8;
@@ -33,7 +33,7 @@ pub(crate) fn temp_str(fan: char, index: usize) -> String {
 
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type))]
-#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
 pub struct CurveData {
     pub fan: FanCurvePU,
     pub pwm: [u8; 8],
@@ -173,17 +173,17 @@ impl CurveData {
         for (index, out) in self.pwm.iter().enumerate() {
             let pwm = pwm_str(pwm_num, index);
             trace!("writing {pwm}");
-            device.set_attribute_value(&pwm, &out.to_string())?;
+            set_attr(device, &pwm, &out.to_string())?;
         }
 
         for (index, out) in self.temp.iter().enumerate() {
             let temp = temp_str(pwm_num, index);
             trace!("writing {temp}");
-            device.set_attribute_value(&temp, &out.to_string())?;
+            set_attr(device, &temp, &out.to_string())?;
         }
 
         // Enable must be done *after* all points are written
-        device.set_attribute_value(format!("pwm{pwm_num}_enable"), enable)
+        set_attr(device, &format!("pwm{pwm_num}_enable"), enable)
     }
 }
 
@@ -1,14 +1,21 @@
+pub mod app_profile;
 pub mod error;
 pub mod fan_curve_set;
+pub mod thermal_guard;
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
 
 use error::ProfileError;
 use fan_curve_set::CurveData;
 use log::debug;
+use serde::de::{self, EnumAccess, VariantAccess, Visitor};
+use serde::{Deserializer, Serializer};
 use serde_derive::{Deserialize, Serialize};
 use typeshare::typeshare;
 use udev::Device;
@@ -18,6 +25,33 @@ use zbus::zvariant::Type;
 pub const PLATFORM_PROFILE: &str = "/sys/firmware/acpi/platform_profile";
 pub const PLATFORM_PROFILES: &str = "/sys/firmware/acpi/platform_profile_choices";
 
+/// Called with the path and value of every sysfs write made by this crate,
+/// returns false if the write should not reach the hardware
+pub type WriteHook = fn(&Path, &str) -> bool;
+
+static WRITE_HOOK: OnceLock<WriteHook> = OnceLock::new();
+
+/// Install the hook used for hardware write capture, see
+/// `rog_platform::capture`. Can only be set once per process.
+pub fn set_write_hook(hook: WriteHook) {
+    WRITE_HOOK.set(hook).ok();
+}
+
+fn write_allowed(path: &Path, value: &str) -> bool {
+    match WRITE_HOOK.get() {
+        Some(hook) => hook(path, value),
+        None => true,
+    }
+}
+
+/// Set a sysfs attribute of the device, passing it to the write hook first
+pub(crate) fn set_attr(device: &mut Device, attr: &str, value: &str) -> std::io::Result<()> {
+    if write_allowed(&device.syspath().join(attr), value) {
+        device.set_attribute_value(attr, value)?;
+    }
+    Ok(())
+}
+
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn find_fan_curve_node() -> Result<Option<Device>, ProfileError> {
@@ -37,33 +71,157 @@ pub fn find_fan_curve_node() -> Result<Option<Device>, ProfileError> {
     Err(ProfileError::NotSupported)
 }
 
+/// The `platform_profile` choices the kernel may advertise in
+/// `platform_profile_choices`. Only the subset the kernel lists for a laptop is
+/// usable on that laptop, see [`Profile::get_profile_names`]. Choices added to
+/// the kernel after this crate are kept as [`Profile::Other`].
+///
+/// Serialized as the variant name, or for `Other` the kernel name in the same
+/// style (`max-power` as `MaxPower`). An `Other` name is only read back if the
+/// kernel advertises it.
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type), zvariant(signature = "s"))]
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub enum Profile {
     Balanced,
     Performance,
     Quiet,
+    LowPower,
+    Cool,
+    BalancedPerformance,
+    Custom,
+    MaxPower,
+    Other(OtherProfile),
+}
+
+/// The kernel name of a [`Profile`] with no variant of its own. Each name is
+/// stored once for the life of the process so that `Profile` can stay `Copy`.
+/// Only names the kernel advertises are stored, so the set stays small.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct OtherProfile {
+    kernel_name: &'static str,
+    type_name: &'static str,
+}
+
+impl OtherProfile {
+    pub fn kernel_name(&self) -> &'static str {
+        self.kernel_name
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+static OTHER_PROFILES: Mutex<Vec<OtherProfile>> = Mutex::new(Vec::new());
+
+/// `max-power` to `MaxPower`
+fn type_name_of(kernel_name: &str) -> String {
+    kernel_name
+        .split(['-', '_'])
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |c| {
+                c.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect()
+}
+
+/// `MaxPower` to `max-power`, a kernel name is returned lowercased
+fn kernel_name_of(name: &str) -> String {
+    let name = name.trim();
+    if name.contains('-') || !name.chars().any(|c| c.is_ascii_uppercase()) {
+        return name.to_ascii_lowercase();
+    }
+    let mut kernel = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            kernel.push('-');
+        }
+        kernel.push(c.to_ascii_lowercase());
+    }
+    kernel
 }
 
 impl Profile {
+    /// The profiles with a variant, in declaration order
+    pub const KNOWN: [Profile; 8] = [
+        Profile::Balanced,
+        Profile::Performance,
+        Profile::Quiet,
+        Profile::LowPower,
+        Profile::Cool,
+        Profile::BalancedPerformance,
+        Profile::Custom,
+        Profile::MaxPower,
+    ];
+
     pub fn is_platform_profile_supported() -> bool {
         Path::new(PLATFORM_PROFILES).exists()
     }
 
     pub fn get_active_profile() -> Result<Profile, ProfileError> {
         let buf = fs::read_to_string(PLATFORM_PROFILE)?;
-        Ok(buf.as_str().into())
+        Ok(Profile::intern(&buf))
     }
 
+    /// Fetch the profiles advertised by the kernel, in the order the kernel
+    /// lists them. Names not known to this crate are [`Profile::Other`].
     pub fn get_profile_names() -> Result<Vec<Profile>, ProfileError> {
         let buf = fs::read_to_string(PLATFORM_PROFILES)?;
-        Ok(buf.rsplit(' ').map(|p| p.into()).collect())
+        Ok(Self::parse_profile_names(&buf))
+    }
+
+    fn parse_profile_names(buf: &str) -> Vec<Profile> {
+        buf.split_whitespace().map(Profile::intern).collect()
+    }
+
+    /// A name read from the kernel. Never fails, a name with no variant is
+    /// stored as [`Profile::Other`], so this must not be given names from
+    /// anywhere else.
+    fn intern(name: &str) -> Profile {
+        let kernel_name = kernel_name_of(name);
+        if let Some(known) = Self::KNOWN
+            .into_iter()
+            .find(|p| <&str>::from(*p) == kernel_name)
+        {
+            return known;
+        }
+
+        let mut others = OTHER_PROFILES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(other) = others.iter().find(|o| o.kernel_name == kernel_name) {
+            return Profile::Other(*other);
+        }
+        let other = OtherProfile {
+            type_name: Box::leak(type_name_of(&kernel_name).into_boxed_str()),
+            kernel_name: Box::leak(kernel_name.into_boxed_str()),
+        };
+        others.push(other);
+        Profile::Other(other)
+    }
+
+    /// The name of the variant, or for `Other` the kernel name in that style
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Profile::Balanced => "Balanced",
+            Profile::Performance => "Performance",
+            Profile::Quiet => "Quiet",
+            Profile::LowPower => "LowPower",
+            Profile::Cool => "Cool",
+            Profile::BalancedPerformance => "BalancedPerformance",
+            Profile::Custom => "Custom",
+            Profile::MaxPower => "MaxPower",
+            Profile::Other(o) => o.type_name,
+        }
     }
 
     pub fn set_profile(profile: Profile) -> Result<(), ProfileError> {
-        let mut file = OpenOptions::new().write(true).open(PLATFORM_PROFILE)?;
-        file.write_all(<&str>::from(profile).as_bytes())?;
+        let name = <&str>::from(profile);
+        if write_allowed(Path::new(PLATFORM_PROFILE), name) {
+            let mut file = OpenOptions::new().write(true).open(PLATFORM_PROFILE)?;
+            file.write_all(name.as_bytes())?;
+        }
         Ok(())
     }
 
@@ -75,13 +233,19 @@ impl Profile {
         }
     }
 
-    pub fn get_next_profile(current: Profile) -> Profile {
-        // Read first just incase the user has modified the config before calling this
-        match current {
-            Profile::Balanced => Profile::Performance,
-            Profile::Performance => Profile::Quiet,
-            Profile::Quiet => Profile::Balanced,
-        }
+    /// Get the profile following `current` in `choices`, wrapping around at
+    /// the end. If `current` is not in `choices` the first choice is used.
+    ///
+    /// With the kernel ordering of `quiet balanced performance` this gives the
+    /// classic Balanced -> Performance -> Quiet cycle.
+    pub fn get_next_profile(current: Profile, choices: &[Profile]) -> Profile {
+        choices
+            .iter()
+            .position(|p| *p == current)
+            .and_then(|i| choices.get((i + 1) % choices.len()))
+            .or_else(|| choices.first())
+            .copied()
+            .unwrap_or(current)
     }
 }
 
@@ -92,21 +256,18 @@ impl Default for Profile {
 }
 
 impl From<Profile> for &str {
+    /// The name as used by the kernel `platform_profile`
     fn from(profile: Profile) -> &'static str {
         match profile {
             Profile::Balanced => "balanced",
             Profile::Performance => "performance",
             Profile::Quiet => "quiet",
-        }
-    }
-}
-
-impl From<&str> for Profile {
-    fn from(profile: &str) -> Profile {
-        match profile.to_ascii_lowercase().trim() {
-            "performance" => Profile::Performance,
-            "quiet" => Profile::Quiet,
-            _ => Profile::Balanced,
+            Profile::LowPower => "low-power",
+            Profile::Cool => "cool",
+            Profile::BalancedPerformance => "balanced-performance",
+            Profile::Custom => "custom",
+            Profile::MaxPower => "max-power",
+            Profile::Other(o) => o.kernel_name,
         }
     }
 }
@@ -114,19 +275,134 @@ impl From<&str> for Profile {
 impl std::str::FromStr for Profile {
     type Err = ProfileError;
 
+    /// Parse either the kernel name (`low-power`) or the type name
+    /// (`LowPower`), case insensitive. A name with no variant is accepted if
+    /// the kernel advertises it, or it was already seen.
     fn from_str(profile: &str) -> Result<Self, Self::Err> {
-        match profile.to_ascii_lowercase().trim() {
-            "balanced" => Ok(Profile::Balanced),
-            "performance" => Ok(Profile::Performance),
-            "quiet" => Ok(Profile::Quiet),
-            _ => Err(ProfileError::ParseProfileName),
+        let name = profile.trim().to_ascii_lowercase();
+        let flat = |s: &str| s.replace(['-', '_'], "");
+        if let Some(known) = Self::KNOWN
+            .into_iter()
+            .find(|p| flat(<&str>::from(*p)) == flat(&name))
+        {
+            return Ok(known);
         }
+
+        let seen = OTHER_PROFILES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|o| flat(o.kernel_name) == flat(&name))
+            .copied();
+        if let Some(other) = seen {
+            return Ok(Profile::Other(other));
+        }
+        let choices = fs::read_to_string(PLATFORM_PROFILES).unwrap_or_default();
+        choices
+            .split_whitespace()
+            .find(|c| flat(c) == flat(&name))
+            .map(Profile::intern)
+            .ok_or(ProfileError::ParseProfileName)
     }
 }
 
 impl Display for Profile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.type_name())
+    }
+}
+
+impl serde::Serialize for Profile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = Self::KNOWN
+            .iter()
+            .position(|p| p == self)
+            .unwrap_or(Self::KNOWN.len());
+        serializer.serialize_unit_variant("Profile", index as u32, self.type_name())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Profile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        const VARIANTS: &[&str] = &[
+            "Balanced",
+            "Performance",
+            "Quiet",
+            "LowPower",
+            "Cool",
+            "BalancedPerformance",
+            "Custom",
+            "MaxPower",
+        ];
+
+        /// A variant name, read as an identifier so that formats like RON
+        /// accept it unquoted
+        struct Name(Profile);
+
+        impl<'de> serde::Deserialize<'de> for Name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer
+                    .deserialize_identifier(ProfileVisitor)
+                    .map(Name)
+            }
+        }
+
+        struct ProfileVisitor;
+
+        impl<'de> Visitor<'de> for ProfileVisitor {
+            type Value = Profile;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "a platform profile name")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Profile, E> {
+                Profile::from_str(v).map_err(|_| E::unknown_variant(v, VARIANTS))
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Profile, A::Error> {
+                let (name, variant): (Name, _) = data.variant()?;
+                variant.unit_variant()?;
+                Ok(name.0)
+            }
+        }
+
+        deserializer.deserialize_enum("Profile", VARIANTS, ProfileVisitor)
+    }
+}
+
+/// The order `next_profile` moves through profiles in, and the profiles it
+/// should never select.
+#[typeshare]
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct ProfileCycle {
+    /// Cycle order. If empty then the kernel choices order is used
+    pub order: Vec<Profile>,
+    /// Profiles to pass over when cycling
+    pub skip: Vec<Profile>,
+}
+
+impl ProfileCycle {
+    /// The profiles this cycle will move through, limited to the available
+    /// `choices`
+    pub fn profiles(&self, choices: &[Profile]) -> Vec<Profile> {
+        let order = if self.order.is_empty() {
+            choices
+        } else {
+            &self.order
+        };
+        order
+            .iter()
+            .filter(|p| choices.contains(p) && !self.skip.contains(p))
+            .copied()
+            .collect()
+    }
+
+    /// Get the profile after `current` in this cycle. If `current` is not part
+    /// of the cycle (such as when skipped) then the first profile is used.
+    pub fn next_profile(&self, current: Profile, choices: &[Profile]) -> Profile {
+        Profile::get_next_profile(current, &self.profiles(choices))
     }
 }
 
@@ -197,14 +473,14 @@ impl Default for FanCurvePU {
     }
 }
 
-/// Main purpose of `FanCurves` is to enable restoring state on system boot
+/// Main purpose of `FanCurves` is to enable restoring state on system boot.
+/// Curves are stored per `Profile`, only profiles which have had their curves
+/// read from the device will have an entry.
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type))]
 #[derive(Deserialize, Serialize, Debug, Default)]
 pub struct FanCurveProfiles {
-    pub balanced: Vec<CurveData>,
-    pub performance: Vec<CurveData>,
-    pub quiet: Vec<CurveData>,
+    pub curves: BTreeMap<Profile, Vec<CurveData>>,
 }
 
 impl FanCurveProfiles {
@@ -250,11 +526,7 @@ impl FanCurveProfiles {
             curves.push(curve);
         }
 
-        match profile {
-            Profile::Balanced => self.balanced = curves,
-            Profile::Performance => self.performance = curves,
-            Profile::Quiet => self.quiet = curves,
-        }
+        self.curves.insert(profile, curves);
         Ok(())
     }
 
@@ -273,7 +545,7 @@ impl FanCurveProfiles {
         for fan in fans {
             let pwm_num: char = fan.into();
             let pwm = format!("pwm{pwm_num}_enable");
-            device.set_attribute_value(&pwm, "3")?;
+            set_attr(device, &pwm, "3")?;
         }
         self.read_from_dev_profile(profile, device)?;
         Ok(())
@@ -288,34 +560,19 @@ impl FanCurveProfiles {
         profile: Profile,
         device: &mut Device,
     ) -> std::io::Result<()> {
-        let fans = match profile {
-            Profile::Balanced => &mut self.balanced,
-            Profile::Performance => &mut self.performance,
-            Profile::Quiet => &mut self.quiet,
-        };
-        for fan in fans {
-            debug!("write_profile_curve_to_platform: writing profile:{profile}, {fan:?}");
-            fan.write_to_device(device)?;
+        if let Some(fans) = self.curves.get_mut(&profile) {
+            for fan in fans {
+                debug!("write_profile_curve_to_platform: writing profile:{profile}, {fan:?}");
+                fan.write_to_device(device)?;
+            }
         }
         Ok(())
     }
 
     pub fn set_profile_curves_enabled(&mut self, profile: Profile, enabled: bool) {
-        match profile {
-            Profile::Balanced => {
-                for curve in self.balanced.iter_mut() {
-                    curve.enabled = enabled;
-                }
-            }
-            Profile::Performance => {
-                for curve in self.performance.iter_mut() {
-                    curve.enabled = enabled;
-                }
-            }
-            Profile::Quiet => {
-                for curve in self.quiet.iter_mut() {
-                    curve.enabled = enabled;
-                }
+        if let Some(curves) = self.curves.get_mut(&profile) {
+            for curve in curves.iter_mut() {
+                curve.enabled = enabled;
             }
         }
     }
@@ -326,96 +583,160 @@ impl FanCurveProfiles {
         fan: FanCurvePU,
         enabled: bool,
     ) {
-        match profile {
-            Profile::Balanced => {
-                for curve in self.balanced.iter_mut() {
-                    if curve.fan == fan {
-                        curve.enabled = enabled;
-                        break;
-                    }
-                }
-            }
-            Profile::Performance => {
-                for curve in self.performance.iter_mut() {
-                    if curve.fan == fan {
-                        curve.enabled = enabled;
-                        break;
-                    }
-                }
-            }
-            Profile::Quiet => {
-                for curve in self.quiet.iter_mut() {
-                    if curve.fan == fan {
-                        curve.enabled = enabled;
-                        break;
-                    }
+        if let Some(curves) = self.curves.get_mut(&profile) {
+            for curve in curves.iter_mut() {
+                if curve.fan == fan {
+                    curve.enabled = enabled;
+                    break;
                 }
             }
         }
     }
 
     pub fn get_fan_curves_for(&self, name: Profile) -> &[CurveData] {
-        match name {
-            Profile::Balanced => &self.balanced,
-            Profile::Performance => &self.performance,
-            Profile::Quiet => &self.quiet,
-        }
+        self.curves.get(&name).map_or(&[], |c| c.as_slice())
     }
 
     pub fn get_fan_curve_for(&self, name: &Profile, pu: FanCurvePU) -> Option<&CurveData> {
-        match name {
-            Profile::Balanced => {
-                for this_curve in self.balanced.iter() {
-                    if this_curve.fan == pu {
-                        return Some(this_curve);
-                    }
-                }
-            }
-            Profile::Performance => {
-                for this_curve in self.performance.iter() {
-                    if this_curve.fan == pu {
-                        return Some(this_curve);
-                    }
-                }
-            }
-            Profile::Quiet => {
-                for this_curve in self.quiet.iter() {
-                    if this_curve.fan == pu {
-                        return Some(this_curve);
-                    }
-                }
-            }
-        }
-        None
+        self.get_fan_curves_for(*name)
+            .iter()
+            .find(|this_curve| this_curve.fan == pu)
     }
 
     pub fn save_fan_curve(&mut self, curve: CurveData, profile: Profile) -> std::io::Result<()> {
-        match profile {
-            Profile::Balanced => {
-                for this_curve in self.balanced.iter_mut() {
-                    if this_curve.fan == curve.fan {
-                        *this_curve = curve;
-                        break;
-                    }
-                }
-            }
-            Profile::Performance => {
-                for this_curve in self.performance.iter_mut() {
-                    if this_curve.fan == curve.fan {
-                        *this_curve = curve;
-                        break;
-                    }
-                }
-            }
-            Profile::Quiet => {
-                for this_curve in self.quiet.iter_mut() {
-                    if this_curve.fan == curve.fan {
-                        *this_curve = curve;
-                        break;
-                    }
+        if let Some(curves) = self.curves.get_mut(&profile) {
+            for this_curve in curves.iter_mut() {
+                if this_curve.fan == curve.fan {
+                    *this_curve = curve;
+                    break;
                 }
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_from_kernel_names() {
+        assert_eq!(Profile::from_str("low-power\n").unwrap(), Profile::LowPower);
+        assert_eq!(
+            Profile::from_str("balanced-performance").unwrap(),
+            Profile::BalancedPerformance
+        );
+        assert_eq!(Profile::from_str("Quiet").unwrap(), Profile::Quiet);
+        assert_eq!(Profile::from_str("max-power").unwrap(), Profile::MaxPower);
+        assert!(Profile::from_str("never-advertised").is_err());
+
+        for p in [
+            Profile::Balanced,
+            Profile::Performance,
+            Profile::Quiet,
+            Profile::LowPower,
+            Profile::Cool,
+            Profile::BalancedPerformance,
+            Profile::Custom,
+            Profile::MaxPower,
+        ] {
+            assert_eq!(Profile::from_str(<&str>::from(p)).unwrap(), p);
+        }
+    }
+
+    #[test]
+    fn profile_choices_keep_unknown() {
+        let choices = Profile::parse_profile_names("low-power turbo balanced max-power\n");
+        assert_eq!(choices.len(), 4);
+        assert_eq!(choices[0], Profile::LowPower);
+        assert_eq!(choices[2], Profile::Balanced);
+        assert_eq!(choices[3], Profile::MaxPower);
+
+        let turbo = match choices[1] {
+            Profile::Other(turbo) => turbo,
+            p => panic!("turbo should be Profile::Other, got {p:?}"),
+        };
+        assert_eq!(turbo.kernel_name(), "turbo");
+        assert_eq!(<&str>::from(choices[1]), "turbo");
+        assert_eq!(Profile::intern("turbo"), choices[1]);
+        // Seen once, so it can now be parsed by name
+        assert_eq!(Profile::from_str("Turbo").unwrap(), choices[1]);
+    }
+
+    #[test]
+    fn profile_serde_round_trip() {
+        let extra = Profile::parse_profile_names("extra-quiet")[0];
+        assert_eq!(extra.type_name(), "ExtraQuiet");
+
+        for p in [Profile::LowPower, Profile::MaxPower, extra] {
+            let ron = ron::to_string(&p).unwrap();
+            assert_eq!(ron, p.type_name());
+            assert_eq!(ron::from_str::<Profile>(&ron).unwrap(), p);
+
+            let json = serde_json::to_string(&p).unwrap();
+            assert_eq!(json, format!("\"{}\"", p.type_name()));
+            assert_eq!(serde_json::from_str::<Profile>(&json).unwrap(), p);
+        }
+
+        // A profile this laptop doesn't advertise is rejected, rather than
+        // stored for any name sent
+        assert!(ron::from_str::<Profile>("SuperSilent").is_err());
+        assert!(serde_json::from_str::<Profile>("\"super-silent\"").is_err());
+    }
+
+    #[test]
+    fn profile_cycle_order_and_skip() {
+        let choices = [Profile::Quiet, Profile::Balanced, Profile::Performance];
+        let cycle = ProfileCycle {
+            order: vec![Profile::Performance, Profile::LowPower, Profile::Balanced],
+            skip: vec![],
+        };
+        assert_eq!(
+            cycle.profiles(&choices),
+            [Profile::Performance, Profile::Balanced]
+        );
+        assert_eq!(
+            cycle.next_profile(Profile::Balanced, &choices),
+            Profile::Performance
+        );
+
+        let cycle = ProfileCycle {
+            order: vec![],
+            skip: vec![Profile::Performance],
+        };
+        assert_eq!(
+            cycle.next_profile(Profile::Balanced, &choices),
+            Profile::Quiet
+        );
+        assert_eq!(
+            cycle.next_profile(Profile::Performance, &choices),
+            Profile::Quiet
+        );
+    }
+
+    #[test]
+    fn next_profile_cycles_choices() {
+        let choices = [Profile::Quiet, Profile::Balanced, Profile::Performance];
+        assert_eq!(
+            Profile::get_next_profile(Profile::Balanced, &choices),
+            Profile::Performance
+        );
+        assert_eq!(
+            Profile::get_next_profile(Profile::Performance, &choices),
+            Profile::Quiet
+        );
+        assert_eq!(
+            Profile::get_next_profile(Profile::Quiet, &choices),
+            Profile::Balanced
+        );
+        assert_eq!(
+            Profile::get_next_profile(Profile::LowPower, &choices),
+            Profile::Quiet
+        );
+        assert_eq!(
+            Profile::get_next_profile(Profile::LowPower, &[]),
+            Profile::LowPower
+        );
+    }
+}
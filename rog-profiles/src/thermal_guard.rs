@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_derive::{Deserialize, Serialize};
+use typeshare::typeshare;
+#[cfg(feature = "dbus")]
+use zbus::zvariant::Type;
+
+use crate::error::ProfileError;
+use crate::Profile;
+
+const HWMON_PATH: &str = "/sys/class/hwmon";
+
+/// Escalate to another profile when a temperature stays high for a while. A
+/// guard is configured for the profile it watches over.
+#[typeshare]
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct ThermalGuard {
+    /// The hwmon `name` to read temperatures from, such as `k10temp` or
+    /// `coretemp`. If empty then the hottest of all hwmon sensors is used.
+    pub sensor: String,
+    /// Temperature in degrees celsius that must be exceeded
+    pub threshold: u8,
+    /// Seconds the temperature must stay over `threshold` before escalating
+    pub window: u32,
+    /// Degrees below `threshold` the temperature must fall to before
+    /// switching back
+    pub hysteresis: u8,
+    /// Seconds the temperature must stay below `threshold - hysteresis`
+    /// before switching back
+    pub hysteresis_window: u32,
+    /// The profile to switch to
+    pub escalate_to: Profile,
+    /// Also enable the fan curves of `escalate_to` while escalated
+    pub fan_curves: bool,
+}
+
+impl Default for ThermalGuard {
+    fn default() -> Self {
+        Self {
+            sensor: String::new(),
+            threshold: 90,
+            window: 30,
+            hysteresis: 10,
+            hysteresis_window: 60,
+            escalate_to: Profile::Performance,
+            fan_curves: false,
+        }
+    }
+}
+
+impl ThermalGuard {
+    /// Read the highest `temp*_input` of the hwmon devices matching `sensor`,
+    /// in degrees celsius
+    pub fn read_temperature(&self) -> Result<u8, ProfileError> {
+        read_hwmon_temp(Path::new(HWMON_PATH), &self.sensor)
+    }
+}
+
+fn read_hwmon_temp(base: &Path, sensor: &str) -> Result<u8, ProfileError> {
+    let mut max: Option<i64> = None;
+    for hwmon in fs::read_dir(base)
+        .map_err(|e| ProfileError::Path(base.to_string_lossy().to_string(), e))?
+        .flatten()
+    {
+        let path = hwmon.path();
+        if !sensor.is_empty() {
+            let name = fs::read_to_string(path.join("name")).unwrap_or_default();
+            if name.trim() != sensor {
+                continue;
+            }
+        }
+        for attr in fs::read_dir(&path).into_iter().flatten().flatten() {
+            let file = attr.file_name();
+            let file = file.to_string_lossy();
+            if !(file.starts_with("temp") && file.ends_with("_input")) {
+                continue;
+            }
+            if let Some(temp) = fs::read_to_string(attr.path())
+                .ok()
+                .and_then(|s| s.trim().parse::<i64>().ok())
+            {
+                max = Some(max.map_or(temp, |m| m.max(temp)));
+            }
+        }
+    }
+    max.map(|t| (t / 1000).clamp(0, u8::MAX as i64) as u8)
+        .ok_or_else(|| ProfileError::NotFound(format!("hwmon temperature for '{sensor}'")))
+}
+
+/// Emitted when a guard escalates or returns from escalation
+#[typeshare]
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ThermalGuardEvent {
+    /// True if switching to `to` because of temperature, false if returning
+    pub escalated: bool,
+    pub from: Profile,
+    pub to: Profile,
+    /// The temperature that caused the switch, in degrees celsius
+    pub temperature: u8,
+    /// The guard threshold, in degrees celsius
+    pub threshold: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ThermalGuardAction {
+    Escalate,
+    Restore,
+}
+
+/// Tracks how long a temperature has been over or under the limits of a
+/// `ThermalGuard`
+#[derive(Debug, Default)]
+pub struct ThermalGuardTracker {
+    since: Option<Instant>,
+    escalated: bool,
+}
+
+impl ThermalGuardTracker {
+    pub fn is_escalated(&self) -> bool {
+        self.escalated
+    }
+
+    /// Drop any escalation and timing state
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Feed a new temperature reading, returns the action to take if a window
+    /// has elapsed
+    pub fn update(
+        &mut self,
+        guard: &ThermalGuard,
+        temperature: u8,
+        now: Instant,
+    ) -> Option<ThermalGuardAction> {
+        let (outside, window, action) = if self.escalated {
+            (
+                temperature <= guard.threshold.saturating_sub(guard.hysteresis),
+                guard.hysteresis_window,
+                ThermalGuardAction::Restore,
+            )
+        } else {
+            (
+                temperature > guard.threshold,
+                guard.window,
+                ThermalGuardAction::Escalate,
+            )
+        };
+
+        if !outside {
+            self.since = None;
+            return None;
+        }
+        let since = *self.since.get_or_insert(now);
+        if now.duration_since(since) >= Duration::from_secs(window as u64) {
+            self.since = None;
+            self.escalated = !self.escalated;
+            return Some(action);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ThermalGuard, ThermalGuardAction, ThermalGuardTracker};
+
+    #[test]
+    fn guard_escalates_and_restores_with_hysteresis() {
+        let guard = ThermalGuard {
+            threshold: 90,
+            window: 10,
+            hysteresis: 10,
+            hysteresis_window: 20,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        let mut tracker = ThermalGuardTracker::default();
+
+        assert_eq!(tracker.update(&guard, 95, at(0)), None);
+        assert_eq!(tracker.update(&guard, 95, at(5)), None);
+        // Dipping under the threshold restarts the window
+        assert_eq!(tracker.update(&guard, 88, at(6)), None);
+        assert_eq!(tracker.update(&guard, 95, at(7)), None);
+        assert_eq!(
+            tracker.update(&guard, 95, at(17)),
+            Some(ThermalGuardAction::Escalate)
+        );
+        assert!(tracker.is_escalated());
+
+        // Under the threshold but within the hysteresis
+        assert_eq!(tracker.update(&guard, 85, at(20)), None);
+        assert_eq!(tracker.update(&guard, 85, at(60)), None);
+        assert_eq!(tracker.update(&guard, 80, at(61)), None);
+        assert_eq!(
+            tracker.update(&guard, 75, at(81)),
+            Some(ThermalGuardAction::Restore)
+        );
+        assert!(!tracker.is_escalated());
+    }
+}